@@ -3,64 +3,136 @@ extern crate time;
 
 mod unit_test_config;
 
-use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP, ModbusRTU};
+use libmodbus_rs::{Error, Modbus, ModbusClient, ModbusConnection, ModbusTCP, ModbusRTU, PipelinedTcpClient, Request};
 use std::env;
-use std::io::Error;
 use std::mem::size_of;
 use std::process::exit;
 use time::PreciseTime;
-use unit_test_config::*;
+use crate::unit_test_config::*;
 
 
 const G_MSEC_PER_SEC: i64 = 1_000;
 
-fn run() -> Result<(), std::io::Error> {
+/// The measured operations, each with the number of points it transfers
+#[derive(Clone, Copy)]
+enum Benchmark {
+    ReadBits(u16),
+    ReadRegisters(u16),
+    WriteAndReadRegisters(u16),
+}
+
+impl Benchmark {
+    /// The request sent by the pipelined client
+    fn request(self) -> Request {
+        match self {
+            Benchmark::ReadBits(count) => Request::ReadCoils { address: 0, count },
+            Benchmark::ReadRegisters(count) => Request::ReadHoldingRegisters { address: 0, count },
+            Benchmark::WriteAndReadRegisters(count) => Request::WriteAndReadRegisters {
+                read_address: 0,
+                read_count: count,
+                write_address: 0,
+                values: vec![0; count as usize],
+            },
+        }
+    }
+}
+
+/// The libmodbus context, or a TCP connection with several requests in flight
+enum Client {
+    Blocking(ModbusConnection),
+    Pipelined(PipelinedTcpClient, usize),
+}
+
+impl Client {
+    /// Runs `benchmark` `n_loop` times
+    fn repeat(&mut self, n_loop: i64, benchmark: Benchmark) -> Result<(), Error> {
+        match *self {
+            Client::Blocking(ref modbus) => {
+                let mut tab_bit = vec![0u8; Modbus::MAX_READ_BITS as usize];
+                let mut tab_reg = vec![0u16; Modbus::MAX_READ_REGISTERS as usize];
+                let values = vec![0u16; Modbus::MAX_WR_WRITE_REGISTERS as usize];
+                for _ in 0..n_loop {
+                    match benchmark {
+                        Benchmark::ReadBits(count) => {
+                            modbus.read_bits(0, count, &mut tab_bit)?;
+                        },
+                        Benchmark::ReadRegisters(count) => {
+                            modbus.read_registers(0, count, &mut tab_reg)?;
+                        },
+                        Benchmark::WriteAndReadRegisters(count) => {
+                            modbus.write_and_read_registers(0, count, &values, 0, count, &mut tab_reg)?;
+                        },
+                    }
+                }
+            },
+            Client::Pipelined(ref mut client, in_flight) => {
+                let request = benchmark.request();
+                let mut sent = 0;
+                for _ in 0..n_loop {
+                    // keep the pipeline filled
+                    while sent < n_loop && client.in_flight() < in_flight {
+                        client.send(request.clone())?;
+                        sent += 1;
+                    }
+                    if let Some(confirmation) = client.receive()? {
+                        confirmation.result?;
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+fn run() -> Result<(), Error> {
     let args: Vec<_> = env::args().collect();
     let (backend, n_loop) = if args.len() > 1 {
         match args[1].to_lowercase().as_ref() {
             "tcp" => (Backend::TCP, 100_000),
             "rtu" => (Backend::RTU, 100),
             _ => {
-                println!("Usage:\n  {} [tcp|rtu] - Modbus client to measure data bandwith\n\n", args[0]);
+                println!("Usage:\n  {} [tcp [requests in flight]|rtu] - Modbus client to measure data bandwith\n\n",
+                         args[0]);
                 std::process::exit(-1);
             }
         }
     } else {
         (Backend::TCP, 100_000)
     };
-
-    let mut modbus;
-    if backend == Backend::TCP {
-        modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("Could not create TCP context");
+    // more than one request in flight uses the pipelined TCP client instead of libmodbus
+    let in_flight = args.get(2).and_then(|arg| arg.parse().ok()).unwrap_or(1usize);
+
+    let mut client = if backend == Backend::TCP && in_flight > 1 {
+        match PipelinedTcpClient::connect("127.0.0.1:1502") {
+            Err(err) => {
+                println!("Connection failed: {}", err);
+                exit(-1)
+            }
+            Ok(client) => Client::Pipelined(client, in_flight),
+        }
     } else {
-        modbus = Modbus::new_rtu("/dev/ttyUSB1", 115200, 'N', 8, 1).expect("Could not create RTU context");
-        modbus.set_slave(1).expect("Could not set slave id");
-    }
-
-    match modbus.connect() {
-        Err(_) => {
-            println!("Connection failed: {}", Modbus::strerror(Error::last_os_error().raw_os_error().unwrap()));
-            exit(-1)
+        let mut modbus;
+        if backend == Backend::TCP {
+            modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("Could not create TCP context");
+        } else {
+            modbus = Modbus::new_rtu("/dev/ttyUSB1", 115200, 'N', 8, 1).expect("Could not create RTU context");
+            modbus.set_slave(1).expect("Could not set slave id");
         }
-        _ => {}
-    }
 
-    /* Allocate and initialize the memory to store the status */
-    let mut tab_bit = vec![0u8; Modbus::MAX_READ_BITS as usize * size_of::<u8>()];
-
-    /* Allocate and initialize the memory to store the registers */
-    let mut tab_reg = vec![0u16; Modbus::MAX_READ_REGISTERS as usize * size_of::<u16>()];
+        match modbus.connect() {
+            Err(err) => {
+                println!("Connection failed: {}", err);
+                exit(-1)
+            }
+            Ok(modbus) => Client::Blocking(modbus),
+        }
+    };
 
     println!("READ BITS\n");
 
     let nb_points = Modbus::MAX_READ_BITS as u16;
     let start = PreciseTime::now();
-    for _ in 0..n_loop {
-        let rc = modbus.read_bits(0, nb_points, &mut tab_bit);
-        if rc.is_err() {
-            return Err(Error::last_os_error());
-        }
-    }
+    client.repeat(n_loop, Benchmark::ReadBits(nb_points))?;
     let end = PreciseTime::now();
     let elapsed = start.to(end).num_milliseconds();
 
@@ -91,12 +163,7 @@ fn run() -> Result<(), std::io::Error> {
 
     let nb_points = Modbus::MAX_READ_REGISTERS as u16;
     let start = PreciseTime::now();
-    for _ in 0..n_loop {
-        let rc = modbus.read_registers(0, nb_points, &mut tab_reg);
-        if rc.is_err() {
-            return Err(Error::last_os_error());
-        }
-    }
+    client.repeat(n_loop, Benchmark::ReadRegisters(nb_points))?;
     let end = PreciseTime::now();
     let elapsed = start.to(end).num_milliseconds();
 
@@ -127,13 +194,7 @@ fn run() -> Result<(), std::io::Error> {
 
     let nb_points = Modbus::MAX_WR_WRITE_REGISTERS as u16;
     let start = PreciseTime::now();
-    for _ in 0..n_loop {
-        let rc = modbus.write_and_read_registers(0, nb_points, &tab_reg.clone(), // FIXME: this clone costs to much
-                                                 0, nb_points, &mut tab_reg);
-        if rc.is_err() {
-            return Err(Error::last_os_error());
-        }
-    }
+    client.repeat(n_loop, Benchmark::WriteAndReadRegisters(nb_points))?;
     let end = PreciseTime::now();
     let elapsed = start.to(end).num_milliseconds();
 
@@ -169,7 +230,7 @@ fn run() -> Result<(), std::io::Error> {
 
 fn main() {
     if let Err(ref err) = run() {
-        println!("{}", Modbus::strerror(err.raw_os_error().unwrap()));
+        println!("{}", err);
 
         std::process::exit(1)
     }
@@ -1,27 +1,49 @@
+extern crate libc;
 extern crate libmodbus_rs;
 
 mod unit_test_config;
 
-use libmodbus_rs::{Modbus, ModbusMapping, ModbusTCP};
+use libmodbus_rs::{Modbus, ModbusMapping, ModbusTCP, ShutdownHandle, TcpServer};
+use std::sync::OnceLock;
 
 
 const NB_CONNECTION: i32 = 5;
 
-fn run() -> Result<(), std::io::Error> {
-    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("Could not create modbus TCP context");
+static SHUTDOWN: OnceLock<ShutdownHandle> = OnceLock::new();
 
-    let _mb_mapping = ModbusMapping::new(Modbus::MAX_READ_BITS, 0,
+extern "C" fn stop_server(_signal: libc::c_int) {
+    // only an atomic store, safe in a signal handler
+    if let Some(handle) = SHUTDOWN.get() {
+        handle.shutdown();
+    }
+}
+
+fn run() -> Result<(), libmodbus_rs::Error> {
+    let modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("Could not create modbus TCP context");
+
+    let mb_mapping = ModbusMapping::new(Modbus::MAX_READ_BITS, 0,
                                             Modbus::MAX_READ_REGISTERS, 0).expect("Failed to allocate the mapping");
 
-    let _server_socket = modbus.tcp_listen(NB_CONNECTION).expect("Unable to listen TCP connection");
+    let listener = modbus.tcp_listen(NB_CONNECTION).expect("Unable to listen TCP connection");
+
+    // serves all connected clients until Ctrl-C or SIGTERM
+    let mut server = TcpServer::new(listener);
+    let _ = SHUTDOWN.set(server.shutdown_handle());
+    unsafe {
+        libc::signal(libc::SIGINT, stop_server as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, stop_server as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
 
+    server.serve(&mb_mapping)?;
+    server.close();
+    println!("Server stopped");
 
     Ok(())
 }
 
 fn main() {
     if let Err(ref err) = run() {
-        println!("{}", Modbus::strerror(err.raw_os_error().unwrap()));
+        println!("Server error: {}", err);
 
         std::process::exit(1);
     }
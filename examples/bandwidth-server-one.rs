@@ -4,7 +4,7 @@ mod unit_test_config;
 
 use libmodbus_rs::{Modbus, ModbusMapping, ModbusServer, ModbusTCP, ModbusRTU};
 use std::env;
-use unit_test_config::*;
+use crate::unit_test_config::*;
 
 
 fn run() -> Result<(), std::io::Error> {
@@ -22,17 +22,15 @@ fn run() -> Result<(), std::io::Error> {
         Backend::TCP
     };
 
-    let mut modbus;
-    let mut socket;
-    if backend == Backend::TCP {
-        modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("Could not create TCP context");
-        socket = modbus.tcp_listen(1).expect("Could not listen to TCP socket");
-        modbus.tcp_accept(&mut socket).unwrap();
+    let modbus = if backend == Backend::TCP {
+        let modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("Could not create TCP context");
+        let listener = modbus.tcp_listen(1).expect("Could not listen to TCP socket");
+        listener.tcp_accept().unwrap()
     } else {
-        modbus = Modbus::new_rtu("/dev/ttyUSB0", 115200, 'N', 8, 1).expect("Could not create RTU context");
+        let mut modbus = Modbus::new_rtu("/dev/ttyUSB0", 115200, 'N', 8, 1).expect("Could not create RTU context");
         modbus.set_slave(1).unwrap();
-        modbus.connect().unwrap();
-    }
+        modbus.connect().unwrap()
+    };
 
     let mb_mapping = ModbusMapping::new(Modbus::MAX_READ_BITS, 0,
                                         Modbus::MAX_READ_REGISTERS, 0).expect("Failed to allocate the mapping");
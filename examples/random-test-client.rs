@@ -42,7 +42,7 @@ fn run() -> Result<(), String> {
     let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("could not create TCP context");
 
     modbus.set_debug(true).expect("could not set DEBUG mode");
-    modbus.connect().expect("could not coonect");
+    let modbus = modbus.connect().expect("could not coonect");
 
     let mut num_bit = ADDRESS_END - ADDRESS_START;
 
@@ -4,13 +4,13 @@ use libmodbus_rs::{Modbus, ModbusServer, ModbusMapping, ModbusTCP};
 
 
 fn run() -> Result<(), String> {
-    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("could not create modbus TCP context");
+    let modbus = Modbus::new_tcp("127.0.0.1", 1502).expect("could not create modbus TCP context");
     // modbus.set_debug(true).expect("could not set DEBUG mode");
 
     let modbus_mapping = ModbusMapping::new(500, 500, 500, 500).expect("could not create Modbus Mapping");
 
-    let mut socket = modbus.tcp_listen(1).expect("could not listen");
-    modbus.tcp_accept(&mut socket).expect("could not create socket");
+    let listener = modbus.tcp_listen(1).expect("could not listen");
+    let modbus = listener.tcp_accept().expect("could not create socket");
 
 
     loop {
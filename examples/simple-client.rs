@@ -42,7 +42,7 @@ fn run(matches: &ArgMatches) -> Result<(), Error> {
     }
 
     modbus.set_debug(true)?;
-    modbus.connect()?;
+    let _modbus = modbus.connect().map_err(|err| err.into_error())?;
 
     // Work HERE
 
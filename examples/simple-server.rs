@@ -37,17 +37,17 @@ fn run(matches: &ArgMatches) -> Result<(), Error> {
         },
         Backend::TCP => {
             let mut _query = vec![0u8; Modbus::TCP_MAX_ADU_LENGTH as usize];
-            let mut modbus = Modbus::new_tcp("127.0.0.1", 1502)?;
+            let modbus = Modbus::new_tcp("127.0.0.1", 1502)?;
 
-            let mut socket = modbus.tcp_listen(1)?;
-            modbus.tcp_accept(&mut socket)?;
+            let listener = modbus.tcp_listen(1)?;
+            let _modbus = listener.tcp_accept().map_err(|err| err.into_error())?;
         },
         Backend::TCPPI => {
             let mut _query = vec![0u8; Modbus::TCP_MAX_ADU_LENGTH as usize];
-            let mut modbus = Modbus::new_tcp_pi("::0", "1502")?;
+            let modbus = Modbus::new_tcp_pi("::0", "1502")?;
 
-            let mut socket = modbus.tcp_listen(1)?;
-            modbus.tcp_accept(&mut socket)?;
+            let listener = modbus.tcp_listen(1)?;
+            let _modbus = listener.tcp_accept().map_err(|err| err.into_error())?;
         },
     }
 
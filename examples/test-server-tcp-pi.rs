@@ -7,9 +7,9 @@ use libmodbus_rs::{Modbus, ModbusMapping, ModbusServer, ModbusTCPPI};
 
 
 fn run() -> Result<(), Error> {
-    let mut modbus = Modbus::new_tcp_pi("::0", "1502")?;
-    let mut socket = modbus.tcp_pi_listen(1)?;
-    modbus.tcp_pi_accept(&mut socket)?;
+    let modbus = Modbus::new_tcp_pi("::0", "1502")?;
+    let listener = modbus.tcp_pi_listen(1)?;
+    let modbus = listener.tcp_pi_accept().map_err(|err| err.into_error())?;
 
     let modbus_mapping = ModbusMapping::new(500, 500, 500, 500)?;
     let mut query = vec![0u8; Modbus::MAX_ADU_LENGTH as usize];
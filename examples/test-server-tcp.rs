@@ -10,9 +10,9 @@ use libmodbus_rs::{Modbus, ModbusMapping, ModbusServer, ModbusTCP};
 
 
 fn run() -> Result<(), Error> {
-    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502)?;
-    let mut socket = modbus.tcp_listen(1)?;
-    modbus.tcp_accept(&mut socket)?;
+    let modbus = Modbus::new_tcp("127.0.0.1", 1502)?;
+    let listener = modbus.tcp_listen(1)?;
+    let modbus = listener.tcp_accept().map_err(|err| err.into_error())?;
 
     let modbus_mapping = ModbusMapping::new(500, 500, 500, 500)?;
     let mut query = vec![0u8; Modbus::MAX_ADU_LENGTH as usize];
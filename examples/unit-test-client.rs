@@ -5,10 +5,10 @@ mod unit_test_config;
 
 use failure::Error;
 use libmodbus_rs::prelude::*;
-use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP, ModbusTCPPI, ModbusRTU,
+use libmodbus_rs::{Modbus, ModbusClient, ModbusConnection, ModbusTCP, ModbusTCPPI, ModbusRTU,
                    Exception, FunctionCode, Timeout, ErrorRecoveryMode};
 use std::env;
-use unit_test_config::*;
+use crate::unit_test_config::*;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -80,10 +80,10 @@ fn run() -> Result<(), Error> {
 
     let old_response_timeout = modbus.get_response_timeout()
         .expect("could not get response timeout");
-    match modbus.connect() {
+    let mut modbus = match modbus.connect() {
         Err(err) => panic!("Connection failed: {}", err),
-        Ok(_) => {}
-    }
+        Ok(modbus) => modbus,
+    };
     let new_response_timeout = modbus.get_response_timeout()
         .expect("could not get response timeout");
 
@@ -427,7 +427,7 @@ fn run() -> Result<(), Error> {
         let mut raw_invalid_req = vec![INVALID_SERVER_ID, 0x03, 0x00, 0x01, 0xFF, 0xFF];
         const RAW_RSP_LENGTH: usize = 7;
         let mut raw_rsp = vec![INVALID_SERVER_ID, 0x03, 0x04, 0, 0, 0, 0];
-        let mut rsp = vec![0; Modbus::RTU_MAX_ADU_LENGTH];
+        let mut rsp = vec![0; Modbus::MAX_ADU_LENGTH];
 
         /* No response in RTU mode */
         print!("1-A/3 No response from slave {}: ", INVALID_SERVER_ID);
@@ -626,7 +626,7 @@ fn run() -> Result<(), Error> {
         std::process::exit(-1)
     }
 
-    modbus.close();
+    let modbus = modbus.close();
     modbus.free();
 
     /* Test init functions */
@@ -646,7 +646,7 @@ fn run() -> Result<(), Error> {
     if success { Ok(()) } else { Err(format_err!("Unit test client failure")) }
 }
 
-fn test_server(modbus: &mut Modbus, backend: Backend) -> Result<(), Error> {
+fn test_server(modbus: &mut ModbusConnection, backend: Backend) -> Result<(), Error> {
     /* Read requests */
     const READ_RAW_REQ_LEN: usize = 6;
     let slave = match backend {
@@ -789,7 +789,7 @@ fn test_server(modbus: &mut Modbus, backend: Backend) -> Result<(), Error> {
     Ok(())
 }
 
-fn send_crafted_request(modbus: &mut Modbus, function: FunctionCode,
+fn send_crafted_request(modbus: &mut ModbusConnection, function: FunctionCode,
     mut req: &mut [u8], req_len: usize,
     max_value: u16, bytes: u16,
     backend_length: u16, backend_offset: u16) -> Result<(), Error>
@@ -8,13 +8,16 @@ extern crate libmodbus_rs;
 mod unit_test_config;
 
 use failure::Error;
-use libmodbus_rs::{Modbus, ModbusMapping, ModbusClient, ModbusServer, ModbusTCP, ModbusTCPPI, ModbusRTU};
+use libmodbus_rs::{Indication, Modbus, ModbusMapping, ModbusClient, ModbusServer, ModbusTCP, ModbusTCPPI, ModbusRTU,
+                   Request};
+use libmodbus_rs::Error as ModbusError;
 use libmodbus_rs::Exception;
+use libmodbus_rs::frame::tcp;
 use libmodbus_rs::prelude::*;
 use std::env;
 use std::thread::sleep;
 use std::time::Duration;
-use unit_test_config::*;
+use crate::unit_test_config::*;
 
 
 fn run() -> Result<(), Error> {
@@ -74,30 +77,34 @@ fn run() -> Result<(), Error> {
        Only the read-only input values are assigned. */
 
     /* Initialize input values that's can be only done server side. */
-    set_bits_from_bytes(modbus_mapping.get_input_bits_mut(), 0, UT_INPUT_BITS_NB,
-                        &UT_INPUT_BITS_TAB);
+    {
+        let mut mapping = modbus_mapping.lock();
 
-    /* Initialize values of INPUT REGISTERS */
-    for i in 0..UT_INPUT_REGISTERS_NB {
-        modbus_mapping.get_input_registers_mut()[i as usize] = UT_INPUT_REGISTERS_TAB[i as usize];
+        set_bits_from_bytes(mapping.get_input_bits_mut(), 0, UT_INPUT_BITS_NB,
+                            &UT_INPUT_BITS_TAB);
+
+        /* Initialize values of INPUT REGISTERS */
+        for i in 0..UT_INPUT_REGISTERS_NB {
+            mapping.get_input_registers_mut()[i as usize] = UT_INPUT_REGISTERS_TAB[i as usize];
+        }
     }
 
-    match backend {
+    let modbus = match backend {
         Backend::TCP => {
-            let mut socket = modbus.tcp_listen(1)?;
-            modbus.tcp_accept(&mut socket)?;
+            let listener = modbus.tcp_listen(1)?;
+            listener.tcp_accept().map_err(|err| err.into_error())?
         },
         Backend::TCPPI => {
-            let mut socket = modbus.tcp_pi_listen(1)?;
-            modbus.tcp_pi_accept(&mut socket)?;
+            let listener = modbus.tcp_pi_listen(1)?;
+            listener.tcp_pi_accept().map_err(|err| err.into_error())?
         },
         Backend::RTU => {
-            modbus.connect()?;
+            modbus.connect().map_err(|err| err.into_error())?
         },
-    }
+    };
 
     loop {
-        let mut rc: std::result::Result<i32, Error>;
+        let mut rc: std::result::Result<i32, ModbusError>;
         loop {
             rc = modbus.receive(&mut query);
             /* Filtered queries return 0 */
@@ -109,23 +116,24 @@ fn run() -> Result<(), Error> {
 
         /* The connection is not closed on errors which require on reply such as
             bad CRC in RTU. */
-        if rc.is_err() && rc.as_ref().unwrap_err().to_string() != "Invalid CRC" {
+        let rc = match rc {
+            Ok(rc) => rc,
+            Err(ModbusError::BadCrc) => continue,
             /* Quit */
-            break;
-        }
+            Err(_) => break,
+        };
 
         /* Special server behavior to test client */
-        if query[header_length] == 0x03 {
-            /* Read holding registers */
-
-            if query[header_length + 3] as u16 == UT_REGISTERS_NB_SPECIAL {
+        let indication = Indication::parse(&modbus, &query[..rc as usize]);
+        if let Ok(Indication { request: Request::ReadHoldingRegisters { address, count }, transaction_id, .. }) = indication {
+            if count == UT_REGISTERS_NB_SPECIAL {
                 println!("Set an incorrect number of values");
-                query[header_length + 3] = (UT_REGISTERS_NB_SPECIAL - 1) as u8;
-            } else if query[header_length + 1] as u16 == UT_REGISTERS_ADDRESS_SPECIAL {
+                query[header_length + 4] = (UT_REGISTERS_NB_SPECIAL - 1) as u8;
+            } else if address == UT_REGISTERS_ADDRESS_SPECIAL {
                 println!("Reply to this special register address by an exception");
                 modbus.reply_exception(&query, Exception::SlaveDeviceBusy).unwrap();
                 continue;
-            } else if query[header_length + 1] as u16 == UT_REGISTERS_ADDRESS_INVALID_TID_OR_SLAVE {
+            } else if address == UT_REGISTERS_ADDRESS_INVALID_TID_OR_SLAVE {
                 const RAW_REQ_LENGTH: usize = 5;
                 let mut raw_req = vec![
                     if backend == Backend::RTU { INVALID_SERVER_ID } else { 0xFF },
@@ -136,23 +144,25 @@ fn run() -> Result<(), Error> {
                 println!("Reply with an invalid TID or slave");
                 modbus.send_raw_request(&mut raw_req, RAW_REQ_LENGTH).unwrap();
                 continue;
-            } else if query[header_length + 1] as u16 == UT_REGISTERS_ADDRESS_SLEEP_500_MS {
+            } else if address == UT_REGISTERS_ADDRESS_SLEEP_500_MS {
                 println!("Sleep 0.5 s before replying");
                 sleep(Duration::from_millis(500));
-            } else if query[header_length + 1] as u16 == UT_REGISTERS_ADDRESS_BYTE_SLEEP_5_MS {
+            } else if address == UT_REGISTERS_ADDRESS_BYTE_SLEEP_5_MS {
                 /* Test low level only available in TCP mode */
                 /* Catch the reply and send reply byte a byte */
-                let mut req = vec![0x00, 0x1C, 0x00, 0x00, 0x00, 0x05, 0xFF, 0x03, 0x02, 0x00, 0x00];
-                let req_length = 11;
+                /* Copy TID */
+                let transaction_id = match transaction_id {
+                    Some(transaction_id) => transaction_id,
+                    None => continue,
+                };
+                let req = tcp::Adu::new(transaction_id, 0xFF, &[0x03, 0x02, 0x00, 0x00]).encode()?;
                 let w_s = modbus.get_socket();
                 if w_s.is_err() {
                     println!("Unable to get a valid socket in special test");
                     continue;
                 }
 
-                /* Copy TID */
-                req[1] = query[1];
-                for i in 0..req_length {
+                for i in 0..req.len() {
                     println!("({:2X})", req[i]);
                     sleep(Duration::from_millis(5));
                     // let rc = send(w_s, (const char*)(req + i), 1, MSG_NOSIGNAL);
@@ -164,7 +174,7 @@ fn run() -> Result<(), Error> {
             }
         }
 
-        let rc = modbus.reply(&query, rc.unwrap(), &modbus_mapping);
+        let rc = modbus.reply(&query, rc, &modbus_mapping);
         if rc.is_err() { break; }
 
     }
@@ -0,0 +1,237 @@
+//! `#[derive(ModbusRegisters)]` for [libmodbus-rs](https://github.com/zzeroo/libmodbus-rs)
+//!
+//! This crate is used through the `derive` feature of libmodbus-rs, the generated code refers to `::libmodbus_rs`.
+//! See the `ModbusRegisters` trait of libmodbus-rs for the attributes.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitInt, LitStr, Type};
+
+
+/// Tables as named in the attribute, with the variant of `libmodbus_rs::Table`
+const TABLES: [(&str, &str); 4] = [("coil", "Coils"),
+                                   ("discrete", "DiscreteInputs"),
+                                   ("holding", "HoldingRegisters"),
+                                   ("input", "InputRegisters")];
+
+/// Value types with their size in bytes, `0` for bits
+const TYPES: [(&str, usize); 9] = [("bool", 0),
+                                   ("u16", 2),
+                                   ("i16", 2),
+                                   ("u32", 4),
+                                   ("i32", 4),
+                                   ("u64", 8),
+                                   ("i64", 8),
+                                   ("f32", 4),
+                                   ("f64", 8)];
+
+/// Whether the bytes of every register and the registers of a value are little endian
+type Orders = (bool, bool);
+
+#[proc_macro_derive(ModbusRegisters, attributes(modbus))]
+pub fn derive_modbus_registers(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// A field with its `#[modbus(...)]` attribute
+struct Field {
+    ident: syn::Ident,
+    /// The type the field is encoded as
+    ty: Type,
+    /// Variant of `libmodbus_rs::Table`
+    table: &'static str,
+    address: u16,
+    little_endian_bytes: bool,
+    little_endian_words: bool,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => fields.named.iter().map(parse_field).collect::<Result<Vec<_>, Error>>()?,
+            _ => return Err(Error::new(input.span(), "ModbusRegisters needs a struct with named fields")),
+        },
+        _ => return Err(Error::new(input.span(), "ModbusRegisters can only be derived for structs")),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let count = fields.len();
+    let points = fields.iter().map(|field| {
+        let (ty, address) = (&field.ty, field.address);
+        let table = format_ident!("{}", field.table);
+        quote! {
+            ::libmodbus_rs::Point {
+                table: ::libmodbus_rs::Table::#table,
+                address: #address,
+                length: <#ty as ::libmodbus_rs::FieldValue>::LENGTH,
+            }
+        }
+    });
+    let decode = fields.iter().enumerate().map(|(index, field)| {
+        let (ident, ty) = (&field.ident, &field.ty);
+        let (byte_order, word_order) = orders(field);
+        quote! {
+            #ident: <#ty as ::libmodbus_rs::FieldValue>::decode(&values[#index], #byte_order, #word_order)?
+        }
+    });
+    let encode = fields.iter().map(|field| {
+        let (ident, ty) = (&field.ident, &field.ty);
+        let (byte_order, word_order) = orders(field);
+        quote! {
+            <#ty as ::libmodbus_rs::FieldValue>::encode(&self.#ident, #byte_order, #word_order)
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::libmodbus_rs::ModbusRegisters for #name #ty_generics #where_clause {
+            const POINTS: &'static [::libmodbus_rs::Point] = &[#(#points),*];
+
+            fn decode(values: &[::libmodbus_rs::Response]) -> ::std::result::Result<Self, ::libmodbus_rs::Error> {
+                if values.len() != #count {
+                    return ::std::result::Result::Err(::libmodbus_rs::Error::BadData);
+                }
+                ::std::result::Result::Ok(#name { #(#decode),* })
+            }
+
+            fn encode(&self) -> ::std::vec::Vec<::libmodbus_rs::Response> {
+                ::std::vec![#(#encode),*]
+            }
+        }
+    })
+}
+
+fn orders(field: &Field) -> (TokenStream2, TokenStream2) {
+    let order = |little_endian| if little_endian { format_ident!("LittleEndian") } else { format_ident!("BigEndian") };
+    let (byte_order, word_order) = (order(field.little_endian_bytes), order(field.little_endian_words));
+    (quote!(::libmodbus_rs::ByteOrder::#byte_order), quote!(::libmodbus_rs::WordOrder::#word_order))
+}
+
+fn parse_field(field: &syn::Field) -> Result<Field, Error> {
+    let ident = field.ident.clone().ok_or_else(|| Error::new(field.span(), "expected a named field"))?;
+    let mut location = None;
+    let mut encoding = None;
+    let mut little_endian_bytes = None;
+    let mut little_endian_words = None;
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("modbus")) {
+        attr.parse_nested_meta(|meta| {
+            if let Some(&(_, table)) = TABLES.iter().find(|&&(name, _)| meta.path.is_ident(name)) {
+                if location.is_some() {
+                    return Err(meta.error("the field already has an address"));
+                }
+                let address: LitInt = meta.value()?.parse()?;
+                location = Some((table, address.base10_parse::<u16>()?));
+            } else if meta.path.is_ident("ty") {
+                let ty: LitStr = meta.value()?.parse()?;
+                encoding = Some((parse_encoding(&ty)?, ty.span()));
+            } else if meta.path.is_ident("byte_order") {
+                little_endian_bytes = Some(parse_order(&meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("word_order") {
+                little_endian_words = Some(parse_order(&meta.value()?.parse()?)?);
+            } else {
+                return Err(meta.error("expected `coil`, `discrete`, `holding`, `input`, `ty`, `byte_order` or `word_order`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let (table, address) = location.ok_or_else(|| {
+        Error::new(ident.span(), "missing `#[modbus(<table> = <address>)]`, table is coil, discrete, holding or input")
+    })?;
+    let has_orders = little_endian_bytes.is_some() || little_endian_words.is_some();
+    let (ty, bits) = match encoding {
+        Some(((name, order), span)) => {
+            if let Some((bytes, words)) = order {
+                if has_orders {
+                    return Err(Error::new(span, "the order is given by `ty`, remove `byte_order` and `word_order`"));
+                }
+                little_endian_bytes = Some(bytes);
+                little_endian_words = Some(words);
+            }
+            (syn::parse_str::<Type>(name)?, name == "bool")
+        },
+        None => {
+            let bits = match field.ty {
+                Type::Path(ref path) => path.path.is_ident("bool"),
+                _ => false,
+            };
+            (field.ty.clone(), bits)
+        },
+    };
+    if bits != (table == "Coils" || table == "DiscreteInputs") {
+        return Err(Error::new(field.ty.span(),
+                              "`bool` fields belong to coil or discrete, other fields to holding or input"));
+    }
+    if bits && has_orders {
+        return Err(Error::new(field.ty.span(), "`bool` has no byte or word order"));
+    }
+
+    Ok(Field {
+        ident,
+        ty,
+        table,
+        address,
+        little_endian_bytes: little_endian_bytes.unwrap_or(false),
+        little_endian_words: little_endian_words.unwrap_or(false),
+    })
+}
+
+/// Splits `"f32_cdab"` into the type and, if given, whether bytes and words are little endian
+fn parse_encoding(lit: &LitStr) -> Result<(&'static str, Option<Orders>), Error> {
+    let value = lit.value();
+    let mut parts = value.splitn(2, '_');
+    let name = parts.next().unwrap_or_default();
+    let &(name, bytes) = TYPES.iter()
+                              .find(|&&(type_name, _)| type_name == name)
+                              .ok_or_else(|| Error::new(lit.span(), format!("unknown type `{}`", name)))?;
+
+    let order = match parts.next() {
+        Some(_) if bytes == 0 => return Err(Error::new(lit.span(), "`bool` has no byte order")),
+        Some(order) => order,
+        None => return Ok((name, None)),
+    };
+    for &little_endian_bytes in &[false, true] {
+        for &little_endian_words in &[false, true] {
+            if order == byte_letters(bytes, little_endian_bytes, little_endian_words) {
+                return Ok((name, Some((little_endian_bytes, little_endian_words))));
+            }
+        }
+    }
+
+    let orders: Vec<String> = [(false, false), (true, false), (false, true), (true, true)]
+        .iter()
+        .map(|&(little_endian_bytes, little_endian_words)| byte_letters(bytes, little_endian_bytes, little_endian_words))
+        .collect();
+    Err(Error::new(lit.span(), format!("invalid order `{}` for `{}`, expected one of {}", order, name, orders.join(", "))))
+}
+
+/// The bytes as stored in the registers, named like the `get_float_*` functions of libmodbus with `a` for the least
+/// significant byte
+fn byte_letters(bytes: usize, little_endian_bytes: bool, little_endian_words: bool) -> String {
+    let letters: Vec<char> = "abcdefgh".chars().take(bytes).collect();
+    let mut words: Vec<Vec<char>> = letters.rchunks(2).map(|word| word.iter().rev().cloned().collect()).collect();
+    if little_endian_words {
+        words.reverse();
+    }
+    if little_endian_bytes {
+        for word in &mut words {
+            word.reverse();
+        }
+    }
+    words.concat().into_iter().collect()
+}
+
+/// Whether `"little_endian"` or `"big_endian"` is given, spelled like in a `RegisterMap` document
+fn parse_order(lit: &LitStr) -> Result<bool, Error> {
+    match lit.value().as_str() {
+        "big_endian" => Ok(false),
+        "little_endian" => Ok(true),
+        order => Err(Error::new(lit.span(), format!("invalid order `{}`, expected big_endian or little_endian", order))),
+    }
+}
@@ -1,24 +1,225 @@
-// https://github.com/withoutboats/failure/issues/110
-use failure;
+use crate::modbus::{Exception, Modbus};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
 use std::io;
 
 
+/// Errors returned by the libmodbus-rs API
+///
+/// libmodbus reports its own error conditions with `errno` values above
+/// [`Modbus::ENOBASE`](struct.Modbus.html#associatedconstant.ENOBASE). These values are decoded into the matching
+/// variant, all other `errno` values are kept as `io::Error`.
 #[derive(Debug, Fail)]
 pub enum Error {
+    /// The remote device answered with an exception response (`EMBXILFUN` to `EMBXGTAR`)
+    #[fail(display = "{}", _0)]
+    Exception(#[cause] Exception),
+    /// Invalid CRC (`EMBBADCRC`)
+    #[fail(display = "Invalid CRC")]
+    BadCrc,
+    /// Invalid data (`EMBBADDATA`)
+    #[fail(display = "Invalid data")]
+    BadData,
+    /// Invalid exception code (`EMBBADEXC`)
+    #[fail(display = "Invalid exception code")]
+    BadException,
+    /// Unknown exception code (`EMBUNKEXC`)
+    #[fail(display = "Unknown exception")]
+    UnknownException,
+    /// Too many data, the response or the request would overflow the protocol limits (`EMBMDATA`)
+    #[fail(display = "Too many data")]
+    TooManyData,
+    /// Response not from requested slave (`EMBBADSLAVE`)
+    #[fail(display = "Response not from requested slave")]
+    BadSlave,
+    /// The slice passed to a function holds fewer elements than the request needs
+    #[fail(display = "Buffer too small, {} elements required but only {} available", required, available)]
+    BufferTooSmall {
+        /// number of elements the request needs
+        required: usize,
+        /// number of elements in the given slice
+        available: usize,
+    },
+    /// A request of a chunked read or write failed, the chunks before it were transferred
+    #[fail(display = "Chunk of {} values at address {} failed: {}", count, address, error)]
+    ChunkFailed {
+        /// start address of the failed request
+        address: u16,
+        /// number of values of the failed request
+        count: u16,
+        /// the Error of the failed request
+        error: Box<Error>,
+    },
+    /// A frame is shorter than its header, or than the length announced in the header
+    #[fail(display = "Incomplete frame")]
+    IncompleteFrame,
+    /// A register map could not be parsed or contains an inconsistent point
+    #[fail(display = "Invalid register map: {}", _0)]
+    InvalidRegisterMap(String),
+    /// A point of a register map does not exist, or does not allow the operation
+    #[fail(display = "Point {}: {}", name, reason)]
+    InvalidPoint {
+        /// name of the point
+        name: String,
+        /// why the point can not be read or written
+        reason: &'static str,
+    },
+    /// A timeout with `usec` out of the range 0 to 999999, or with more seconds than fit into `sec`
+    #[fail(display = "Invalid timeout")]
+    InvalidTimeout,
+    /// No response (or no complete response) within the configured timeouts (`ETIMEDOUT`)
+    #[fail(display = "Timeout")]
+    Timeout,
+    /// Any other operating system error
     #[fail(display = "IO Error {}", _0)]
     IoError(#[cause] io::Error),
-    #[fail(display = "Error {}", _0)]
-    Custom(failure::Error),
 }
 
-impl From <io::Error> for Error {
+impl Error {
+    /// Decode an `errno` value, as set by the libmodbus functions, into an `Error`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Error, Exception, Modbus};
+    ///
+    /// match Error::from_errno(Modbus::ENOBASE as i32 + 2) {
+    ///     Error::Exception(Exception::IllegalDataAddress) => {},
+    ///     _ => panic!("wrong error"),
+    /// }
+    /// ```
+    pub fn from_errno(errnum: i32) -> Error {
+        if errnum == libc::ETIMEDOUT {
+            return Error::Timeout;
+        }
+
+        match errnum - Modbus::ENOBASE as i32 {
+            code @ 1..=11 => Exception::try_from(code as u8).map(Error::Exception).unwrap_or_else(|err| err),
+            12 => Error::BadCrc,
+            13 => Error::BadData,
+            14 => Error::BadException,
+            15 => Error::UnknownException,
+            16 => Error::TooManyData,
+            17 => Error::BadSlave,
+            _ => Error::IoError(io::Error::from_raw_os_error(errnum)),
+        }
+    }
+
+    /// `is_transient` - whether the same request may succeed if it is sent again
+    ///
+    /// Timeouts, I/O errors of the link, corrupted or incomplete frames and the exceptions `Acknowledge`,
+    /// `SlaveDeviceBusy`, `GatewayPath` and `GatewayTarget` are transient. Other exceptions and invalid arguments are
+    /// not, a retry would fail the same way. A failed chunk is transient if its Error is.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Error, Exception};
+    ///
+    /// assert!(Error::Timeout.is_transient());
+    /// assert!(Error::Exception(Exception::SlaveDeviceBusy).is_transient());
+    /// assert!(!Error::Exception(Exception::IllegalDataAddress).is_transient());
+    /// ```
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            Error::Timeout | Error::IoError(_) | Error::BadCrc | Error::IncompleteFrame => true,
+            Error::Exception(exception) => matches!(exception,
+                                                    Exception::Acknowledge
+                                                    | Exception::SlaveDeviceBusy
+                                                    | Exception::GatewayPath
+                                                    | Exception::GatewayTarget),
+            Error::ChunkFailed { ref error, .. } => error.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Build an `Error` from the current `errno` value
+    ///
+    /// This should be called directly after a libmodbus function signaled an error, before any other call can
+    /// overwrite `errno`.
+    pub fn last_os_error() -> Error {
+        io::Error::last_os_error().into()
+    }
+}
+
+impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
-        Error::IoError(err)
+        match err.raw_os_error() {
+            Some(errnum) => Error::from_errno(errnum),
+            None => Error::IoError(err),
+        }
+    }
+}
+
+/// An Error together with the value the failed call consumed
+///
+/// [`connect()`](struct.Modbus.html#method.connect) and the `accept` functions of
+/// [`ModbusListener`](struct.ModbusListener.html) consume their receiver. If they fail, the context or listener is
+/// handed back here, so it can be used again, e.g. to retry after `EINTR` or `ECONNABORTED`. It converts into an
+/// `Error`, so `?` works in functions returning `Result<_, Error>`.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{Modbus, ModbusTCP};
+///
+/// let modbus = Modbus::new_tcp("127.0.0.1", 1).unwrap();
+/// let modbus = match modbus.connect() {
+///     Ok(connection) => connection.close(),
+///     Err(err) => {
+///         println!("Error: {}", err.error());
+///         err.into_inner()
+///     },
+/// };
+/// ```
+pub struct IntoInnerError<T> {
+    error: Error,
+    inner: T,
+}
+
+impl<T> IntoInnerError<T> {
+    pub(crate) fn new(error: Error, inner: T) -> IntoInnerError<T> {
+        IntoInnerError { error, inner }
+    }
+
+    /// The error of the failed call
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Take back the value consumed by the failed call, dropping the error
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Take the error, dropping the value
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+
+    /// Take both, the error and the value
+    pub fn into_parts(self) -> (Error, T) {
+        (self.error, self.inner)
     }
 }
 
-impl From<failure::Error> for Error {
-    fn from(err: failure::Error) -> Error {
-        Error::Custom(err)
+impl<T> fmt::Debug for IntoInnerError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<T> fmt::Display for IntoInnerError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl<T> error::Error for IntoInnerError<T> {}
+
+impl<T> From<IntoInnerError<T>> for Error {
+    fn from(err: IntoInnerError<T>) -> Error {
+        err.error
     }
 }
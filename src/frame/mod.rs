@@ -0,0 +1,11 @@
+//! Pure Rust encoders and decoders for Modbus frames
+//!
+//! The libmodbus backends build and parse the application data units (ADU) internally. The functions in this module
+//! work on plain byte slices and need no [`Modbus`](../struct.Modbus.html) context, so they can be used to inspect
+//! traffic, to build frames in tests or to check what a backend puts on the wire.
+//!
+//! * [RTU frames](rtu/index.html), slave address, PDU and CRC-16
+//! * [TCP frames](tcp/index.html), MBAP header and PDU
+
+pub mod rtu;
+pub mod tcp;
@@ -0,0 +1,152 @@
+//! Modbus RTU framing
+//!
+//! A RTU application data unit (ADU) consists of the slave address, the protocol data unit (PDU, function code and
+//! data) and a CRC-16/MODBUS checksum, transmitted low byte first:
+//!
+//! ```text
+//! +-------+----------+--------------+---------+---------+
+//! | slave | function |     data     | CRC low | CRC high|
+//! +-------+----------+--------------+---------+---------+
+//! ```
+//!
+//! On a RTU context [`send_raw_request()`](../../struct.ModbusConnection.html#method.send_raw_request) sends
+//! `raw_req[0]` as slave address and the remaining bytes as PDU, the bytes on the wire are the same as those of
+//! `Adu::new(raw_req[0], &raw_req[1..]).encode()`.
+
+use crate::error::Error;
+use crate::modbus::Modbus;
+
+
+/// Length of the slave address
+pub const ADDRESS_LENGTH: usize = 1;
+/// Length of the CRC-16 checksum
+pub const CRC_LENGTH: usize = 2;
+/// Smallest possible frame, slave address, function code and checksum
+pub const MIN_ADU_LENGTH: usize = ADDRESS_LENGTH + 1 + CRC_LENGTH;
+
+/// A decoded (or to be encoded) RTU application data unit
+///
+/// The PDU is borrowed from the frame or from the caller, no data is copied until the frame is encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Adu<'a> {
+    /// Slave address, `0` is the broadcast address
+    pub slave: u8,
+    /// Protocol data unit, function code followed by the data
+    pub pdu: &'a [u8],
+}
+
+impl<'a> Adu<'a> {
+    /// Create a new ADU for the given slave address and PDU
+    pub fn new(slave: u8, pdu: &'a [u8]) -> Adu<'a> {
+        Adu { slave, pdu }
+    }
+
+    /// Function code of the PDU, `None` if the PDU is empty
+    pub fn function(&self) -> Option<u8> {
+        self.pdu.first().cloned()
+    }
+
+    /// `encode` - build the frame as it is sent on the serial line
+    ///
+    /// # Return value
+    ///
+    /// The frame, slave address, PDU and checksum, or `Error::BadData` if the PDU is empty and `Error::TooManyData`
+    /// if it is longer than [`Modbus::MAX_PDU_LENGTH`](../../struct.Modbus.html#associatedconstant.MAX_PDU_LENGTH).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::frame::rtu::Adu;
+    ///
+    /// // read 10 holding registers starting at address 0 of slave 1
+    /// let frame = Adu::new(1, &[0x03, 0x00, 0x00, 0x00, 0x0A]).encode().unwrap();
+    /// assert_eq!(frame, vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]);
+    /// ```
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        if self.pdu.is_empty() {
+            return Err(Error::BadData);
+        }
+        if self.pdu.len() > Modbus::MAX_PDU_LENGTH {
+            return Err(Error::TooManyData);
+        }
+
+        let mut frame = Vec::with_capacity(ADDRESS_LENGTH + self.pdu.len() + CRC_LENGTH);
+        frame.push(self.slave);
+        frame.extend_from_slice(self.pdu);
+        let crc = crc16(&frame);
+        frame.push(crc as u8);
+        frame.push((crc >> 8) as u8);
+
+        Ok(frame)
+    }
+
+    /// `decode` - split a received frame into slave address and PDU
+    ///
+    /// The frame must contain exactly one ADU, including the checksum.
+    ///
+    /// # Return value
+    ///
+    /// The ADU borrowing the PDU from `frame`. If the frame is shorter than
+    /// [`MIN_ADU_LENGTH`](constant.MIN_ADU_LENGTH.html) `Error::IncompleteFrame` is returned, a frame longer than
+    /// [`Modbus::RTU_MAX_ADU_LENGTH`](../../struct.Modbus.html#associatedconstant.RTU_MAX_ADU_LENGTH) results in
+    /// `Error::TooManyData` and a checksum mismatch, e.g. of a truncated or corrupted frame, in `Error::BadCrc`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::Error;
+    /// use libmodbus_rs::frame::rtu::Adu;
+    ///
+    /// let adu = Adu::decode(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]).unwrap();
+    /// assert_eq!(adu.slave, 1);
+    /// assert_eq!(adu.function(), Some(0x03));
+    ///
+    /// match Adu::decode(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0B, 0xC5, 0xCD]) {
+    ///     Err(Error::BadCrc) => {},
+    ///     _ => panic!("corrupted frame not detected"),
+    /// }
+    /// ```
+    pub fn decode(frame: &'a [u8]) -> Result<Adu<'a>, Error> {
+        if frame.len() < MIN_ADU_LENGTH {
+            return Err(Error::IncompleteFrame);
+        }
+        if frame.len() > Modbus::RTU_MAX_ADU_LENGTH {
+            return Err(Error::TooManyData);
+        }
+
+        let (data, checksum) = frame.split_at(frame.len() - CRC_LENGTH);
+        if crc16(data) != u16::from(checksum[0]) | u16::from(checksum[1]) << 8 {
+            return Err(Error::BadCrc);
+        }
+
+        Ok(Adu {
+            slave: data[0],
+            pdu: &data[ADDRESS_LENGTH..],
+        })
+    }
+}
+
+/// `crc16` - compute the CRC-16/MODBUS checksum
+///
+/// Polynomial `0xA001` (reflected `0x8005`), initial value `0xFFFF`. The checksum is appended to a RTU frame low byte
+/// first.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::frame::rtu::crc16;
+///
+/// assert_eq!(crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]), 0xCDC5);
+/// assert_eq!(crc16(b"123456789"), 0x4B37);
+/// ```
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, byte| {
+        (0..8).fold(crc ^ u16::from(*byte), |crc, _| {
+            if crc & 0x0001 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
@@ -0,0 +1,311 @@
+//! Modbus TCP framing
+//!
+//! A TCP application data unit (ADU) consists of the MBAP header (Modbus Application Protocol header) and the protocol
+//! data unit (PDU, function code and data). All header fields are big endian:
+//!
+//! ```text
+//! +----------------+-------------+--------+---------+----------+--------------+
+//! | transaction id | protocol id | length | unit id | function |     data     |
+//! |    2 bytes     |   2 bytes   | 2 bytes| 1 byte  |          |              |
+//! +----------------+-------------+--------+---------+----------+--------------+
+//! ```
+//!
+//! The length field counts the bytes following it, the unit id and the PDU.
+//!
+//! TCP is a stream, a single read from a socket can return a partial frame or several frames. The
+//! [`Decoder`](struct.Decoder.html) collects the received bytes and splits them into complete frames.
+
+use crate::error::Error;
+use crate::modbus::Modbus;
+use std::io::{ErrorKind, Read};
+
+
+/// Length of the MBAP header, including the unit id
+pub const HEADER_LENGTH: usize = 7;
+/// Protocol id of Modbus, all other values are reserved
+pub const PROTOCOL_ID: u16 = 0;
+
+/// The MBAP header of a TCP frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// Transaction id, copied by the server from the request into the response
+    pub transaction_id: u16,
+    /// Protocol id, `0` for Modbus
+    pub protocol_id: u16,
+    /// Number of following bytes, unit id and PDU
+    pub length: u16,
+    /// Unit id, the slave address of a device behind a gateway
+    pub unit_id: u8,
+}
+
+impl Header {
+    /// `decode` - parse the MBAP header at the start of `frame`
+    ///
+    /// # Return value
+    ///
+    /// The header, or `Error::IncompleteFrame` if `frame` is shorter than
+    /// [`HEADER_LENGTH`](constant.HEADER_LENGTH.html). The fields are not validated, see
+    /// [`validate()`](#method.validate).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::frame::tcp::Header;
+    ///
+    /// let header = Header::decode(&[0x00, 0x1C, 0x00, 0x00, 0x00, 0x06, 0xFF, 0x03]).unwrap();
+    /// assert_eq!(header.transaction_id, 0x1C);
+    /// assert_eq!(header.length, 6);
+    /// assert_eq!(header.unit_id, 0xFF);
+    /// ```
+    pub fn decode(frame: &[u8]) -> Result<Header, Error> {
+        if frame.len() < HEADER_LENGTH {
+            return Err(Error::IncompleteFrame);
+        }
+
+        Ok(Header {
+            transaction_id: u16::from(frame[0]) << 8 | u16::from(frame[1]),
+            protocol_id: u16::from(frame[2]) << 8 | u16::from(frame[3]),
+            length: u16::from(frame[4]) << 8 | u16::from(frame[5]),
+            unit_id: frame[6],
+        })
+    }
+
+    /// `encode` - the header bytes as sent on the wire
+    pub fn encode(&self) -> [u8; HEADER_LENGTH] {
+        [(self.transaction_id >> 8) as u8,
+         self.transaction_id as u8,
+         (self.protocol_id >> 8) as u8,
+         self.protocol_id as u8,
+         (self.length >> 8) as u8,
+         self.length as u8,
+         self.unit_id]
+    }
+
+    /// `validate` - check protocol id and length field
+    ///
+    /// # Return value
+    ///
+    /// `Error::BadData` if the protocol id is not [`PROTOCOL_ID`](constant.PROTOCOL_ID.html) or the length does not
+    /// even cover the unit id and a function code, `Error::TooManyData` if the frame would be longer than
+    /// [`Modbus::TCP_MAX_ADU_LENGTH`](../../struct.Modbus.html#associatedconstant.TCP_MAX_ADU_LENGTH).
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.protocol_id != PROTOCOL_ID || self.length < 2 {
+            return Err(Error::BadData);
+        }
+        if self.frame_length() > Modbus::TCP_MAX_ADU_LENGTH {
+            return Err(Error::TooManyData);
+        }
+        Ok(())
+    }
+
+    /// Length of the complete frame announced by this header, header and PDU
+    pub fn frame_length(&self) -> usize {
+        HEADER_LENGTH - 1 + self.length as usize
+    }
+}
+
+/// A decoded (or to be encoded) TCP application data unit
+///
+/// The PDU is borrowed from the frame or from the caller, no data is copied until the frame is encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Adu<'a> {
+    /// Transaction id
+    pub transaction_id: u16,
+    /// Unit id, [`Modbus::TCP_SLAVE`](../../struct.Modbus.html#associatedconstant.TCP_SLAVE) if no gateway is involved
+    pub unit_id: u8,
+    /// Protocol data unit, function code followed by the data
+    pub pdu: &'a [u8],
+}
+
+impl<'a> Adu<'a> {
+    /// Create a new ADU for the given transaction id, unit id and PDU
+    pub fn new(transaction_id: u16, unit_id: u8, pdu: &'a [u8]) -> Adu<'a> {
+        Adu { transaction_id, unit_id, pdu }
+    }
+
+    /// Function code of the PDU, `None` if the PDU is empty
+    pub fn function(&self) -> Option<u8> {
+        self.pdu.first().cloned()
+    }
+
+    /// The MBAP header of this ADU
+    pub fn header(&self) -> Header {
+        Header {
+            transaction_id: self.transaction_id,
+            protocol_id: PROTOCOL_ID,
+            length: (self.pdu.len() + 1) as u16,
+            unit_id: self.unit_id,
+        }
+    }
+
+    /// `encode` - build the frame as it is sent on the socket
+    ///
+    /// # Return value
+    ///
+    /// The frame, MBAP header and PDU, or `Error::BadData` if the PDU is empty and `Error::TooManyData` if it is
+    /// longer than [`Modbus::MAX_PDU_LENGTH`](../../struct.Modbus.html#associatedconstant.MAX_PDU_LENGTH).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::frame::tcp::Adu;
+    ///
+    /// let frame = Adu::new(0x1C, 0xFF, &[0x03, 0x02, 0x00, 0x00]).encode().unwrap();
+    /// assert_eq!(frame, vec![0x00, 0x1C, 0x00, 0x00, 0x00, 0x05, 0xFF, 0x03, 0x02, 0x00, 0x00]);
+    /// ```
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        if self.pdu.is_empty() {
+            return Err(Error::BadData);
+        }
+        if self.pdu.len() > Modbus::MAX_PDU_LENGTH {
+            return Err(Error::TooManyData);
+        }
+
+        let mut frame = Vec::with_capacity(HEADER_LENGTH + self.pdu.len());
+        frame.extend_from_slice(&self.header().encode());
+        frame.extend_from_slice(self.pdu);
+
+        Ok(frame)
+    }
+
+    /// `decode` - split a received frame into MBAP header fields and PDU
+    ///
+    /// The frame must contain exactly one ADU.
+    ///
+    /// # Return value
+    ///
+    /// The ADU borrowing the PDU from `frame`. A frame shorter than announced in its header results in
+    /// `Error::IncompleteFrame`, a longer one in `Error::TooManyData`. An invalid header is reported as by
+    /// [`Header::validate()`](struct.Header.html#method.validate).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::Error;
+    /// use libmodbus_rs::frame::tcp::Adu;
+    ///
+    /// let frame = [0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0xFF, 0x03, 0x00, 0x00, 0x00, 0x0A];
+    /// let adu = Adu::decode(&frame).unwrap();
+    /// assert_eq!(adu.transaction_id, 1);
+    /// assert_eq!(adu.pdu, &[0x03, 0x00, 0x00, 0x00, 0x0A]);
+    ///
+    /// match Adu::decode(&frame[..10]) {
+    ///     Err(Error::IncompleteFrame) => {},
+    ///     _ => panic!("truncated frame not detected"),
+    /// }
+    /// ```
+    pub fn decode(frame: &'a [u8]) -> Result<Adu<'a>, Error> {
+        let header = Header::decode(frame)?;
+        header.validate()?;
+
+        if frame.len() < header.frame_length() {
+            return Err(Error::IncompleteFrame);
+        }
+        if frame.len() > header.frame_length() {
+            return Err(Error::TooManyData);
+        }
+
+        Ok(Adu {
+            transaction_id: header.transaction_id,
+            unit_id: header.unit_id,
+            pdu: &frame[HEADER_LENGTH..],
+        })
+    }
+}
+
+/// Reassembles TCP frames from a byte stream
+///
+/// The bytes received from a socket are passed to [`push()`](#method.push), complete frames are taken out with
+/// [`next_frame()`](#method.next_frame). [`read_frame()`](#method.read_frame) does both for a
+/// [`Read`](https://doc.rust-lang.org/std/io/trait.Read.html) implementation like a `TcpStream`.
+///
+/// If a header is invalid the stream can not be resynchronized, the buffered data is discarded and the connection
+/// should be closed.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::frame::tcp::{Adu, Decoder};
+///
+/// let mut decoder = Decoder::new();
+///
+/// decoder.push(&[0x00, 0x01, 0x00, 0x00, 0x00]);
+/// assert_eq!(decoder.next_frame().unwrap(), None);
+///
+/// decoder.push(&[0x03, 0xFF, 0x05, 0x00]);
+/// let frame = decoder.next_frame().unwrap().unwrap();
+/// assert_eq!(Adu::decode(&frame).unwrap().pdu, &[0x05, 0x00]);
+/// ```
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+}
+
+impl Decoder {
+    /// Create an empty decoder
+    pub fn new() -> Decoder {
+        Decoder { buffer: Vec::with_capacity(Modbus::TCP_MAX_ADU_LENGTH) }
+    }
+
+    /// Append received bytes
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Number of buffered bytes not yet returned as frame
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// `next_frame` - take the next complete frame out of the buffer
+    ///
+    /// # Return value
+    ///
+    /// `Ok(Some(frame))` with the complete frame, header and PDU, which can be passed to
+    /// [`Adu::decode()`](struct.Adu.html#method.decode), `Ok(None)` if more data is needed, or the error of
+    /// [`Header::validate()`](struct.Header.html#method.validate) if the buffered header is invalid.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let header = match Header::decode(&self.buffer) {
+            Ok(header) => header,
+            Err(_) => return Ok(None),
+        };
+        if let Err(err) = header.validate() {
+            self.buffer.clear();
+            return Err(err);
+        }
+
+        let length = header.frame_length();
+        if self.buffer.len() < length {
+            return Ok(None);
+        }
+        let rest = self.buffer.split_off(length);
+
+        Ok(Some(::std::mem::replace(&mut self.buffer, rest)))
+    }
+
+    /// `read_frame` - read from `reader` until a complete frame is available
+    ///
+    /// Frames already buffered are returned without reading.
+    ///
+    /// # Return value
+    ///
+    /// `Ok(Some(frame))` with the complete frame, `Ok(None)` if the stream ended between two frames or
+    /// `Error::IncompleteFrame` if it ended within a frame. Read errors and invalid headers are returned as well.
+    pub fn read_frame<R: Read>(&mut self, reader: &mut R) -> Result<Option<Vec<u8>>, Error> {
+        let mut chunk = [0u8; Modbus::TCP_MAX_ADU_LENGTH];
+
+        loop {
+            if let Some(frame) = self.next_frame()? {
+                return Ok(Some(frame));
+            }
+
+            match reader.read(&mut chunk) {
+                Ok(0) if self.buffer.is_empty() => return Ok(None),
+                Ok(0) => return Err(Error::IncompleteFrame),
+                Ok(len) => self.push(&chunk[..len]),
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {},
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
@@ -122,29 +122,57 @@ html_root_url = "https://zzeroo.com/")]
 //!
 //! ### Connection
 //!
+//! A [`Modbus`](struct.Modbus.html) context only holds the configuration. The connection state is encoded in the
+//! returned types:
+//!
+//! * [`connect()`](struct.Modbus.html#method.connect) consumes the context and returns a
+//!   [`ModbusConnection`](struct.ModbusConnection.html), which implements the client and server traits
+//! * [`tcp_listen()`](struct.Modbus.html#method.tcp_listen) returns a [`ModbusListener`](struct.ModbusListener.html),
+//!   its [`tcp_accept()`](struct.ModbusListener.html#method.tcp_accept) returns the connection to the client
+//! * [`close()`](struct.ModbusConnection.html#method.close) gives the unconnected context back, dropping a
+//!   connection or listener closes its sockets
+//!
 //! ### [`Client`](trait.ModbusClient.html)
 //!
 //! The Modbus protocol defines different data types and functions to read and write them from/to remote devices.
 //! The following functions are used by the clients to send Modbus requests:
 //!
 //! * Read data
-//!     - [`read_bits()`](struct.Modbus.html#method.read_bits),
-//! [`read_input_bits()`](struct.Modbus.html#method.read_input_bits),
-//! [`read_registers()`](struct.Modbus.html#method.read_registers),
-//! [`read_input_registers()`](struct.Modbus.html#method.read_input_registers),
-//! [`report_slave_id()`](struct.Modbus.html#method.report_slave_id)
+//!     - [`read_bits()`](struct.ModbusConnection.html#method.read_bits),
+//! [`read_input_bits()`](struct.ModbusConnection.html#method.read_input_bits),
+//! [`read_registers()`](struct.ModbusConnection.html#method.read_registers),
+//! [`read_input_registers()`](struct.ModbusConnection.html#method.read_input_registers),
+//! [`report_slave_id()`](struct.ModbusConnection.html#method.report_slave_id)
 //! * Write data
-//!     - [`write_bit()`](struct.Modbus.html#method.write_bit),
-//! [`write_register()`](struct.Modbus.html#method.write_register),
-//! [`write_bits()`](struct.Modbus.html#method.write_bits),
-//! [`write_registers()`](struct.Modbus.html#method.write_registers)
+//!     - [`write_bit()`](struct.ModbusConnection.html#method.write_bit),
+//! [`write_register()`](struct.ModbusConnection.html#method.write_register),
+//! [`write_bits()`](struct.ModbusConnection.html#method.write_bits),
+//! [`write_registers()`](struct.ModbusConnection.html#method.write_registers)
 //! * Write and read data
-//!     - [`write_and_read_registers()`](struct.Modbus.html#method.write_and_read_registers)
+//!     - [`write_and_read_registers()`](struct.ModbusConnection.html#method.write_and_read_registers)
 //! * Raw requests
-//!     - [`send_raw_request()`](struct.Modbus.html#method.send_raw_request),
-//! [`receive_confirmation()`](struct.Modbus.html#method.receive_confirmation)
+//!     - [`send_raw_request()`](struct.ModbusConnection.html#method.send_raw_request),
+//! [`receive_confirmation()`](struct.ModbusConnection.html#method.receive_confirmation)
 //! * Reply an exception
-//!     - [`reply_exception()`](struct.Modbus.html#method.reply_exception)
+//!     - [`reply_exception()`](struct.ModbusConnection.html#method.reply_exception)
+//!
+//! Values scattered over the address space are read with few requests by a [`ReadPlanner`](struct.ReadPlanner.html),
+//! it merges neighbouring [`Point`](struct.Point.html)s into requests and scatters the responses back to them.
+//!
+//! Several devices on one connection, like a RTU multi-drop bus, are addressed with a [`Device`](struct.Device.html)
+//! each. It binds the slave ID to a [`Bus`](struct.Bus.html) shared by the devices, which can be used from different
+//! threads.
+//!
+//! Links that drop, like cellular connections, are handled by a [`ReconnectingClient`](struct.ReconnectingClient.html).
+//! It connects again after the link failed and retries requests with a [`RetryPolicy`](struct.RetryPolicy.html) of
+//! attempts, exponential backoff and retried errors. A policy also retries any other operation.
+//!
+//! On links with a high latency a [`PipelinedTcpClient`](struct.PipelinedTcpClient.html) keeps several requests in
+//! flight on one TCP connection, the responses are matched to the requests by their transaction id.
+//!
+//! With the `tokio` cargo feature the same requests are available as `async fn`s of an
+//! [`AsyncModbusClient`](struct.AsyncModbusClient.html), a Modbus TCP client which does not block a thread per
+//! connection.
 //!
 //! ### [`Server`](trait.ModbusServer.html)
 //!
@@ -153,21 +181,60 @@ html_root_url = "https://zzeroo.com/")]
 //! In TCP , you must not use the usual [`connect()`](struct.Modbus.html#method.connect) to establish the connection
 //! but a pair of accept/listen calls
 //!
-//! * [`tcp_listen()`](struct.Modbus.html#method.tcp_listen), [`tcp_accept()`](struct.Modbus.html#method.tcp_accept),
+//! * [`tcp_listen()`](struct.Modbus.html#method.tcp_listen), [`tcp_accept()`](struct.ModbusListener.html#method.tcp_accept),
 //! [`tcp_pi_listen`()](struct.Modbus.html#method.tcp_pi_listen),
-//! [`tcp_pi_accept`()](struct.Modbus.html#method.tcp_pi_accept)
+//! [`tcp_pi_accept`()](struct.ModbusListener.html#method.tcp_pi_accept)
+//!
+//! A [`TcpServer`](struct.TcpServer.html) created from the listener serves many clients at once.
 //!
 //! then the data can be received with
 //!
-//! * [`receive()`](struct.Modbus.html#method.receive)
+//! * [`receive()`](struct.ModbusConnection.html#method.receive)
 //!
 //! and a response can be send with
 //!
-//! * [`reply()`](struct.Modbus.html#method.reply), [`reply_exception()`](struct.Modbus.html#method.reply_exception)
+//! * [`reply()`](struct.ModbusConnection.html#method.reply), [`reply_exception()`](struct.ModbusConnection.html#method.reply_exception)
+//!
+//! To inspect or route a received request before replying, it can be decoded with
+//! [`Indication::parse()`](struct.Indication.html#method.parse) into a [`Request`](enum.Request.html).
+//!
+//! Instead of a mapping, a [`RequestHandler`](trait.RequestHandler.html) can compute the values on demand, the
+//! request is answered with [`reply_with()`](struct.ModbusConnection.html#method.reply_with).
+//!
+//! With the `tokio` cargo feature an [`AsyncTcpServer`](struct.AsyncTcpServer.html) serves TCP clients on a tokio
+//! runtime, answering from a mapping or an [`AsyncRequestHandler`](trait.AsyncRequestHandler.html).
 //!
 //! To handle the mapping of your Modbus data, you must use a [`ModbusMapping`](struct.ModbusMapping.html) struct:
 //! [`ModbusMapping::new()`](struct.ModbusMapping.html#method.new)
 //!
+//! The tables of the mapping are accessed with [`ModbusMapping::lock()`](struct.ModbusMapping.html#method.lock),
+//! this way the mapping can be updated from other threads while the server replies to requests.
+//!
+//! ### [`Values`](trait.RegisterValue.html)
+//!
+//! Values spanning several registers, like 32 or 64 bit integers and floats, are decoded and encoded with
+//! [`prelude::get_value()`](prelude/fn.get_value.html) and [`prelude::set_value()`](prelude/fn.set_value.html) in
+//! any [`ByteOrder`](enum.ByteOrder.html) and [`WordOrder`](enum.WordOrder.html). The prelude also packs strings,
+//! BCD numbers and named [`Flag`](struct.Flag.html)s of bitfields into registers.
+//!
+//! A [`RegisterMap`](struct.RegisterMap.html) describes the points of a device by name, type and scaling, in code or
+//! loaded from TOML or JSON with the `toml` and `serde_json` cargo features. Points are read and written by name as
+//! engineering [`Value`](enum.Value.html)s.
+//!
+//! A [`Poller`](struct.Poller.html) reads groups of points from several devices at their own intervals, keeps the
+//! last value and quality of every point and reports changes beyond a deadband to callbacks and channels.
+//!
+//! With the `derive` cargo feature `#[derive(ModbusRegisters)]` maps the fields of a struct to fixed addresses, the
+//! struct is then read from a device or stored in a [`ModbusMapping`](struct.ModbusMapping.html) as a whole, see
+//! [`ModbusRegisters`](trait.ModbusRegisters.html).
+//!
+//! ### [`Frames`](frame/index.html)
+//!
+//! The [`frame`](frame/index.html) module encodes and decodes Modbus frames in Rust, without a libmodbus context:
+//!
+//! * [`frame::rtu`](frame/rtu/index.html) - RTU frames with CRC-16
+//! * [`frame::tcp`](frame/tcp/index.html) - TCP frames with MBAP header, including reassembly of a byte stream
+//!
 
 // `error_chain!` can recurse deeply(3)
 #![recursion_limit = "1024"]
@@ -176,21 +243,56 @@ html_root_url = "https://zzeroo.com/")]
 extern crate libc;
 extern crate libmodbus_sys;
 
+#[cfg(feature = "tokio")]
+mod modbus_async_client;
+#[cfg(feature = "tokio")]
+mod modbus_async_server;
 mod modbus_client;
+mod modbus_device;
+mod modbus_handler;
 mod modbus_mapping;
+mod modbus_pipelined_client;
+mod modbus_poller;
+mod modbus_read_planner;
+mod modbus_register_map;
+mod modbus_registers;
+mod modbus_request;
+mod modbus_response;
+mod modbus_retry;
 mod modbus_rtu;
 mod modbus_server;
 mod modbus_tcp_pi;
 mod modbus_tcp;
+mod modbus_tcp_server;
+mod modbus_value;
 mod modbus;
 pub mod error;
+pub mod frame;
 pub mod prelude;
 
 pub use self::error::*;
+#[cfg(feature = "tokio")]
+pub use self::modbus_async_client::AsyncModbusClient;
+#[cfg(feature = "tokio")]
+pub use self::modbus_async_server::{AsyncRequestHandler, AsyncTcpServer};
 pub use self::modbus_client::ModbusClient;
-pub use self::modbus_mapping::ModbusMapping;
+pub use self::modbus_device::{Bus, Device};
+pub use self::modbus_handler::RequestHandler;
+pub use self::modbus_mapping::{ModbusMapping, ModbusMappingGuard};
+pub use self::modbus_pipelined_client::{Confirmation, PipelinedTcpClient};
+pub use self::modbus_poller::{Change, PollGroup, Poller, Quality, Sample};
+pub use self::modbus_read_planner::{Point, ReadPlan, ReadPlanner, Table};
+pub use self::modbus_register_map::{Access, DataType, RegisterMap, RegisterPoint, Value};
+pub use self::modbus_registers::{FieldValue, ModbusRegisters};
+#[cfg(feature = "derive")]
+pub use libmodbus_derive::ModbusRegisters;
+pub use self::modbus_request::{Indication, Request};
+pub use self::modbus_response::Response;
+pub use self::modbus_retry::{ReconnectingClient, RetryPolicy};
 pub use self::modbus_rtu::{ModbusRTU, RequestToSendMode, SerialMode};
-pub use self::modbus_server::ModbusServer;
+pub use self::modbus_server::{ModbusServer, ShutdownHandle};
 pub use self::modbus_tcp_pi::ModbusTCPPI;
 pub use self::modbus_tcp::ModbusTCP;
-pub use self::modbus::{Modbus, Timeout, ErrorRecoveryMode, Exception, FunctionCode};
+pub use self::modbus_tcp_server::TcpServer;
+pub use self::modbus_value::{ByteOrder, Flag, RegisterValue, WordOrder};
+pub use self::modbus::{Modbus, ModbusConnection, ModbusListener, Timeout, ErrorRecoveryMode, Exception, FunctionCode};
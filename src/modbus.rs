@@ -1,6 +1,14 @@
-use failure::Error;
-use libc::{c_int, c_uint};
+use crate::error::{Error, IntoInnerError};
+use crate::modbus_rtu::{self, CustomRts};
+use libc::{self, c_int, c_uint};
 use libmodbus_sys as ffi;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::time::Duration;
 
 
 /// Modbus protocol exceptions
@@ -37,10 +45,70 @@ pub enum Exception {
     GatewayTarget = 11,
 }
 
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use crate::Exception::*;
+
+        // same messages as `modbus_strerror()`
+        let message = match *self {
+            IllegalFunction => "Illegal function",
+            IllegalDataAddress => "Illegal data address",
+            IllegalDataValue => "Illegal data value",
+            SlaveOrServerFailure => "Slave device or server failure",
+            Acknowledge => "Acknowledge",
+            SlaveDeviceBusy => "Slave device or server is busy",
+            NegativeAcknowledge => "Negative acknowledge",
+            MemoryParity => "Memory parity error",
+            NotDefined => "Not defined",
+            GatewayPath => "Gateway path unavailable",
+            GatewayTarget => "Target device failed to respond",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl error::Error for Exception {}
+
+/// Decode the exception code of an exception response
+///
+/// Codes outside of the range 1 to 11 are no valid exception codes and result in an `Error::BadException`.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::Exception;
+/// use std::convert::TryFrom;
+///
+/// assert_eq!(Exception::try_from(6).unwrap(), Exception::SlaveDeviceBusy);
+/// assert!(Exception::try_from(0).is_err());
+/// ```
+impl TryFrom<u8> for Exception {
+    type Error = Error;
+
+    fn try_from(code: u8) -> Result<Exception, Error> {
+        use crate::Exception::*;
+
+        match code {
+            1 => Ok(IllegalFunction),
+            2 => Ok(IllegalDataAddress),
+            3 => Ok(IllegalDataValue),
+            4 => Ok(SlaveOrServerFailure),
+            5 => Ok(Acknowledge),
+            6 => Ok(SlaveDeviceBusy),
+            7 => Ok(NegativeAcknowledge),
+            8 => Ok(MemoryParity),
+            9 => Ok(NotDefined),
+            10 => Ok(GatewayPath),
+            11 => Ok(GatewayTarget),
+            _ => Err(Error::BadException),
+        }
+    }
+}
+
 /// Modbus function codes
 ///
 /// Documentation source: https://en.wikipedia.org/wiki/Modbus#Supported_function_codes
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FunctionCode {
     /// 0x01 Read Coils
     ReadCoils = 0x01,
@@ -58,16 +126,55 @@ pub enum FunctionCode {
     ReadExceptionStatus = 0x07,
     /// 0x08 Diagnostic
     Diagnostic = 0x08,
-    /// 0x15 Write Multiple Coils
-    WriteMultipleCoils = 0x15,
-    /// 0x16 Write Multiple Holding Registers
-    WriteMultipleRegisters = 0x16,
-    /// 0x17 Report Slave ID
-    ReportSlaveId = 0x17,
-    /// 0x22 Mask Write Register
-    MaskWriteRegister = 0x22,
-    /// 0x23 Read/Write Multiple Registers
-    WriteAndReadRegisters = 0x23,
+    /// 0x0F Write Multiple Coils
+    WriteMultipleCoils = 0x0F,
+    /// 0x10 Write Multiple Holding Registers
+    WriteMultipleRegisters = 0x10,
+    /// 0x11 Report Slave ID
+    ReportSlaveId = 0x11,
+    /// 0x16 Mask Write Register
+    MaskWriteRegister = 0x16,
+    /// 0x17 Read/Write Multiple Registers
+    WriteAndReadRegisters = 0x17,
+}
+
+/// Convert a function code into the enum
+///
+/// Unknown function codes are answered by a server with an
+/// [`Exception::IllegalFunction`](enum.Exception.html#variant.IllegalFunction), the same error is returned here.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::FunctionCode;
+/// use std::convert::TryFrom;
+///
+/// assert_eq!(FunctionCode::try_from(0x10).unwrap(), FunctionCode::WriteMultipleRegisters);
+/// assert!(FunctionCode::try_from(0x42).is_err());
+/// ```
+impl TryFrom<u8> for FunctionCode {
+    type Error = Error;
+
+    fn try_from(code: u8) -> Result<FunctionCode, Error> {
+        use crate::FunctionCode::*;
+
+        match code {
+            0x01 => Ok(ReadCoils),
+            0x02 => Ok(ReadDiscreteInputs),
+            0x03 => Ok(ReadHoldingRegisters),
+            0x04 => Ok(ReadInputRegisters),
+            0x05 => Ok(WriteSingleCoil),
+            0x06 => Ok(WriteSingleRegister),
+            0x07 => Ok(ReadExceptionStatus),
+            0x08 => Ok(Diagnostic),
+            0x0F => Ok(WriteMultipleCoils),
+            0x10 => Ok(WriteMultipleRegisters),
+            0x11 => Ok(ReportSlaveId),
+            0x16 => Ok(MaskWriteRegister),
+            0x17 => Ok(WriteAndReadRegisters),
+            _ => Err(Error::Exception(Exception::IllegalFunction)),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -78,7 +185,7 @@ pub enum ErrorRecoveryMode {
 
 impl ErrorRecoveryMode {
     fn as_raw(&self) -> ffi::modbus_error_recovery_mode {
-        use ErrorRecoveryMode::*;
+        use crate::ErrorRecoveryMode::*;
 
         match *self {
             Link => ffi::modbus_error_recovery_mode_MODBUS_ERROR_RECOVERY_LINK,
@@ -169,6 +276,54 @@ impl Default for Timeout {
     }
 }
 
+/// Convert a `Duration` into a `Timeout`, sub microsecond parts are truncated
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::Timeout;
+/// use std::convert::TryFrom;
+/// use std::time::Duration;
+///
+/// assert_eq!(Timeout::try_from(Duration::from_millis(1500)).unwrap(), Timeout { sec: 1, usec: 500000 });
+/// assert!(Timeout::try_from(Duration::from_secs(u64::MAX)).is_err());
+/// ```
+impl TryFrom<Duration> for Timeout {
+    type Error = Error;
+
+    fn try_from(duration: Duration) -> Result<Timeout, Error> {
+        if duration.as_secs() > u64::from(u32::MAX) {
+            return Err(Error::InvalidTimeout);
+        }
+
+        Ok(Timeout::new(duration.as_secs() as u32, duration.subsec_micros()))
+    }
+}
+
+/// Convert a `Timeout` into a `Duration`
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::Timeout;
+/// use std::time::Duration;
+///
+/// assert_eq!(Duration::from(Timeout { sec: 1, usec: 500000 }), Duration::from_millis(1500));
+/// ```
+impl From<Timeout> for Duration {
+    fn from(timeout: Timeout) -> Duration {
+        Duration::from_secs(u64::from(timeout.sec)) + Duration::from_micros(u64::from(timeout.usec))
+    }
+}
+
+/// Checks the `usec < 1_000_000` rule of libmodbus before a `Timeout` is handed over
+fn check_timeout(timeout: &Timeout) -> Result<(), Error> {
+    if timeout.usec >= 1_000_000 {
+        return Err(Error::InvalidTimeout);
+    }
+    Ok(())
+}
+
 /// Safe interface for [libmodbus](http://libmodbus.org)
 ///
 /// The different parts of libmodbus are implemented as traits. The modules of this crate contains these
@@ -176,10 +331,34 @@ impl Default for Timeout {
 ///
 #[derive(Debug)]
 pub struct Modbus {
-    pub ctx: *mut ffi::modbus_t,
+    pub(crate) ctx: *mut ffi::modbus_t,
+    /// closure set by `rtu_set_custom_rts()`, owned by the context and freed together with it, null if none is set
+    pub(crate) custom_rts: *mut CustomRts,
 }
 
+// A libmodbus context is not bound to the thread which created it, it can be moved to another thread. It must not be
+// used by two threads at the same time, so `Modbus` is not `Sync`.
+unsafe impl Send for Modbus {}
+
 impl Modbus {
+    /// Take ownership of a context returned by one of the `modbus_new_*()` functions, `ctx` must not be null
+    pub(crate) fn from_raw(ctx: *mut ffi::modbus_t) -> Modbus {
+        Modbus { ctx, custom_rts: ptr::null_mut() }
+    }
+
+    /// `as_raw` - get the libmodbus context
+    ///
+    /// The pointer stays owned by this `Modbus`, it is valid as long as the context is not dropped.
+    ///
+    /// # Safety
+    ///
+    /// The context must not be freed through the pointer, and not be used by another thread while this context is
+    /// in use. Connecting or closing it through libmodbus bypasses the state kept by
+    /// [`ModbusConnection`](struct.ModbusConnection.html).
+    pub unsafe fn as_raw(&self) -> *mut ffi::modbus_t {
+        self.ctx
+    }
+
     // Constants
     /// Modbus_Application_Protocol_V1_1b.pdf (chapter 6 section 1 page 12)
     /// Quantity of Coils to read (2 bytes): 1 to 2000 (0x7D0)
@@ -230,9 +409,15 @@ impl Modbus {
     /// The [`connect()`](#method.connect) function shall establish a connection to a Modbus server,
     /// a network or a bus.
     ///
+    /// The context is consumed, all requests are sent through the returned
+    /// [`ModbusConnection`](struct.ModbusConnection.html). Use [`close()`](struct.ModbusConnection.html#method.close)
+    /// to get the context back.
+    ///
     /// # Return value
     ///
-    /// The function return an OK Result if successful. Otherwise it contains an Error.
+    /// The function return a Result containing the connection if successful. Otherwise it contains an
+    /// [`IntoInnerError`](struct.IntoInnerError.html) holding the Error and the context, so the connection can be
+    /// retried.
     ///
     /// # Examples
     ///
@@ -240,46 +425,19 @@ impl Modbus {
     /// use libmodbus_rs::{Modbus, ModbusTCP};
     ///
     /// // create server
-    /// let mut server = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let server = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// // create client
     /// let client = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// // start server in listen mode
-    /// let _ = server.tcp_listen(1).unwrap();
+    /// let _listener = server.tcp_listen(1).unwrap();
     ///
     /// assert!(client.connect().is_ok())
     /// ```
-    pub fn connect(&self) -> Result<(), Error> {
+    pub fn connect(self) -> Result<ModbusConnection, IntoInnerError<Modbus>> {
         unsafe {
             match ffi::modbus_connect(self.ctx) {
-                -1 => bail!(::std::io::Error::last_os_error()),
-                0 => Ok(()),
-                _ => panic!("libmodbus API incompatible response"),
-            }
-        }
-    }
-
-    /// `flush` - flush non-transmitted data
-    ///
-    /// The [`flush()`](#method.flush) function shall discard data received but not read to the socket or file
-    /// descriptor associated to the context ctx.
-    ///
-    /// # Return value
-    ///
-    /// The function return an OK Result if successful. Otherwise it contains an Error.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use libmodbus_rs::{Modbus, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
-    ///
-    /// assert!(modbus.flush().is_ok());
-    /// ```
-    pub fn flush(&self) -> Result<(), Error> {
-        unsafe {
-            match ffi::modbus_flush(self.ctx) {
-                -1 => bail!(::std::io::Error::last_os_error()),
-                0 => Ok(()),
+                -1 => Err(IntoInnerError::new(Error::last_os_error(), self)),
+                0 => Ok(ModbusConnection { modbus: self, listener: None }),
                 _ => panic!("libmodbus API incompatible response"),
             }
         }
@@ -324,7 +482,7 @@ impl Modbus {
     pub fn set_slave(&mut self, slave: u8) -> Result<(), Error> {
         unsafe {
             match ffi::modbus_set_slave(self.ctx, slave as c_int) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -346,7 +504,7 @@ impl Modbus {
     pub fn get_slave(&self) -> Result<u8, Error> {
         unsafe {
             match ffi::modbus_get_slave(self.ctx) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 num => Ok(num as u8),
             }
         }
@@ -386,7 +544,7 @@ impl Modbus {
     pub fn set_debug(&mut self, flag: bool) -> Result<(), Error> {
         unsafe {
             match ffi::modbus_set_debug(self.ctx, flag as c_int) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -416,7 +574,7 @@ impl Modbus {
         let mut timeout = Timeout { sec: 0, usec: 0 };
         unsafe {
             match ffi::modbus_get_byte_timeout(self.ctx, &mut timeout.sec, &mut timeout.usec) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(timeout),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -431,7 +589,7 @@ impl Modbus {
     /// longer than the defined timeout,
     /// an ETIMEDOUT error will be raised by the function waiting for a response.
     ///
-    /// The value of **usec** argument must be in the range 0 to 999999.
+    /// The value of **usec** argument must be in the range 0 to 999999, otherwise `Error::InvalidTimeout` is returned.
     ///
     /// If both **sec** and **usec** are zero, this timeout will not be used at all. In this case,
     /// [`set_byte_timeout()`](#method.set_byte_timeout)
@@ -456,9 +614,11 @@ impl Modbus {
     /// assert!(modbus.set_byte_timeout(timeout).is_ok());
     /// ```
     pub fn set_byte_timeout(&mut self, timeout: Timeout) -> Result<(), Error> {
+        check_timeout(&timeout)?;
+
         unsafe {
             match ffi::modbus_set_byte_timeout(self.ctx, timeout.sec, timeout.usec) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -488,7 +648,7 @@ impl Modbus {
         let mut timeout = Timeout { sec: 0, usec: 0 };
         unsafe {
             match ffi::modbus_get_response_timeout(self.ctx, &mut timeout.sec, &mut timeout.usec) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(timeout),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -510,6 +670,8 @@ impl Modbus {
     /// of the response timeout.
     /// When a byte timeout is set, the response timeout is only used to wait for until the first byte of the response.
     ///
+    /// The value of **usec** must be in the range 0 to 999999, otherwise `Error::InvalidTimeout` is returned.
+    ///
     /// # Return value
     ///
     /// The function return an OK Result if successful. Otherwise it contains an Error.
@@ -527,15 +689,193 @@ impl Modbus {
     /// assert!(modbus.set_response_timeout(timeout).is_ok());
     /// ```
     pub fn set_response_timeout(&mut self, timeout: Timeout) -> Result<(), Error> {
+        check_timeout(&timeout)?;
+
         unsafe {
             match ffi::modbus_set_response_timeout(self.ctx, timeout.sec, timeout.usec) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
         }
     }
 
+    /// `get_indication_timeout` - get timeout used to wait for an indication
+    ///
+    /// The [`get_indication_timeout()`](#method.get_indication_timeout) function shall return the timeout interval
+    /// used by a server to wait for an indication (request) from a client.
+    ///
+    /// # Return value
+    ///
+    /// The function return a Result containing a [`Timeout`](struct.Timeout.html) if successful.
+    /// Otherwise it contains an Error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP, Timeout};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    ///
+    /// assert_eq!(modbus.get_indication_timeout().unwrap(), Timeout { sec: 0, usec: 0 });
+    /// ```
+    pub fn get_indication_timeout(&self) -> Result<Timeout, Error> {
+        let mut timeout = Timeout { sec: 0, usec: 0 };
+        unsafe {
+            match ffi::modbus_get_indication_timeout(self.ctx, &mut timeout.sec, &mut timeout.usec) {
+                -1 => Err(Error::last_os_error()),
+                0 => Ok(timeout),
+                _ => panic!("libmodbus API incompatible response"),
+            }
+        }
+    }
+
+    /// `set_indication_timeout` - set timeout between indications
+    ///
+    /// The [`set_indication_timeout()`](#method.set_indication_timeout) function shall set the timeout interval used
+    /// by a server to wait for a request from a client.
+    /// If the elapsed time for the first byte of an indication is longer than the given timeout,
+    /// [`receive()`](struct.ModbusConnection.html#method.receive) returns `Error::Timeout`, so a server can wake up periodically.
+    ///
+    /// If both **sec** and **usec** are zero (the default), the server waits forever.
+    ///
+    /// The value of **usec** must be in the range 0 to 999999, otherwise `Error::InvalidTimeout` is returned.
+    ///
+    /// # Return value
+    ///
+    /// The function return an OK Result if successful. Otherwise it contains an Error.
+    ///
+    /// # Parameters
+    ///
+    /// * [`Timeout`](struct.Timeout.html)  - Timeout
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP, Timeout};
+    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let timeout = Timeout { sec: 1, usec: 0 };
+    /// assert!(modbus.set_indication_timeout(timeout).is_ok());
+    /// ```
+    pub fn set_indication_timeout(&mut self, timeout: Timeout) -> Result<(), Error> {
+        check_timeout(&timeout)?;
+
+        unsafe {
+            match ffi::modbus_set_indication_timeout(self.ctx, timeout.sec, timeout.usec) {
+                -1 => Err(Error::last_os_error()),
+                0 => Ok(()),
+                _ => panic!("libmodbus API incompatible response"),
+            }
+        }
+    }
+
+    /// `get_byte_timeout_duration` - get the byte timeout as `Duration`
+    ///
+    /// Same as [`get_byte_timeout()`](#method.get_byte_timeout) but converts the result into a
+    /// `std::time::Duration`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// use std::time::Duration;
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    ///
+    /// assert_eq!(modbus.get_byte_timeout_duration().unwrap(), Duration::from_millis(500));
+    /// ```
+    pub fn get_byte_timeout_duration(&self) -> Result<Duration, Error> {
+        self.get_byte_timeout().map(Duration::from)
+    }
+
+    /// `set_byte_timeout_duration` - set the byte timeout from a `Duration`
+    ///
+    /// Same as [`set_byte_timeout()`](#method.set_byte_timeout) but takes a `std::time::Duration`. Sub microsecond
+    /// parts are truncated, a duration with more seconds than fit into a `u32` returns `Error::InvalidTimeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// use std::time::Duration;
+    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    ///
+    /// assert!(modbus.set_byte_timeout_duration(Duration::from_millis(250)).is_ok());
+    /// ```
+    pub fn set_byte_timeout_duration(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.set_byte_timeout(Timeout::try_from(timeout)?)
+    }
+
+    /// `get_response_timeout_duration` - get the response timeout as `Duration`
+    ///
+    /// Same as [`get_response_timeout()`](#method.get_response_timeout) but converts the result into a
+    /// `std::time::Duration`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// use std::time::Duration;
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    ///
+    /// assert_eq!(modbus.get_response_timeout_duration().unwrap(), Duration::from_millis(500));
+    /// ```
+    pub fn get_response_timeout_duration(&self) -> Result<Duration, Error> {
+        self.get_response_timeout().map(Duration::from)
+    }
+
+    /// `set_response_timeout_duration` - set the response timeout from a `Duration`
+    ///
+    /// Same as [`set_response_timeout()`](#method.set_response_timeout) but takes a `std::time::Duration`. Sub microsecond
+    /// parts are truncated, a duration with more seconds than fit into a `u32` returns `Error::InvalidTimeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// use std::time::Duration;
+    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    ///
+    /// assert!(modbus.set_response_timeout_duration(Duration::from_millis(250)).is_ok());
+    /// ```
+    pub fn set_response_timeout_duration(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.set_response_timeout(Timeout::try_from(timeout)?)
+    }
+
+    /// `get_indication_timeout_duration` - get the indication timeout as `Duration`
+    ///
+    /// Same as [`get_indication_timeout()`](#method.get_indication_timeout) but converts the result into a
+    /// `std::time::Duration`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// use std::time::Duration;
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    ///
+    /// assert_eq!(modbus.get_indication_timeout_duration().unwrap(), Duration::from_millis(0));
+    /// ```
+    pub fn get_indication_timeout_duration(&self) -> Result<Duration, Error> {
+        self.get_indication_timeout().map(Duration::from)
+    }
+
+    /// `set_indication_timeout_duration` - set the indication timeout from a `Duration`
+    ///
+    /// Same as [`set_indication_timeout()`](#method.set_indication_timeout) but takes a `std::time::Duration`. Sub microsecond
+    /// parts are truncated, a duration with more seconds than fit into a `u32` returns `Error::InvalidTimeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// use std::time::Duration;
+    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    ///
+    /// assert!(modbus.set_indication_timeout_duration(Duration::from_millis(250)).is_ok());
+    /// ```
+    pub fn set_indication_timeout_duration(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.set_indication_timeout(Timeout::try_from(timeout)?)
+    }
+
     /// `set_error_recovery` - set the error recovery mode
     ///
     /// The [`set_error_recovery()`](#method.set_error_recovery) function shall set the error recovery mode to apply
@@ -585,7 +925,7 @@ impl Modbus {
 
         unsafe {
             match ffi::modbus_set_error_recovery(self.ctx, flags) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -615,7 +955,7 @@ impl Modbus {
 
         unsafe {
             match ffi::modbus_set_socket(self.ctx, socket) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => unreachable!(),
             }
@@ -643,7 +983,7 @@ impl Modbus {
     pub fn get_socket(&self) -> Result<i32, Error> {
         unsafe {
             match ffi::modbus_get_socket(self.ctx) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 socket => Ok(socket),
             }
         }
@@ -670,6 +1010,89 @@ impl Modbus {
         unsafe { ffi::modbus_get_header_length(self.ctx) }
     }
 
+    /// `strerror`  - return the error message
+    ///
+    /// The [`strerror()`](#method.strerror) function shall return a message `String` corresponding to the error number
+    /// specified by the `errnum` argument.
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    ///
+    /// assert_eq!(Modbus::strerror(112345694), "Too many data");
+    /// ```
+    pub fn strerror(errnum: i32) -> String {
+
+        let c_str = unsafe {
+            ::std::ffi::CStr::from_ptr(ffi::modbus_strerror(errnum))
+        };
+        String::from_utf8_lossy(c_str.to_bytes()).into_owned()
+    }
+
+
+
+    /// `free` - free a libmodbus context
+    ///
+    /// The [`free()`](#method.free) function shall free an allocated modbus_t structure.
+    ///
+    /// The context is consumed, so it can not be used or freed again afterwards.
+    ///
+    /// **It should not nessesary to call these function. Because rusts drop trait handles that for you!**
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// modbus.free();
+    /// ```
+    pub fn free(self) {
+        drop(self)
+    }
+}
+
+/// Connected libmodbus context, returned by [`connect()`](struct.Modbus.html#method.connect) and
+/// [`tcp_accept()`](struct.ModbusListener.html#method.tcp_accept)
+///
+/// The client ([`ModbusClient`](trait.ModbusClient.html)) and server ([`ModbusServer`](trait.ModbusServer.html))
+/// functions are only available on a connection. All settings of the underlying [`Modbus`](struct.Modbus.html)
+/// context, like the slave or the timeouts, can still be changed through it.
+///
+/// The connection is closed when it is dropped, or explicitly with [`close()`](#method.close).
+#[derive(Debug)]
+pub struct ModbusConnection {
+    modbus: Modbus,
+    // listening socket the connection was accepted from, closed together with the connection
+    listener: Option<c_int>,
+}
+
+impl ModbusConnection {
+    /// `flush` - flush non-transmitted data
+    ///
+    /// The [`flush()`](#method.flush) function shall discard data received but not read to the socket or file
+    /// descriptor associated to the context ctx.
+    ///
+    /// # Return value
+    ///
+    /// The function return an OK Result if successful. Otherwise it contains an Error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// assert!(modbus.flush().is_ok());
+    /// ```
+    pub fn flush(&self) -> Result<(), Error> {
+        unsafe {
+            match ffi::modbus_flush(self.ctx) {
+                -1 => Err(Error::last_os_error()),
+                0 => Ok(()),
+                _ => panic!("libmodbus API incompatible response"),
+            }
+        }
+    }
+
     /// `reply_exception` - send an exception reponse
     ///
     /// The modbus_reply_exception() function shall send an exception response based on the exception_code in argument.
@@ -702,8 +1125,8 @@ impl Modbus {
     /// # Examples
     ///
     /// ```rust,no_run
-    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// use libmodbus_rs::Exception;
     ///
     /// let request: Vec<u8> = vec![0x01];
@@ -712,69 +1135,180 @@ impl Modbus {
     pub fn reply_exception(&self, request: &[u8], exception_code: Exception) -> Result<i32, Error> {
         unsafe {
             match ffi::modbus_reply_exception(self.ctx, request.as_ptr(), exception_code as c_uint) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len),
             }
         }
     }
 
-
-    /// `strerror`  - return the error message
-    ///
-    /// The [`strerror()`](#method.strerror) function shall return a message `String` corresponding to the error number
-    /// specified by the `errnum` argument.
-    ///
-    /// ```rust
-    /// use libmodbus_rs::{Modbus, ModbusTCP};
-    ///
-    /// assert_eq!(Modbus::strerror(112345694), "Too many data");
-    /// ```
-    pub fn strerror(errnum: i32) -> String {
-
-        let c_str = unsafe {
-            ::std::ffi::CStr::from_ptr(ffi::modbus_strerror(errnum))
-        };
-        String::from_utf8_lossy(c_str.to_bytes()).into_owned()
-    }
-
-
-
     /// `close` - close a Modbus connection
     ///
     /// The [`close()`](#method.close) function shall close the connection established with the backend set in the
     /// context.
     ///
+    /// The connection is consumed and the unconnected context is returned, it can be connected again or freed.
+    ///
     /// **It should not nessesary to call these function. Because rusts drop trait handles that for you!**
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusTCP};
-    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
-    /// modbus.close();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    /// let modbus = modbus.close();
+    /// modbus.free();
     /// ```
-    pub fn close(&self) {
+    pub fn close(self) -> Modbus {
+        let connection = ManuallyDrop::new(self);
+        connection.close_sockets();
+        // the sockets are closed, only the context is left to own
+        unsafe { ptr::read(&connection.modbus) }
+    }
+
+    /// Close the listening socket the connection was accepted on, if any, the connection to the client stays open
+    pub(crate) fn close_listener(&mut self) {
+        if let Some(listener) = self.listener.take() {
+            unsafe { libc::close(listener) };
+        }
+    }
+
+    fn close_sockets(&self) {
         unsafe {
             ffi::modbus_close(self.ctx);
+            if let Some(listener) = self.listener {
+                libc::close(listener);
+            }
         }
     }
+}
 
-    /// `free` - free a libmodbus context
-    ///
-    /// The [`free()`](#method.free) function shall free an allocated modbus_t structure.
+impl Deref for ModbusConnection {
+    type Target = Modbus;
+
+    fn deref(&self) -> &Modbus {
+        &self.modbus
+    }
+}
+
+impl DerefMut for ModbusConnection {
+    fn deref_mut(&mut self) -> &mut Modbus {
+        &mut self.modbus
+    }
+}
+
+impl Drop for ModbusConnection {
+    fn drop(&mut self) {
+        self.close_sockets();
+    }
+}
+
+/// Listening libmodbus context, returned by [`tcp_listen()`](struct.Modbus.html#method.tcp_listen) and
+/// [`tcp_pi_listen()`](struct.Modbus.html#method.tcp_pi_listen)
+///
+/// A client connection is accepted with [`tcp_accept()`](#method.tcp_accept) or
+/// [`tcp_pi_accept()`](#method.tcp_pi_accept). The listening socket is closed when the listener, or the connection
+/// accepted from it, is dropped.
+#[derive(Debug)]
+pub struct ModbusListener {
+    modbus: Modbus,
+    socket: c_int,
+}
+
+impl ModbusListener {
+    /// Start listening, `listen` is the backend specific libmodbus function
+    pub(crate) fn listen(modbus: Modbus, num_connection: c_int,
+                         listen: unsafe extern "C" fn(*mut ffi::modbus_t, c_int) -> c_int)
+                         -> Result<ModbusListener, Error> {
+        unsafe {
+            match listen(modbus.ctx, num_connection) {
+                -1 => Err(Error::last_os_error()),
+                socket => Ok(ModbusListener { modbus, socket }),
+            }
+        }
+    }
+
+    /// Accept a connection, `accept` is the backend specific libmodbus function
+    pub(crate) fn accept(self, accept: unsafe extern "C" fn(*mut ffi::modbus_t, *mut c_int) -> c_int)
+                         -> Result<ModbusConnection, IntoInnerError<ModbusListener>> {
+        let mut socket = self.socket;
+        unsafe {
+            match accept(self.ctx, &mut socket) {
+                -1 => Err(IntoInnerError::new(Error::last_os_error(), self)),
+                _ => {
+                    let listener = ManuallyDrop::new(self);
+                    Ok(ModbusConnection {
+                        modbus: ptr::read(&listener.modbus),
+                        listener: Some(listener.socket),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Move context and listening socket into a connection without accepting a client
+    pub(crate) fn into_connection(self) -> ModbusConnection {
+        let listener = ManuallyDrop::new(self);
+        ModbusConnection {
+            modbus: unsafe { ptr::read(&listener.modbus) },
+            listener: Some(listener.socket),
+        }
+    }
+
+    /// `socket` - get the listening socket
     ///
-    /// **It should not nessesary to call these function. Because rusts drop trait handles that for you!**
+    /// The [`socket()`](#method.socket) function returns the socket the listener waits on for new connections, e.g.
+    /// to watch it with `select()` together with already accepted connections.
     ///
     /// # Examples
     ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusTCP};
+    /// let listener = Modbus::new_tcp("127.0.0.1", 1502).unwrap().tcp_listen(1).unwrap();
+    ///
+    /// assert!(listener.socket() >= 0);
     /// ```
+    pub fn socket(&self) -> i32 {
+        self.socket
+    }
+
+    /// `close` - stop listening
+    ///
+    /// The [`close()`](#method.close) function closes the listening socket and returns the context.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusTCP};
-    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
-    /// modbus.free();
+    /// let listener = Modbus::new_tcp("127.0.0.1", 1502).unwrap().tcp_listen(1).unwrap();
+    /// let modbus = listener.close();
     /// ```
-    pub fn free(&mut self) {
+    pub fn close(self) -> Modbus {
+        let listener = ManuallyDrop::new(self);
         unsafe {
-            ffi::modbus_free(self.ctx);
+            libc::close(listener.socket);
+            ptr::read(&listener.modbus)
+        }
+    }
+}
+
+impl Deref for ModbusListener {
+    type Target = Modbus;
+
+    fn deref(&self) -> &Modbus {
+        &self.modbus
+    }
+}
+
+impl DerefMut for ModbusListener {
+    fn deref_mut(&mut self) -> &mut Modbus {
+        &mut self.modbus
+    }
+}
+
+impl Drop for ModbusListener {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.socket);
         }
     }
 }
@@ -800,12 +1334,12 @@ impl Modbus {
 /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
 /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 /// // before
-/// assert_eq!(modbus_mapping.get_input_bits_mut(), [0u8, 0, 0, 0, 0]);
+/// assert_eq!(modbus_mapping.lock().get_input_bits(), [0u8, 0, 0, 0, 0]);
 ///
-/// set_bits_from_byte(modbus_mapping.get_input_bits_mut(), 2, 0b1111_1111);
+/// set_bits_from_byte(modbus_mapping.lock().get_input_bits_mut(), 2, 0b1111_1111);
 ///
 /// // after
-/// assert_eq!(modbus_mapping.get_input_bits_mut(), [0u8, 0, 1, 1, 1]);
+/// assert_eq!(modbus_mapping.lock().get_input_bits(), [0u8, 0, 1, 1, 1]);
 /// ```
 pub fn set_bits_from_byte(dest: &mut [u8], index: u32, value: u8) {
     unsafe { ffi::modbus_set_bits_from_byte(dest.as_mut_ptr(), index as c_int, value) }
@@ -835,12 +1369,12 @@ pub fn set_bits_from_byte(dest: &mut [u8], index: u32, value: u8) {
 /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 ///
 /// // before
-/// assert_eq!(modbus_mapping.get_input_bits_mut(), [0u8, 0, 0, 0, 0]);
+/// assert_eq!(modbus_mapping.lock().get_input_bits(), [0u8, 0, 0, 0, 0]);
 ///
-/// set_bits_from_bytes(modbus_mapping.get_input_bits_mut(), 0, 2, &[0b0000_1111]);
+/// set_bits_from_bytes(modbus_mapping.lock().get_input_bits_mut(), 0, 2, &[0b0000_1111]);
 ///
 /// // after
-/// assert_eq!(modbus_mapping.get_input_bits_mut(), [1u8, 1, 0, 0, 0]);
+/// assert_eq!(modbus_mapping.lock().get_input_bits(), [1u8, 1, 0, 0, 0]);
 /// ```
 pub fn set_bits_from_bytes(dest: &mut [u8], index: u16, num_bit: u16, bytes: &[u8]) {
     unsafe { ffi::modbus_set_bits_from_bytes(dest.as_mut_ptr(), index as c_int, num_bit as c_uint, bytes.as_ptr()) }
@@ -1081,7 +1615,12 @@ pub fn set_float_dcba(src: f32, dest: &mut [u16]) {
 
 impl Drop for Modbus {
     fn drop(&mut self) {
-        self.close();
-        self.free();
+        modbus_rtu::register_custom_rts(self.ctx, ptr::null_mut());
+        unsafe {
+            ffi::modbus_free(self.ctx);
+            if !self.custom_rts.is_null() {
+                drop(Box::from_raw(self.custom_rts));
+            }
+        }
     }
 }
@@ -0,0 +1,287 @@
+use crate::error::Error;
+use crate::frame::tcp;
+use crate::modbus::Modbus;
+use crate::modbus_request::{bit_values, byte_values, register_values, Request};
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time;
+
+
+/// Response timeout of a new client, the same as the default of libmodbus
+const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_millis(500);
+
+
+/// Modbus TCP client for [tokio](https://tokio.rs), available with the `tokio` feature
+///
+/// The blocking [`ModbusClient`](trait.ModbusClient.html) functions wait inside libmodbus, every connection needs its
+/// own thread. The `AsyncModbusClient` implements the TCP backend in Rust instead, the same requests are `async fn`s
+/// waiting on a tokio [`TcpStream`](https://docs.rs/tokio/1/tokio/net/struct.TcpStream.html). Like that many devices
+/// can be polled from a few threads.
+///
+/// Read functions return the values instead of filling a buffer, like the `_vec` variants of the blocking client.
+/// Errors are reported with the same [`Error`](enum.Error.html) variants, an exception response of the device as
+/// `Error::Exception`, no response within the response timeout as `Error::Timeout`.
+///
+/// One request is sent at a time, a request waits for the response to the previous one. A response arriving after
+/// its request timed out is recognized by its transaction id and discarded.
+///
+/// The response timeout covers sending the request as well. A request interrupted while it is sent, by the timeout or
+/// by dropping its future, leaves a partial frame on the connection. All further requests fail with an
+/// `Error::IoError` then, the connection has to be replaced.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::AsyncModbusClient;
+///
+/// # async fn poll() -> Result<(), libmodbus_rs::Error> {
+/// let mut client = AsyncModbusClient::connect("127.0.0.1:1502").await?;
+/// client.set_slave(1);
+///
+/// let registers = client.read_registers(0, 10).await?;
+/// client.write_register(0, registers[0] + 1).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncModbusClient {
+    stream: TcpStream,
+    decoder: tcp::Decoder,
+    slave: u8,
+    transaction_id: u16,
+    response_timeout: Duration,
+    // set while a request is written, still set if writing was interrupted
+    broken: bool,
+}
+
+impl AsyncModbusClient {
+    /// `connect` - establish a connection to a Modbus TCP server
+    ///
+    /// # Parameters
+    ///
+    /// * `addr`    - address of the server, e.g. `"192.168.0.5:502"`, all resolved addresses are tried in turn
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<AsyncModbusClient, Error> {
+        let stream = TcpStream::connect(addr).await?;
+        AsyncModbusClient::from_stream(stream)
+    }
+
+    /// `from_stream` - create a client on an already connected stream
+    pub fn from_stream(stream: TcpStream) -> Result<AsyncModbusClient, Error> {
+        // like libmodbus, requests are sent without delay
+        stream.set_nodelay(true)?;
+
+        Ok(AsyncModbusClient {
+            stream,
+            decoder: tcp::Decoder::new(),
+            slave: Modbus::TCP_SLAVE,
+            transaction_id: 0,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            broken: false,
+        })
+    }
+
+    /// `get_slave` - get the unit id requests are sent to
+    pub fn get_slave(&self) -> u8 {
+        self.slave
+    }
+
+    /// `set_slave` - set the unit id requests are sent to
+    ///
+    /// The default is [`Modbus::TCP_SLAVE`](struct.Modbus.html#associatedconstant.TCP_SLAVE), a gateway forwarding
+    /// the requests to serial devices needs their slave id. See [`set_slave()`](struct.Modbus.html#method.set_slave)
+    /// of the blocking context.
+    pub fn set_slave(&mut self, slave: u8) {
+        self.slave = slave;
+    }
+
+    /// `get_response_timeout` - get the time to wait for a response
+    pub fn get_response_timeout(&self) -> Duration {
+        self.response_timeout
+    }
+
+    /// `set_response_timeout` - set the time to wait for a response, 500 ms by default
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.response_timeout = timeout;
+    }
+
+    /// `read_bits` - read `num` coils (function code 0x01) starting at `address`
+    ///
+    /// Returns `Error::TooManyData` if `num` exceeds
+    /// [`Modbus::MAX_READ_BITS`](struct.Modbus.html#associatedconstant.MAX_READ_BITS).
+    pub async fn read_bits(&mut self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        check_limit(num as usize, Modbus::MAX_READ_BITS)?;
+        let data = self.execute(&Request::ReadCoils { address, count: num }).await?;
+        bit_values(&data, 0, num as usize)
+    }
+
+    /// `read_input_bits` - read `num` discrete inputs (function code 0x02) starting at `address`
+    ///
+    /// Returns `Error::TooManyData` if `num` exceeds
+    /// [`Modbus::MAX_READ_BITS`](struct.Modbus.html#associatedconstant.MAX_READ_BITS).
+    pub async fn read_input_bits(&mut self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        check_limit(num as usize, Modbus::MAX_READ_BITS)?;
+        let data = self.execute(&Request::ReadDiscreteInputs { address, count: num }).await?;
+        bit_values(&data, 0, num as usize)
+    }
+
+    /// `read_registers` - read `num` holding registers (function code 0x03) starting at `address`
+    ///
+    /// Returns `Error::TooManyData` if `num` exceeds
+    /// [`Modbus::MAX_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_READ_REGISTERS).
+    pub async fn read_registers(&mut self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        check_limit(num as usize, Modbus::MAX_READ_REGISTERS)?;
+        let data = self.execute(&Request::ReadHoldingRegisters { address, count: num }).await?;
+        register_values(&data, 0, num as usize)
+    }
+
+    /// `read_input_registers` - read `num` input registers (function code 0x04) starting at `address`
+    ///
+    /// Returns `Error::TooManyData` if `num` exceeds
+    /// [`Modbus::MAX_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_READ_REGISTERS).
+    pub async fn read_input_registers(&mut self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        check_limit(num as usize, Modbus::MAX_READ_REGISTERS)?;
+        let data = self.execute(&Request::ReadInputRegisters { address, count: num }).await?;
+        register_values(&data, 0, num as usize)
+    }
+
+    /// `report_slave_id` - returns a description of the controller (function code 0x11)
+    ///
+    /// The data following the byte count of the response is returned, usually the slave id, the run indicator
+    /// (`0xFF` for on) and additional device specific data.
+    pub async fn report_slave_id(&mut self) -> Result<Vec<u8>, Error> {
+        let data = self.execute(&Request::ReportSlaveId).await?;
+        let byte_count = *data.first().ok_or(Error::IncompleteFrame)? as usize;
+        Ok(byte_values(&data, 0, byte_count)?.to_vec())
+    }
+
+    /// `write_bit` - write the coil at `address` (function code 0x05)
+    pub async fn write_bit(&mut self, address: u16, status: bool) -> Result<(), Error> {
+        self.execute(&Request::WriteSingleCoil { address, value: status }).await?;
+        Ok(())
+    }
+
+    /// `write_register` - write the holding register at `address` (function code 0x06)
+    pub async fn write_register(&mut self, address: u16, value: u16) -> Result<(), Error> {
+        self.execute(&Request::WriteSingleRegister { address, value }).await?;
+        Ok(())
+    }
+
+    /// `write_bits` - write the coils starting at `address` (function code 0x0F)
+    ///
+    /// Returns the number of written coils, or `Error::TooManyData` if `src` holds more than
+    /// [`Modbus::MAX_WRITE_BITS`](struct.Modbus.html#associatedconstant.MAX_WRITE_BITS) values.
+    pub async fn write_bits(&mut self, address: u16, src: &[bool]) -> Result<u16, Error> {
+        check_limit(src.len(), Modbus::MAX_WRITE_BITS)?;
+        self.execute(&Request::WriteMultipleCoils { address, values: src.to_vec() }).await?;
+        Ok(src.len() as u16)
+    }
+
+    /// `write_registers` - write the holding registers starting at `address` (function code 0x10)
+    ///
+    /// Returns the number of written registers, or `Error::TooManyData` if `src` holds more than
+    /// [`Modbus::MAX_WRITE_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_WRITE_REGISTERS) values.
+    pub async fn write_registers(&mut self, address: u16, src: &[u16]) -> Result<u16, Error> {
+        check_limit(src.len(), Modbus::MAX_WRITE_REGISTERS)?;
+        self.execute(&Request::WriteMultipleRegisters { address, values: src.to_vec() }).await?;
+        Ok(src.len() as u16)
+    }
+
+    /// `write_and_read_registers` - write and read holding registers in a single transaction (function code 0x17)
+    ///
+    /// The values of `src` are written starting at `write_address` first, then `read_num` registers are read
+    /// starting at `read_address`. Returns `Error::TooManyData` if `src` holds more than
+    /// [`Modbus::MAX_WR_WRITE_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_WR_WRITE_REGISTERS) values or
+    /// `read_num` exceeds
+    /// [`Modbus::MAX_WR_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_WR_READ_REGISTERS).
+    pub async fn write_and_read_registers(&mut self, write_address: u16, src: &[u16], read_address: u16,
+                                          read_num: u16)
+                                          -> Result<Vec<u16>, Error> {
+        check_limit(src.len(), Modbus::MAX_WR_WRITE_REGISTERS)?;
+        check_limit(read_num as usize, Modbus::MAX_WR_READ_REGISTERS)?;
+        let request = Request::WriteAndReadRegisters {
+            read_address,
+            read_count: read_num,
+            write_address,
+            values: src.to_vec(),
+        };
+        let data = self.execute(&request).await?;
+        register_values(&data, 0, read_num as usize)
+    }
+
+    /// `mask_write_register` - modify the holding register at `address` (function code 0x16)
+    ///
+    /// The new value is `(current & and_mask) | (or_mask & !and_mask)`.
+    pub async fn mask_write_register(&mut self, address: u16, and_mask: u16, or_mask: u16) -> Result<(), Error> {
+        self.execute(&Request::MaskWriteRegister { address, and_mask, or_mask }).await?;
+        Ok(())
+    }
+
+    /// `is_broken` - whether a request was interrupted while it was sent
+    ///
+    /// The server could not make sense of anything sent after the partial frame, every request fails. Connect a new
+    /// client instead.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Sends a request and waits for the response, returns the checked response data after the function code
+    async fn execute(&mut self, request: &Request) -> Result<Vec<u8>, Error> {
+        if self.broken {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "a previous request was interrupted while sent").into());
+        }
+
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let pdu = request.to_pdu();
+        let frame = tcp::Adu::new(self.transaction_id, self.slave, &pdu).encode()?;
+
+        let response = time::timeout(self.response_timeout, self.send_and_receive(&frame))
+            .await
+            .map_err(|_| Error::Timeout)??;
+        let adu = tcp::Adu::decode(&response)?;
+        if adu.unit_id != self.slave && self.slave != Modbus::BROADCAST_ADDRESS {
+            return Err(Error::BadSlave);
+        }
+
+        Ok(request.check_response(adu.pdu)?.to_vec())
+    }
+
+    /// Writes the request frame and waits for its response, the connection stays broken if the write does not finish
+    async fn send_and_receive(&mut self, frame: &[u8]) -> Result<Vec<u8>, Error> {
+        self.broken = true;
+        self.stream.write_all(frame).await?;
+        self.broken = false;
+
+        // reading is cancel safe, a response still arriving is discarded by the next request
+        self.receive_response().await
+    }
+
+    /// Reads from the stream until the frame with the transaction id of the current request is complete
+    async fn receive_response(&mut self) -> Result<Vec<u8>, Error> {
+        let mut buffer = [0u8; Modbus::TCP_MAX_ADU_LENGTH];
+
+        loop {
+            while let Some(frame) = self.decoder.next_frame()? {
+                // responses to requests which timed out are dropped
+                if tcp::Header::decode(&frame)?.transaction_id == self.transaction_id {
+                    return Ok(frame);
+                }
+            }
+
+            let len = self.stream.read(&mut buffer).await?;
+            if len == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by the server").into());
+            }
+            self.decoder.push(&buffer[..len]);
+        }
+    }
+}
+
+/// Checks the number of values of a request against the protocol limit
+fn check_limit(num: usize, max: u32) -> Result<(), Error> {
+    if num > max as usize {
+        return Err(Error::TooManyData);
+    }
+    Ok(())
+}
@@ -0,0 +1,344 @@
+use crate::error::Error;
+use crate::frame::tcp;
+use crate::modbus::{Exception, Modbus};
+use crate::modbus_handler::{check_request, response_pdu, RequestHandler};
+use crate::modbus_mapping::ModbusMapping;
+use crate::modbus_request::Request;
+use crate::modbus_response::Response;
+use std::future::{self, Future};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::task::JoinSet;
+
+
+/// Serves requests of an [`AsyncTcpServer`](struct.AsyncTcpServer.html), the async counterpart of
+/// [`RequestHandler`](trait.RequestHandler.html)
+///
+/// Every method handles one Modbus function and may wait, for example for a database or another device. The default
+/// implementations answer with [`Exception::IllegalFunction`](enum.Exception.html#variant.IllegalFunction), returning
+/// an `Exception` sends it as exception response to the client.
+///
+/// The handler is shared by all connections of the server, so the methods take `&self`, state has to be kept behind
+/// a lock. The methods can be implemented as `async fn`, the returned futures have to be `Send`.
+///
+/// A [`ModbusMapping`](struct.ModbusMapping.html) is a handler answering with its tables, like
+/// [`reply()`](struct.ModbusConnection.html#method.reply).
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{AsyncRequestHandler, Exception};
+/// use std::sync::atomic::{AtomicU16, Ordering};
+///
+/// struct Counter {
+///     count: AtomicU16,
+/// }
+///
+/// impl AsyncRequestHandler for Counter {
+///     async fn read_input_registers(&self, address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+///         match (address, count) {
+///             (0, 1) => Ok(vec![self.count.fetch_add(1, Ordering::SeqCst)]),
+///             _ => Err(Exception::IllegalDataAddress),
+///         }
+///     }
+/// }
+/// ```
+pub trait AsyncRequestHandler: Send + Sync {
+    /// 0x01 Read Coils, returns `count` values
+    fn read_coils(&self, _address: u16, _count: u16) -> impl Future<Output = Result<Vec<bool>, Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x02 Read Discrete Inputs, returns `count` values
+    fn read_discrete_inputs(&self, _address: u16, _count: u16)
+                            -> impl Future<Output = Result<Vec<bool>, Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x03 Read Multiple Holding Registers, returns `count` values
+    fn read_holding_registers(&self, _address: u16, _count: u16)
+                              -> impl Future<Output = Result<Vec<u16>, Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x04 Read Input Registers, returns `count` values
+    fn read_input_registers(&self, _address: u16, _count: u16)
+                            -> impl Future<Output = Result<Vec<u16>, Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x05 Write Single Coil
+    fn write_single_coil(&self, _address: u16, _value: bool) -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x06 Write Single Holding Register
+    fn write_single_register(&self, _address: u16, _value: u16)
+                             -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x0F Write Multiple Coils
+    fn write_multiple_coils(&self, _address: u16, _values: &[bool])
+                            -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x10 Write Multiple Holding Registers
+    fn write_multiple_registers(&self, _address: u16, _values: &[u16])
+                                -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x11 Report Slave ID, returns the data following the byte count of the response
+    fn report_slave_id(&self) -> impl Future<Output = Result<Vec<u8>, Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x16 Mask Write Register, the new value is `(current & and_mask) | (or_mask & !and_mask)`
+    fn mask_write_register(&self, _address: u16, _and_mask: u16, _or_mask: u16)
+                           -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+
+    /// 0x17 Read/Write Multiple Registers, writes `values` first and returns the `read_count` values read afterwards
+    fn write_and_read_registers(&self, _read_address: u16, _read_count: u16, _write_address: u16, _values: &[u16])
+                                -> impl Future<Output = Result<Vec<u16>, Exception>> + Send {
+        future::ready(Err(Exception::IllegalFunction))
+    }
+}
+
+/// The tables are locked for each request, the lock is never held while waiting
+impl AsyncRequestHandler for ModbusMapping {
+    fn read_coils(&self, address: u16, count: u16) -> impl Future<Output = Result<Vec<bool>, Exception>> + Send {
+        future::ready(self.lock().read_coils(address, count))
+    }
+
+    fn read_discrete_inputs(&self, address: u16, count: u16)
+                            -> impl Future<Output = Result<Vec<bool>, Exception>> + Send {
+        future::ready(self.lock().read_discrete_inputs(address, count))
+    }
+
+    fn read_holding_registers(&self, address: u16, count: u16)
+                              -> impl Future<Output = Result<Vec<u16>, Exception>> + Send {
+        future::ready(self.lock().read_holding_registers(address, count))
+    }
+
+    fn read_input_registers(&self, address: u16, count: u16)
+                            -> impl Future<Output = Result<Vec<u16>, Exception>> + Send {
+        future::ready(self.lock().read_input_registers(address, count))
+    }
+
+    fn write_single_coil(&self, address: u16, value: bool) -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(self.lock().write_single_coil(address, value))
+    }
+
+    fn write_single_register(&self, address: u16, value: u16)
+                             -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(self.lock().write_single_register(address, value))
+    }
+
+    fn write_multiple_coils(&self, address: u16, values: &[bool])
+                            -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(self.lock().write_multiple_coils(address, values))
+    }
+
+    fn write_multiple_registers(&self, address: u16, values: &[u16])
+                                -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(self.lock().write_multiple_registers(address, values))
+    }
+
+    fn report_slave_id(&self) -> impl Future<Output = Result<Vec<u8>, Exception>> + Send {
+        future::ready(self.lock().report_slave_id())
+    }
+
+    fn mask_write_register(&self, address: u16, and_mask: u16, or_mask: u16)
+                           -> impl Future<Output = Result<(), Exception>> + Send {
+        future::ready(self.lock().mask_write_register(address, and_mask, or_mask))
+    }
+
+    fn write_and_read_registers(&self, read_address: u16, read_count: u16, write_address: u16, values: &[u16])
+                                -> impl Future<Output = Result<Vec<u16>, Exception>> + Send {
+        future::ready(self.lock().write_and_read_registers(read_address, read_count, write_address, values))
+    }
+}
+
+/// Calls the handler for a request and builds the response PDU, or the exception to answer with
+async fn dispatch<H: AsyncRequestHandler>(handler: &H, request: &Request) -> Result<Vec<u8>, Exception> {
+    check_request(request)?;
+
+    let result = match *request {
+        Request::ReadCoils { address, count } => handler.read_coils(address, count).await.map(Response::Bits),
+        Request::ReadDiscreteInputs { address, count } => {
+            handler.read_discrete_inputs(address, count).await.map(Response::Bits)
+        },
+        Request::ReadHoldingRegisters { address, count } => {
+            handler.read_holding_registers(address, count).await.map(Response::Registers)
+        },
+        Request::ReadInputRegisters { address, count } => {
+            handler.read_input_registers(address, count).await.map(Response::Registers)
+        },
+        Request::WriteSingleCoil { address, value } => {
+            handler.write_single_coil(address, value).await.map(|_| Response::Written)
+        },
+        Request::WriteSingleRegister { address, value } => {
+            handler.write_single_register(address, value).await.map(|_| Response::Written)
+        },
+        Request::WriteMultipleCoils { address, ref values } => {
+            handler.write_multiple_coils(address, values).await.map(|_| Response::Written)
+        },
+        Request::WriteMultipleRegisters { address, ref values } => {
+            handler.write_multiple_registers(address, values).await.map(|_| Response::Written)
+        },
+        Request::ReportSlaveId => handler.report_slave_id().await.map(Response::Data),
+        Request::MaskWriteRegister { address, and_mask, or_mask } => {
+            handler.mask_write_register(address, and_mask, or_mask).await.map(|_| Response::Written)
+        },
+        Request::WriteAndReadRegisters { read_address, read_count, write_address, ref values } => {
+            handler.write_and_read_registers(read_address, read_count, write_address, values)
+                   .await
+                   .map(Response::Registers)
+        },
+        Request::Other { .. } => return Err(Exception::IllegalFunction),
+    };
+
+    response_pdu(request, result)
+}
+
+/// Modbus TCP server for [tokio](https://tokio.rs), available with the `tokio` feature
+///
+/// The async counterpart of [`TcpServer`](struct.TcpServer.html): every accepted connection is served by its own
+/// task, all connections share one [`ModbusMapping`](struct.ModbusMapping.html) or
+/// [`AsyncRequestHandler`](trait.AsyncRequestHandler.html). The TCP backend is implemented in Rust, no libmodbus
+/// context and no blocking thread is involved.
+///
+/// Invalid requests are answered with the same exceptions as by libmodbus, requests with malformed data with
+/// [`Exception::IllegalDataValue`](enum.Exception.html#variant.IllegalDataValue) and unknown functions with
+/// [`Exception::IllegalFunction`](enum.Exception.html#variant.IllegalFunction). A connection sending an invalid MBAP
+/// header is closed.
+///
+/// The server runs until the future returned by [`serve()`](#method.serve) or [`serve_with()`](#method.serve_with)
+/// is dropped, dropping it closes all connections. Use for example `tokio::select!` with a shutdown signal.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{AsyncTcpServer, ModbusMapping};
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mapping = Arc::new(ModbusMapping::new(500, 500, 500, 500).unwrap());
+///     let server = AsyncTcpServer::bind("0.0.0.0:1502").await.unwrap();
+///
+///     tokio::select! {
+///         result = server.serve(mapping) => result.unwrap(),
+///         _ = tokio::signal::ctrl_c() => println!("Server stopped"),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncTcpServer {
+    listener: TcpListener,
+}
+
+impl AsyncTcpServer {
+    /// `bind` - create a server listening on the given address
+    ///
+    /// # Parameters
+    ///
+    /// * `addr`    - local address, e.g. `"0.0.0.0:502"` for all IPv4 interfaces
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> Result<AsyncTcpServer, Error> {
+        Ok(AsyncTcpServer::from_listener(TcpListener::bind(addr).await?))
+    }
+
+    /// `from_listener` - create a server accepting connections from an already bound listener
+    pub fn from_listener(listener: TcpListener) -> AsyncTcpServer {
+        AsyncTcpServer { listener }
+    }
+
+    /// Local address the server is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// `serve` - answer the requests of all clients with the tables of a mapping
+    ///
+    /// The mapping can be updated by the application with [`lock()`](struct.ModbusMapping.html#method.lock) while
+    /// the server is running.
+    ///
+    /// # Return value
+    ///
+    /// The function only returns if accepting a connection fails, with the Error of the listening socket.
+    pub async fn serve(&self, modbus_mapping: Arc<ModbusMapping>) -> Result<(), Error> {
+        self.serve_with(modbus_mapping).await
+    }
+
+    /// `serve_with` - answer the requests of all clients with a handler
+    ///
+    /// Same as [`serve()`](#method.serve), the requests of all connections are passed to the same handler. Requests
+    /// of different connections are handled concurrently, the requests of one connection one after the other.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{AsyncRequestHandler, AsyncTcpServer, Exception};
+    /// use std::sync::Arc;
+    ///
+    /// struct Zeros;
+    ///
+    /// impl AsyncRequestHandler for Zeros {
+    ///     async fn read_holding_registers(&self, _address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+    ///         Ok(vec![0; count as usize])
+    ///     }
+    /// }
+    ///
+    /// # async fn run() {
+    /// let server = AsyncTcpServer::bind("0.0.0.0:1502").await.unwrap();
+    /// server.serve_with(Arc::new(Zeros)).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn serve_with<H: AsyncRequestHandler + 'static>(&self, handler: Arc<H>) -> Result<(), Error> {
+        // the connections are aborted when the set is dropped together with this future
+        let mut connections = JoinSet::new();
+
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            while connections.try_join_next().is_some() {}
+            connections.spawn(serve_connection(stream, handler.clone()));
+        }
+    }
+}
+
+/// Receives and answers the requests of one client until it disconnects or an error occurs
+async fn serve_connection<H: AsyncRequestHandler>(mut stream: TcpStream, handler: Arc<H>) -> Result<(), Error> {
+    stream.set_nodelay(true)?;
+    let mut decoder = tcp::Decoder::new();
+    let mut buffer = [0u8; Modbus::TCP_MAX_ADU_LENGTH];
+
+    loop {
+        while let Some(frame) = decoder.next_frame()? {
+            let adu = tcp::Adu::decode(&frame)?;
+            let pdu = match Request::from_pdu(adu.pdu) {
+                Ok(request) => {
+                    match dispatch(&*handler, &request).await {
+                        Ok(pdu) => pdu,
+                        Err(exception) => vec![request.function() | 0x80, exception as u8],
+                    }
+                },
+                // malformed data after the function code
+                Err(_) => vec![adu.pdu[0] | 0x80, Exception::IllegalDataValue as u8],
+            };
+            let response = tcp::Adu::new(adu.transaction_id, adu.unit_id, &pdu).encode()?;
+            stream.write_all(&response).await?;
+        }
+
+        let len = stream.read(&mut buffer).await?;
+        if len == 0 {
+            return Ok(());
+        }
+        decoder.push(&buffer[..len]);
+    }
+}
@@ -1,31 +1,40 @@
+use crate::error::Error;
+use crate::modbus::{Exception, Modbus, ModbusConnection};
 use libc::c_int;
 use libmodbus_sys as ffi;
-use modbus::Modbus;
-use failure::Error;
+use std::convert::TryFrom;
 
 
 /// The Modbus protocol defines different data types and functions to read and write them from/to remote devices.
 /// The following functions are used by the clients to send Modbus requests:
 ///
 /// * Read data
-///     - [`read_bits()`](struct.Modbus.html#method.read_bits),
-/// [`read_input_bits()`](struct.Modbus.html#method.read_input_bits),
-/// [`read_registers()`](struct.Modbus.html#method.read_registers),
-/// [`read_input_registers()`](struct.Modbus.html#method.read_input_registers),
-/// [`report_slave_id()`](struct.Modbus.html#method.report_slave_id)
+///     - [`read_bits()`](struct.ModbusConnection.html#method.read_bits),
+/// [`read_input_bits()`](struct.ModbusConnection.html#method.read_input_bits),
+/// [`read_registers()`](struct.ModbusConnection.html#method.read_registers),
+/// [`read_input_registers()`](struct.ModbusConnection.html#method.read_input_registers),
+/// [`report_slave_id()`](struct.ModbusConnection.html#method.report_slave_id)
 /// * Write data
-///     - [`write_bit()`](struct.Modbus.html#method.write_bit),
-/// [`write_register()`](struct.Modbus.html#method.write_register),
-/// [`write_bits()`](struct.Modbus.html#method.write_bits),
-/// [`write_registers()`](struct.Modbus.html#method.write_registers)
+///     - [`write_bit()`](struct.ModbusConnection.html#method.write_bit),
+/// [`write_register()`](struct.ModbusConnection.html#method.write_register),
+/// [`write_bits()`](struct.ModbusConnection.html#method.write_bits),
+/// [`write_registers()`](struct.ModbusConnection.html#method.write_registers)
 /// * Write and read data
-///     - [`write_and_read_registers()`](struct.Modbus.html#method.write_and_read_registers)
+///     - [`write_and_read_registers()`](struct.ModbusConnection.html#method.write_and_read_registers)
+/// * Read and write spans beyond the protocol limits, split into several requests
+///     - [`read_bits_range()`](struct.ModbusConnection.html#method.read_bits_range),
+///       [`read_input_bits_range()`](struct.ModbusConnection.html#method.read_input_bits_range),
+///       [`read_registers_range()`](struct.ModbusConnection.html#method.read_registers_range),
+///       [`read_input_registers_range()`](struct.ModbusConnection.html#method.read_input_registers_range),
+///       [`write_bits_range()`](struct.ModbusConnection.html#method.write_bits_range),
+///       [`write_registers_range()`](struct.ModbusConnection.html#method.write_registers_range)
 /// * Raw requests
-///     - [`send_raw_request()`](struct.Modbus.html#method.send_raw_request),
-/// [`receive_confirmation()`](struct.Modbus.html#method.receive_confirmation)
+///     - [`send_raw_request()`](struct.ModbusConnection.html#method.send_raw_request),
+/// [`receive_confirmation()`](struct.ModbusConnection.html#method.receive_confirmation)
 /// * Reply an exception
-///     - [`reply_exception()`](struct.Modbus.html#method.reply_exception)
+///     - [`reply_exception()`](struct.ModbusConnection.html#method.reply_exception)
 ///
+/// Implementors provide the single requests, the `_vec` and `_range` functions are built on top of them.
 pub trait ModbusClient {
     fn read_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error>;
     fn read_input_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error>;
@@ -42,10 +51,336 @@ pub trait ModbusClient {
     fn mask_write_register(&self, address: u16, and_mask: u16, or_mask: u16) -> Result<(), Error>;
     fn send_raw_request(&self, raw_request: &mut [u8], lenght: usize) -> Result<u16, Error>;
     fn receive_confirmation(&self, response: &mut [u8]) -> Result<u16, Error>;
+
+    /// `read_bits_vec` - read many bits into an owned vector
+    ///
+    /// Same as [`read_bits()`](#method.read_bits) but allocates the destination itself, so it can never be too small.
+    ///
+    /// # Return value
+    ///
+    /// The function returns a `Result` containing the bits (coils) as `bool` values, the vector is truncated to the number of
+    /// values the device returned. Otherwise it returns an Error.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - address of the remote device
+    /// * `num`     - number of coils to read
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let bits = modbus.read_bits_vec(0, 10).unwrap();
+    /// assert_eq!(bits.len(), 10);
+    /// ```
+    fn read_bits_vec(&self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        let mut dest = vec![0u8; num as usize];
+        let len = self.read_bits(address, num, &mut dest)?;
+        dest.truncate(len as usize);
+
+        Ok(dest.into_iter().map(|bit| bit != 0).collect())
+    }
+
+    /// `read_input_bits_vec` - read many input bits into an owned vector
+    ///
+    /// Same as [`read_input_bits()`](#method.read_input_bits) but allocates the destination itself, so it can never be too small.
+    ///
+    /// # Return value
+    ///
+    /// The function returns a `Result` containing the input bits as `bool` values, the vector is truncated to the number of
+    /// values the device returned. Otherwise it returns an Error.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - address of the remote device
+    /// * `num`     - number of input bits to read
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let bits = modbus.read_input_bits_vec(0, 10).unwrap();
+    /// assert_eq!(bits.len(), 10);
+    /// ```
+    fn read_input_bits_vec(&self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        let mut dest = vec![0u8; num as usize];
+        let len = self.read_input_bits(address, num, &mut dest)?;
+        dest.truncate(len as usize);
+
+        Ok(dest.into_iter().map(|bit| bit != 0).collect())
+    }
+
+    /// `read_registers_vec` - read many registers into an owned vector
+    ///
+    /// Same as [`read_registers()`](#method.read_registers) but allocates the destination itself, so it can never be too small.
+    ///
+    /// # Return value
+    ///
+    /// The function returns a `Result` containing the holding registers, the vector is truncated to the number of
+    /// values the device returned. Otherwise it returns an Error.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - address of the remote device
+    /// * `num`     - number of holding registers to read
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let registers = modbus.read_registers_vec(0, 10).unwrap();
+    /// assert_eq!(registers.len(), 10);
+    /// ```
+    fn read_registers_vec(&self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        let mut dest = vec![0u16; num as usize];
+        let len = self.read_registers(address, num, &mut dest)?;
+        dest.truncate(len as usize);
+
+        Ok(dest)
+    }
+
+    /// `read_input_registers_vec` - read many input registers into an owned vector
+    ///
+    /// Same as [`read_input_registers()`](#method.read_input_registers) but allocates the destination itself, so it can never be too small.
+    ///
+    /// # Return value
+    ///
+    /// The function returns a `Result` containing the input registers, the vector is truncated to the number of
+    /// values the device returned. Otherwise it returns an Error.
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - address of the remote device
+    /// * `num`     - number of input registers to read
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let registers = modbus.read_input_registers_vec(0, 10).unwrap();
+    /// assert_eq!(registers.len(), 10);
+    /// ```
+    fn read_input_registers_vec(&self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        let mut dest = vec![0u16; num as usize];
+        let len = self.read_input_registers(address, num, &mut dest)?;
+        dest.truncate(len as usize);
+
+        Ok(dest)
+    }
+
+    /// `read_bits_range` - read any number of bits (coils), split into several requests
+    ///
+    /// Same as [`read_bits_vec()`](#method.read_bits_vec), but `num` may exceed
+    /// [`Modbus::MAX_READ_BITS`](struct.Modbus.html#associatedconstant.MAX_READ_BITS). The span is read with as few
+    /// requests as possible, one after the other, and the values are returned in one vector.
+    ///
+    /// # Return value
+    ///
+    /// The function returns a `Result` containing the `num` bits if successful. If a request fails, the Error is
+    /// [`Error::ChunkFailed`](enum.Error.html#variant.ChunkFailed) with the address and number of values of the failed
+    /// request and its Error, the remaining requests are not sent. If the span would extend beyond the last address
+    /// the Error is [`Error::TooManyData`](enum.Error.html#variant.TooManyData).
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - address of the remote device
+    /// * `num`     - number of bits to read
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let bits = modbus.read_bits_range(0, 10_000).unwrap();
+    /// assert_eq!(bits.len(), 10_000);
+    /// ```
+    fn read_bits_range(&self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        read_range(address, num, Modbus::MAX_READ_BITS, |address, count| self.read_bits_vec(address, count))
+    }
+
+    /// `read_input_bits_range` - read any number of input bits, split into several requests
+    ///
+    /// Same as [`read_bits_range()`](#method.read_bits_range) for the input bits, with requests of up to
+    /// [`Modbus::MAX_READ_BITS`](struct.Modbus.html#associatedconstant.MAX_READ_BITS) values.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let bits = modbus.read_input_bits_range(0, 10_000).unwrap();
+    /// assert_eq!(bits.len(), 10_000);
+    /// ```
+    fn read_input_bits_range(&self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        read_range(address, num, Modbus::MAX_READ_BITS, |address, count| self.read_input_bits_vec(address, count))
+    }
+
+    /// `read_registers_range` - read any number of holding registers, split into several requests
+    ///
+    /// Same as [`read_bits_range()`](#method.read_bits_range) for the holding registers, with requests of up to
+    /// [`Modbus::MAX_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_READ_REGISTERS) values.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Error, Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// match modbus.read_registers_range(0, 2000) {
+    ///     Ok(registers) => assert_eq!(registers.len(), 2000),
+    ///     Err(Error::ChunkFailed { address, count, error }) => {
+    ///         println!("reading {} registers at {} failed: {}", count, address, error)
+    ///     },
+    ///     Err(err) => println!("{}", err),
+    /// }
+    /// ```
+    fn read_registers_range(&self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        read_range(address, num, Modbus::MAX_READ_REGISTERS, |address, count| self.read_registers_vec(address, count))
+    }
+
+    /// `read_input_registers_range` - read any number of input registers, split into several requests
+    ///
+    /// Same as [`read_bits_range()`](#method.read_bits_range) for the input registers, with requests of up to
+    /// [`Modbus::MAX_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_READ_REGISTERS) values.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let registers = modbus.read_input_registers_range(0, 2000).unwrap();
+    /// assert_eq!(registers.len(), 2000);
+    /// ```
+    fn read_input_registers_range(&self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        read_range(address, num, Modbus::MAX_READ_REGISTERS, |address, count| {
+            self.read_input_registers_vec(address, count)
+        })
+    }
+
+    /// `write_bits_range` - write any number of bits (coils), split into several requests
+    ///
+    /// Same as [`write_bits()`](#method.write_bits), but `src` may hold more than
+    /// [`Modbus::MAX_WRITE_BITS`](struct.Modbus.html#associatedconstant.MAX_WRITE_BITS) values. The values are
+    /// written with as few requests as possible, one after the other.
+    ///
+    /// # Return value
+    ///
+    /// The function returns an Ok Result if all values were written. If a request fails, the Error is
+    /// [`Error::ChunkFailed`](enum.Error.html#variant.ChunkFailed) with the address and number of values of the failed
+    /// request and its Error. The values before were written, the remaining requests are not sent. If the span would
+    /// extend beyond the last address the Error is [`Error::TooManyData`](enum.Error.html#variant.TooManyData).
+    ///
+    /// # Parameters
+    ///
+    /// * `address` - address of the remote device
+    /// * `src`     - the bits to write, one per `u8` like for `write_bits()`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// assert!(modbus.write_bits_range(0, &vec![1u8; 5000]).is_ok());
+    /// ```
+    fn write_bits_range(&self, address: u16, src: &[u8]) -> Result<(), Error> {
+        let mut values = src.iter();
+        for (address, count) in chunks(address, src.len(), Modbus::MAX_WRITE_BITS)? {
+            let chunk: Vec<u8> = values.by_ref().take(count as usize).cloned().collect();
+            self.write_bits(address, count, &chunk).map_err(chunk_failed(address, count))?;
+        }
+
+        Ok(())
+    }
+
+    /// `write_registers_range` - write any number of holding registers, split into several requests
+    ///
+    /// Same as [`write_bits_range()`](#method.write_bits_range) for the holding registers, with requests of up to
+    /// [`Modbus::MAX_WRITE_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_WRITE_REGISTERS) values.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// assert!(modbus.write_registers_range(0, &vec![0u16; 2000]).is_ok());
+    /// ```
+    fn write_registers_range(&self, address: u16, src: &[u16]) -> Result<(), Error> {
+        let mut offset = 0;
+        for (address, count) in chunks(address, src.len(), Modbus::MAX_WRITE_REGISTERS)? {
+            let chunk = &src[offset..offset + count as usize];
+            self.write_registers(address, count, chunk).map_err(chunk_failed(address, count))?;
+            offset += count as usize;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks `num` against the protocol limit `max` and makes sure the slice holds at least `num` elements
+///
+/// libmodbus writes (or reads) `num` elements through the raw pointer without knowing the slice length, so this
+/// must be called before every FFI call that takes a buffer.
+fn check_buffer(num: u16, max: u32, len: usize) -> Result<(), Error> {
+    if u32::from(num) > max {
+        return Err(Error::TooManyData);
+    }
+    check_len(num as usize, len)
+}
+
+/// Splits `num` values starting at `address` into spans of at most `max` values
+///
+/// Fails with `Error::TooManyData` if the values would extend beyond the last address.
+fn chunks(address: u16, num: usize, max: u32) -> Result<impl Iterator<Item = (u16, u16)>, Error> {
+    if address as usize + num > 0x10000 {
+        return Err(Error::TooManyData);
+    }
+    let max = max as usize;
+
+    Ok((0..num).step_by(max).map(move |offset| ((address as usize + offset) as u16, max.min(num - offset) as u16)))
+}
+
+/// Wraps the Error of a chunk, so the caller knows which request failed
+pub(crate) fn chunk_failed(address: u16, count: u16) -> impl FnOnce(Error) -> Error {
+    move |error| Error::ChunkFailed { address, count, error: Box::new(error) }
+}
+
+/// Reads a span chunk by chunk, a chunk returning fewer values than requested is an `Error::BadData`
+fn read_range<T, F>(address: u16, num: u16, max: u32, mut read: F) -> Result<Vec<T>, Error>
+    where F: FnMut(u16, u16) -> Result<Vec<T>, Error>
+{
+    let mut values = Vec::with_capacity(num as usize);
+    for (address, count) in chunks(address, num as usize, max)? {
+        let chunk = read(address, count)
+            .and_then(|chunk| if chunk.len() == count as usize { Ok(chunk) } else { Err(Error::BadData) })
+            .map_err(chunk_failed(address, count))?;
+        values.extend(chunk);
+    }
+
+    Ok(values)
+}
+
+fn check_len(required: usize, available: usize) -> Result<(), Error> {
+    if available < required {
+        return Err(Error::BufferTooSmall { required, available });
+    }
+    Ok(())
 }
 
 // TODO: add real, working examples
-impl ModbusClient for Modbus {
+impl ModbusClient for ModbusConnection {
     /// `read_bits` - read many bits
     ///
     /// The [`read_bits()`](#method.read_bits) function shall read the status of the `num` bits (coils) to the
@@ -57,6 +392,9 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a `Result` containing the number of read bits if successful. Otherwise it returns an Error.
+    /// If `num` exceeds [`Modbus::MAX_READ_BITS`](struct.Modbus.html#associatedconstant.MAX_READ_BITS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `dest` holds less than `num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -68,15 +406,17 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let mut dest = vec![0u8; 100];
     ///
     /// assert!(modbus.read_bits(0, 1, &mut dest).is_ok());
     /// ```
     fn read_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error> {
+        check_buffer(num, Modbus::MAX_READ_BITS, dest.len())?;
+
         unsafe {
             match ffi::modbus_read_bits(self.ctx, address as c_int, num as c_int, dest.as_mut_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len as u16),
             }
         }
@@ -93,6 +433,9 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a `Result` containing the number of read bits if successful. Otherwise it returns an Error.
+    /// If `num` exceeds [`Modbus::MAX_READ_BITS`](struct.Modbus.html#associatedconstant.MAX_READ_BITS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `dest` holds less than `num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -104,15 +447,17 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let mut dest = vec![0u8; 100];
     ///
     /// assert!(modbus.read_input_bits(0, 1, &mut dest).is_ok());
     /// ```
     fn read_input_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error> {
+        check_buffer(num, Modbus::MAX_READ_BITS, dest.len())?;
+
         unsafe {
             match ffi::modbus_read_input_bits(self.ctx, address as c_int, num as c_int, dest.as_mut_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len as u16),
             }
         }
@@ -128,6 +473,9 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a `Result` containing the number of read bits if successful. Otherwise it returns an Error.
+    /// If `num` exceeds [`Modbus::MAX_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_READ_REGISTERS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `dest` holds less than `num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -139,15 +487,17 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let mut dest = vec![0u16; 100];
     ///
     /// assert!(modbus.read_registers(0, 1, &mut dest).is_ok());
     /// ```
     fn read_registers(&self, address: u16, num: u16, dest: &mut [u16]) -> Result<u16, Error> {
+        check_buffer(num, Modbus::MAX_READ_REGISTERS, dest.len())?;
+
         unsafe {
             match ffi::modbus_read_registers(self.ctx, address as c_int, num as c_int, dest.as_mut_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len as u16),
             }
         }
@@ -165,6 +515,9 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a `Result` containing the number of read bits if successful. Otherwise it returns an Error.
+    /// If `num` exceeds [`Modbus::MAX_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_READ_REGISTERS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `dest` holds less than `num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -176,15 +529,17 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let mut dest = vec![0u16; 100];
     ///
     /// assert!(modbus.read_input_registers(0, 1, &mut dest).is_ok());
     /// ```
     fn read_input_registers(&self, address: u16, num: u16, dest: &mut [u16]) -> Result<u16, Error> {
+        check_buffer(num, Modbus::MAX_READ_REGISTERS, dest.len())?;
+
         unsafe {
             match ffi::modbus_read_input_registers(self.ctx, address as c_int, num as c_int, dest.as_mut_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len as u16),
             }
         }
@@ -205,7 +560,8 @@ impl ModbusClient for Modbus {
     /// The function returns a `Result` containing the number of read bits if successful. If the output was truncated
     /// due the `max_dest` limit then the return value is the number of bytes which would have been written to `dest`.
     /// Thus, a return value greater than the `max_dest` means that the resonse data was truncated.
-    /// Otherwise the Result contains an Error.
+    /// Otherwise the Result contains an Error. If `dest` holds less than `max_dest` bytes the Error is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -216,17 +572,18 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let mut bytes = vec![0u8; Modbus::MAX_PDU_LENGTH];
     ///
     /// assert!(modbus.report_slave_id(Modbus::MAX_PDU_LENGTH, &mut bytes).is_ok());
     /// // assert_eq!(bytes, vec![180, 255, 76, 77, 66, 51, 46, 49, 46, 52]));
     /// ```
     fn report_slave_id(&self, max_dest: usize, dest: &mut [u8]) -> Result<u16, Error> {
+        check_len(max_dest, dest.len())?;
 
         unsafe {
             match ffi::modbus_report_slave_id(self.ctx, max_dest as c_int, dest.as_mut_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len as u16),
             }
         }
@@ -252,7 +609,7 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let address = 1;
     ///
     /// assert!(modbus.write_bit(address, true).is_ok());
@@ -260,7 +617,7 @@ impl ModbusClient for Modbus {
     fn write_bit(&self, address: u16, status: bool) -> Result<(), Error> {
         unsafe {
             match ffi::modbus_write_bit(self.ctx, address as c_int, status as c_int) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 1 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -287,7 +644,7 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let address = 1;
     /// let value = u16::max_value();
     ///
@@ -296,7 +653,7 @@ impl ModbusClient for Modbus {
     fn write_register(&self, address: u16, value: u16) -> Result<(), Error> {
         unsafe {
             match ffi::modbus_write_register(self.ctx, address as c_int, value as c_int) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 1 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -313,6 +670,9 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a Ok Result containing the number of written bits. Otherwise it contains an Error.
+    /// If `num` exceeds [`Modbus::MAX_WRITE_BITS`](struct.Modbus.html#associatedconstant.MAX_WRITE_BITS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `src` holds less than `num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -324,16 +684,18 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let address = 1;
     /// let tab_bytes = vec![0u8];
     ///
     /// assert_eq!(modbus.write_bits(address, 1, &tab_bytes).unwrap(), 1);
     /// ```
     fn write_bits(&self, address: u16, num: u16, src: &[u8]) -> Result<u16, Error> {
+        check_buffer(num, Modbus::MAX_WRITE_BITS, src.len())?;
+
         unsafe {
             match ffi::modbus_write_bits(self.ctx, address as c_int, num as c_int, src.as_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 num => Ok(num as u16),
             }
         }
@@ -350,6 +712,9 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a Ok Result containing the number of written bytes. Otherwise it contains an Error.
+    /// If `num` exceeds [`Modbus::MAX_WRITE_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_WRITE_REGISTERS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `src` holds less than `num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -361,16 +726,18 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let address = 1;
     /// let tab_bytes = vec![0u16];
     ///
     /// assert_eq!(modbus.write_registers(address, 1, &tab_bytes).unwrap(), 1);
     /// ```
     fn write_registers(&self, address: u16, num: u16, src: &[u16]) -> Result<u16, Error> {
+        check_buffer(num, Modbus::MAX_WRITE_REGISTERS, src.len())?;
+
         unsafe {
             match ffi::modbus_write_registers(self.ctx, address as c_int, num as c_int, src.as_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 num => Ok(num as u16),
             }
         }
@@ -388,6 +755,12 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a Ok Result containing the number of read registers. Otherwise it contains an Error.
+    /// If `write_num` exceeds [`Modbus::MAX_WR_WRITE_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_WR_WRITE_REGISTERS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `src` holds less than `write_num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
+    /// If `read_num` exceeds [`Modbus::MAX_WR_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_WR_READ_REGISTERS) the Error is
+    /// [`Error::TooManyData`](enum.Error.html#variant.TooManyData), if `dest` holds less than `read_num` elements it is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Parameters
     ///
@@ -402,7 +775,7 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let address = 1;
     /// let request_bytes = vec![1u16];
     /// let mut response_bytes = vec![0u16];
@@ -414,6 +787,9 @@ impl ModbusClient for Modbus {
     fn write_and_read_registers(&self, write_address: u16, write_num: u16, src: &[u16], read_address: u16,
                                 read_num: u16, dest: &mut [u16])
                                 -> Result<u16, Error> {
+        check_buffer(write_num, Modbus::MAX_WR_WRITE_REGISTERS, src.len())?;
+        check_buffer(read_num, Modbus::MAX_WR_READ_REGISTERS, dest.len())?;
+
         unsafe {
             match ffi::modbus_write_and_read_registers(self.ctx,
                                                                  write_address as c_int,
@@ -422,7 +798,7 @@ impl ModbusClient for Modbus {
                                                                  read_address as c_int,
                                                                  read_num as c_int,
                                                                  dest.as_mut_ptr()) {
-                                                                     -1 => bail!(::std::io::Error::last_os_error()),
+                                                                     -1 => Err(Error::last_os_error()),
                 num => Ok(num as u16),
             }
         }
@@ -453,14 +829,14 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     ///
     /// assert!(modbus.mask_write_register(1, 0xF2, 0x25).is_ok());
     /// ```
     fn mask_write_register(&self, address: u16, and_mask: u16, or_mask: u16) -> Result<(), Error> {
         unsafe {
             match ffi::modbus_mask_write_register(self.ctx, address as c_int, and_mask, or_mask) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 1 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -487,14 +863,15 @@ impl ModbusClient for Modbus {
     /// # Return value
     ///
     /// The function returns a Result, containing the full message lenght,  counting the extra data relating to the
-    /// backend, if successful. Otherwise it contains an Error.
+    /// backend, if successful. Otherwise it contains an Error. If `raw_request` holds less than `length` bytes the Error
+    /// is [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP, FunctionCode};
     ///
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let mut raw_request: Vec<u8> = vec![0xFF, FunctionCode::ReadHoldingRegisters as u8, 0x00, 0x01, 0x0, 0x05];
     /// let mut response = vec![0u8; Modbus::TCP_MAX_ADU_LENGTH];
     /// let request_len = raw_request.len();
@@ -503,11 +880,13 @@ impl ModbusClient for Modbus {
     /// assert!(modbus.receive_confirmation(&mut response).is_ok());
     /// ```
     fn send_raw_request(&self, raw_request: &mut [u8], lenght: usize) -> Result<u16, Error> {
+        check_len(lenght, raw_request.len())?;
+
         unsafe {
             match ffi::modbus_send_raw_request(self.ctx,
                                                          raw_request.as_mut_ptr(),
                                                          lenght as c_int) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 num => Ok(num as u16),
             }
         }
@@ -521,17 +900,18 @@ impl ModbusClient for Modbus {
     /// initial request.
     /// This function can be used to receive request not handled by the library.
     ///
-    /// The maximum size of the response depends on the used backend,
-    /// in RTU the `response` array must be `Modbus::RTU_MAX_ADU_LENGTH` bytes and in TCP it must be
-    /// `Modbus::TCP_MAX_ADU_LENGTH` bytes.
-    /// If you want to write code compatible with both, you can use the constant MODBUS_MAX_ADU_LENGTH (maximum value
-    /// of all libmodbus backends).
-    /// Take care to allocate enough memory to store responses to avoid crashes of your server.
+    /// The maximum size of the response depends on the used backend, `Modbus::RTU_MAX_ADU_LENGTH` bytes in RTU and
+    /// `Modbus::TCP_MAX_ADU_LENGTH` bytes in TCP. Because the backend is not known here, the `response` slice must
+    /// always hold at least `Modbus::MAX_ADU_LENGTH` bytes (maximum value of all libmodbus backends).
     ///
     /// # Return value
     ///
     /// The function returns a Result containing the response length if successful. The returned request length can be
     /// zero if the indication request is ignored (eg. a query for another slave in RTU mode).
+    /// If the response is an exception response the Result contains the decoded
+    /// [`Error::Exception`](enum.Error.html#variant.Exception), the raw response is still stored in `response`.
+    /// If `response` is shorter than `Modbus::MAX_ADU_LENGTH` the Error is
+    /// [`Error::BufferTooSmall`](enum.Error.html#variant.BufferTooSmall).
     /// Otherwise it contains an Error.
     ///
     /// # Parameters
@@ -542,17 +922,27 @@ impl ModbusClient for Modbus {
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
-    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
     /// let mut response = vec![0u8; Modbus::MAX_ADU_LENGTH];
     ///
     /// assert!(modbus.receive_confirmation(&mut response).is_ok());
     /// ```
     fn receive_confirmation(&self, response: &mut [u8]) -> Result<u16, Error> {
-        unsafe {
+        check_len(Modbus::MAX_ADU_LENGTH, response.len())?;
+
+        let len = unsafe {
             match ffi::modbus_receive_confirmation(self.ctx, response.as_mut_ptr()) {
-                -1 => bail!(::std::io::Error::last_os_error()),
-                len => Ok(len as u16),
+                -1 => return Err(Error::last_os_error()),
+                len => len as usize,
             }
+        };
+
+        // Exception responses are marked with the highest bit of the function code
+        let offset = self.get_header_length() as usize;
+        if len > offset + 1 && response[offset] & 0x80 != 0 {
+            return Err(Exception::try_from(response[offset + 1]).map(Error::Exception).unwrap_or_else(|err| err));
         }
+
+        Ok(len as u16)
     }
 }
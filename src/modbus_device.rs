@@ -0,0 +1,173 @@
+use crate::error::Error;
+use crate::modbus::ModbusConnection;
+use crate::modbus_client::ModbusClient;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+
+/// A connection shared by the [`Device`](struct.Device.html)s on it, e.g. a RTU multi-drop bus or a TCP gateway
+///
+/// Clones of a bus share the same connection. Every request of a device locks the connection, so requests of
+/// devices in different threads are sent one after the other.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{Bus, Modbus, ModbusClient, ModbusRTU};
+/// use std::thread;
+///
+/// let modbus = Modbus::new_rtu("/dev/ttyUSB0", 19200, 'E', 8, 1).unwrap();
+/// let bus = Bus::new(modbus.connect().unwrap());
+///
+/// let meter = bus.device(1);
+/// let inverter = bus.device(2);
+/// let meter_thread = thread::spawn(move || meter.read_input_registers_vec(0, 10));
+/// let power = inverter.read_registers_vec(100, 2).unwrap();
+/// let energy = meter_thread.join().unwrap().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Bus {
+    connection: Arc<Mutex<ModbusConnection>>,
+}
+
+impl Bus {
+    /// `new` - share `connection` between devices
+    pub fn new(connection: ModbusConnection) -> Bus {
+        Bus { connection: Arc::new(Mutex::new(connection)) }
+    }
+
+    /// `device` - a handle sending its requests to the slave `slave`
+    ///
+    /// # Parameters
+    ///
+    /// * `slave`   - slave ID of the device, checked by libmodbus with every request
+    pub fn device(&self, slave: u8) -> Device {
+        Device { bus: self.clone(), slave }
+    }
+
+    /// `with_connection` - call `f` with the connection locked, e.g. to change the response timeout
+    ///
+    /// The slave of the connection is the one of the last request, devices set their own with every request.
+    pub fn with_connection<R, F: FnOnce(&mut ModbusConnection) -> R>(&self, f: F) -> R {
+        f(&mut self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ModbusConnection> {
+        // a panic while the connection was locked leaves no Rust state behind, the context can still be used
+        self.connection.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A device on a [`Bus`](struct.Bus.html), a [`ModbusClient`](trait.ModbusClient.html) bound to a slave ID
+///
+/// Every request locks the connection of the bus, sets the slave of the device and is sent, so devices can be used
+/// from different threads without calling [`set_slave()`](struct.Modbus.html#method.set_slave). Range requests
+/// keep the connection locked for all their chunks.
+///
+/// [`send_raw_request()`](#method.send_raw_request) and [`receive_confirmation()`](#method.receive_confirmation)
+/// lock the connection one at a time, another device could send in between. A raw exchange on a shared bus should be
+/// made within [`Bus::with_connection()`](struct.Bus.html#method.with_connection).
+#[derive(Clone, Debug)]
+pub struct Device {
+    bus: Bus,
+    slave: u8,
+}
+
+impl Device {
+    /// The slave ID of the device
+    pub fn get_slave(&self) -> u8 {
+        self.slave
+    }
+
+    /// The bus of the device
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    fn request<T, F: FnOnce(&ModbusConnection) -> Result<T, Error>>(&self, request: F) -> Result<T, Error> {
+        let mut connection = self.bus.lock();
+        connection.set_slave(self.slave)?;
+        request(&connection)
+    }
+}
+
+impl ModbusClient for Device {
+    fn read_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_bits(address, num, dest))
+    }
+
+    fn read_input_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_input_bits(address, num, dest))
+    }
+
+    fn read_registers(&self, address: u16, num: u16, dest: &mut [u16]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_registers(address, num, dest))
+    }
+
+    fn read_input_registers(&self, address: u16, num: u16, dest: &mut [u16]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_input_registers(address, num, dest))
+    }
+
+    fn report_slave_id(&self, max_dest: usize, dest: &mut [u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.report_slave_id(max_dest, dest))
+    }
+
+    fn write_bit(&self, address: u16, status: bool) -> Result<(), Error> {
+        self.request(|connection| connection.write_bit(address, status))
+    }
+
+    fn write_bits(&self, address: u16, num: u16, src: &[u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.write_bits(address, num, src))
+    }
+
+    fn write_register(&self, address: u16, value: u16) -> Result<(), Error> {
+        self.request(|connection| connection.write_register(address, value))
+    }
+
+    fn write_registers(&self, address: u16, num: u16, src: &[u16]) -> Result<u16, Error> {
+        self.request(|connection| connection.write_registers(address, num, src))
+    }
+
+    fn write_and_read_registers(&self, write_address: u16, write_num: u16, src: &[u16], read_address: u16,
+                                read_num: u16, dest: &mut [u16])
+                                -> Result<u16, Error> {
+        self.request(|connection| {
+                connection.write_and_read_registers(write_address, write_num, src, read_address, read_num, dest)
+            })
+    }
+
+    fn mask_write_register(&self, address: u16, and_mask: u16, or_mask: u16) -> Result<(), Error> {
+        self.request(|connection| connection.mask_write_register(address, and_mask, or_mask))
+    }
+
+    fn send_raw_request(&self, raw_request: &mut [u8], lenght: usize) -> Result<u16, Error> {
+        self.request(|connection| connection.send_raw_request(raw_request, lenght))
+    }
+
+    fn receive_confirmation(&self, response: &mut [u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.receive_confirmation(response))
+    }
+
+    fn read_bits_range(&self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        self.request(|connection| connection.read_bits_range(address, num))
+    }
+
+    fn read_input_bits_range(&self, address: u16, num: u16) -> Result<Vec<bool>, Error> {
+        self.request(|connection| connection.read_input_bits_range(address, num))
+    }
+
+    fn read_registers_range(&self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        self.request(|connection| connection.read_registers_range(address, num))
+    }
+
+    fn read_input_registers_range(&self, address: u16, num: u16) -> Result<Vec<u16>, Error> {
+        self.request(|connection| connection.read_input_registers_range(address, num))
+    }
+
+    fn write_bits_range(&self, address: u16, src: &[u8]) -> Result<(), Error> {
+        self.request(|connection| connection.write_bits_range(address, src))
+    }
+
+    fn write_registers_range(&self, address: u16, src: &[u16]) -> Result<(), Error> {
+        self.request(|connection| connection.write_registers_range(address, src))
+    }
+}
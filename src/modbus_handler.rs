@@ -0,0 +1,301 @@
+use crate::error::Error;
+use crate::frame::tcp;
+use crate::modbus::{Exception, FunctionCode, Modbus, ModbusConnection};
+use crate::modbus_client::ModbusClient;
+use crate::modbus_request::{push_bits, push_registers, Indication, Request};
+use crate::modbus_response::Response;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::mem::ManuallyDrop;
+use std::net::TcpStream;
+use std::os::unix::io::FromRawFd;
+
+
+/// Serves requests with values computed on demand, instead of out of a [`ModbusMapping`](struct.ModbusMapping.html)
+///
+/// Every method handles one Modbus function. The default implementations answer with
+/// [`Exception::IllegalFunction`](enum.Exception.html#variant.IllegalFunction), so only the functions a device
+/// supports have to be implemented. Returning an `Exception` sends it as exception response to the client.
+///
+/// The number of values in a request is checked against the protocol limits before the handler is called, a handler
+/// only has to check the addresses.
+///
+/// See [`reply_with()`](struct.ModbusConnection.html#method.reply_with) to answer a received request with a handler.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{Exception, RequestHandler};
+///
+/// struct Thermometer {
+///     setpoint: u16,
+/// }
+///
+/// impl RequestHandler for Thermometer {
+///     fn read_input_registers(&mut self, address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+///         match (address, count) {
+///             // temperature in 0.1 °C, read from the sensor driver
+///             (0, 1) => Ok(vec![215]),
+///             _ => Err(Exception::IllegalDataAddress),
+///         }
+///     }
+///
+///     fn write_single_register(&mut self, address: u16, value: u16) -> Result<(), Exception> {
+///         if address != 0 {
+///             return Err(Exception::IllegalDataAddress);
+///         }
+///         self.setpoint = value;
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait RequestHandler {
+    /// 0x01 Read Coils, returns `count` values
+    fn read_coils(&mut self, _address: u16, _count: u16) -> Result<Vec<bool>, Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x02 Read Discrete Inputs, returns `count` values
+    fn read_discrete_inputs(&mut self, _address: u16, _count: u16) -> Result<Vec<bool>, Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x03 Read Multiple Holding Registers, returns `count` values
+    fn read_holding_registers(&mut self, _address: u16, _count: u16) -> Result<Vec<u16>, Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x04 Read Input Registers, returns `count` values
+    fn read_input_registers(&mut self, _address: u16, _count: u16) -> Result<Vec<u16>, Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x05 Write Single Coil
+    fn write_single_coil(&mut self, _address: u16, _value: bool) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x06 Write Single Holding Register
+    fn write_single_register(&mut self, _address: u16, _value: u16) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x0F Write Multiple Coils
+    fn write_multiple_coils(&mut self, _address: u16, _values: &[bool]) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x10 Write Multiple Holding Registers
+    fn write_multiple_registers(&mut self, _address: u16, _values: &[u16]) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x11 Report Slave ID, returns the data following the byte count of the response
+    ///
+    /// Usually the slave id, the run indicator (`0xFF` for on) and additional device specific data.
+    fn report_slave_id(&mut self) -> Result<Vec<u8>, Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x16 Mask Write Register, the new value is `(current & and_mask) | (or_mask & !and_mask)`
+    fn mask_write_register(&mut self, _address: u16, _and_mask: u16, _or_mask: u16) -> Result<(), Exception> {
+        Err(Exception::IllegalFunction)
+    }
+
+    /// 0x17 Read/Write Multiple Registers, writes `values` first and returns the `read_count` values read afterwards
+    fn write_and_read_registers(&mut self, _read_address: u16, _read_count: u16, _write_address: u16,
+                                _values: &[u16])
+                                -> Result<Vec<u16>, Exception> {
+        Err(Exception::IllegalFunction)
+    }
+}
+
+/// Checks the number of values and that the addressed range ends within the 16 bit address space
+fn check_range(address: u16, count: usize, max: u32) -> Result<(), Exception> {
+    if count == 0 || count > max as usize {
+        return Err(Exception::IllegalDataValue);
+    }
+    if address as usize + count > 0x10000 {
+        return Err(Exception::IllegalDataAddress);
+    }
+    Ok(())
+}
+
+/// Checks that the handler returned the requested number of values
+fn check_count<T>(values: Vec<T>, count: u16) -> Result<Vec<T>, Exception> {
+    if values.len() == count as usize {
+        Ok(values)
+    } else {
+        Err(Exception::SlaveOrServerFailure)
+    }
+}
+
+fn bits_response(function: u8, values: &[bool]) -> Vec<u8> {
+    let mut pdu = vec![function];
+    push_bits(&mut pdu, values);
+    pdu
+}
+
+fn registers_response(function: u8, values: &[u16]) -> Vec<u8> {
+    let mut pdu = vec![function];
+    push_registers(&mut pdu, values);
+    pdu
+}
+
+/// Checks the number of values and the addresses of a request before its handler method is called
+pub(crate) fn check_request(request: &Request) -> Result<(), Exception> {
+    match *request {
+        Request::ReadCoils { address, count } | Request::ReadDiscreteInputs { address, count } => {
+            check_range(address, count as usize, Modbus::MAX_READ_BITS)
+        },
+        Request::ReadHoldingRegisters { address, count } | Request::ReadInputRegisters { address, count } => {
+            check_range(address, count as usize, Modbus::MAX_READ_REGISTERS)
+        },
+        Request::WriteMultipleCoils { address, ref values } => {
+            check_range(address, values.len(), Modbus::MAX_WRITE_BITS)
+        },
+        Request::WriteMultipleRegisters { address, ref values } => {
+            check_range(address, values.len(), Modbus::MAX_WRITE_REGISTERS)
+        },
+        Request::WriteAndReadRegisters { read_address, read_count, write_address, ref values } => {
+            check_range(write_address, values.len(), Modbus::MAX_WR_WRITE_REGISTERS)?;
+            check_range(read_address, read_count as usize, Modbus::MAX_WR_READ_REGISTERS)
+        },
+        Request::Other { .. } => Err(Exception::IllegalFunction),
+        _ => Ok(()),
+    }
+}
+
+/// Builds the response PDU to a request from the result of its handler method, or the exception to answer with
+///
+/// `result` holds the values read for read requests, the data for Report Slave ID and `Response::Written` for write
+/// requests.
+pub(crate) fn response_pdu(request: &Request, result: Result<Response, Exception>) -> Result<Vec<u8>, Exception> {
+    let function = request.function();
+
+    let pdu = match (request, result?) {
+        (&Request::ReadCoils { count, .. }, Response::Bits(values)) |
+        (&Request::ReadDiscreteInputs { count, .. }, Response::Bits(values)) => {
+            bits_response(function, &check_count(values, count)?)
+        },
+        (&Request::ReadHoldingRegisters { count, .. }, Response::Registers(values)) |
+        (&Request::ReadInputRegisters { count, .. }, Response::Registers(values)) |
+        (&Request::WriteAndReadRegisters { read_count: count, .. }, Response::Registers(values)) => {
+            registers_response(function, &check_count(values, count)?)
+        },
+        // the responses of single writes and mask writes repeat the request
+        (&Request::WriteSingleCoil { .. }, Response::Written) |
+        (&Request::WriteSingleRegister { .. }, Response::Written) |
+        (&Request::MaskWriteRegister { .. }, Response::Written) => request.to_pdu(),
+        // the responses of multiple writes repeat the address and the number of values
+        (&Request::WriteMultipleCoils { .. }, Response::Written) |
+        (&Request::WriteMultipleRegisters { .. }, Response::Written) => request.to_pdu()[..5].to_vec(),
+        (&Request::ReportSlaveId, Response::Data(data)) => {
+            if data.len() > Modbus::MAX_PDU_LENGTH - 2 {
+                return Err(Exception::SlaveOrServerFailure);
+            }
+            let mut pdu = vec![function, data.len() as u8];
+            pdu.extend_from_slice(&data);
+            pdu
+        },
+        _ => return Err(Exception::SlaveOrServerFailure),
+    };
+
+    Ok(pdu)
+}
+
+/// Calls the handler for a request and builds the response PDU, or the exception to answer with
+pub(crate) fn dispatch(handler: &mut dyn RequestHandler, request: &Request) -> Result<Vec<u8>, Exception> {
+    check_request(request)?;
+
+    let result = match *request {
+        Request::ReadCoils { address, count } => handler.read_coils(address, count).map(Response::Bits),
+        Request::ReadDiscreteInputs { address, count } => {
+            handler.read_discrete_inputs(address, count).map(Response::Bits)
+        },
+        Request::ReadHoldingRegisters { address, count } => {
+            handler.read_holding_registers(address, count).map(Response::Registers)
+        },
+        Request::ReadInputRegisters { address, count } => {
+            handler.read_input_registers(address, count).map(Response::Registers)
+        },
+        Request::WriteSingleCoil { address, value } => {
+            handler.write_single_coil(address, value).map(|_| Response::Written)
+        },
+        Request::WriteSingleRegister { address, value } => {
+            handler.write_single_register(address, value).map(|_| Response::Written)
+        },
+        Request::WriteMultipleCoils { address, ref values } => {
+            handler.write_multiple_coils(address, values).map(|_| Response::Written)
+        },
+        Request::WriteMultipleRegisters { address, ref values } => {
+            handler.write_multiple_registers(address, values).map(|_| Response::Written)
+        },
+        Request::ReportSlaveId => handler.report_slave_id().map(Response::Data),
+        Request::MaskWriteRegister { address, and_mask, or_mask } => {
+            handler.mask_write_register(address, and_mask, or_mask).map(|_| Response::Written)
+        },
+        Request::WriteAndReadRegisters { read_address, read_count, write_address, ref values } => {
+            handler.write_and_read_registers(read_address, read_count, write_address, values).map(Response::Registers)
+        },
+        Request::Other { .. } => return Err(Exception::IllegalFunction),
+    };
+
+    response_pdu(request, result)
+}
+
+/// Sends a response PDU to the client, which sent the request described by `indication`
+fn send_response(connection: &ModbusConnection, indication: &Indication, pdu: &[u8]) -> Result<i32, Error> {
+    match indication.transaction_id {
+        Some(transaction_id) => {
+            // `send_raw_request()` would send the next transaction id of the context, not the one of the request
+            let frame = tcp::Adu::new(transaction_id, indication.slave, pdu).encode()?;
+            // the socket stays owned by the connection
+            let mut stream = ManuallyDrop::new(unsafe { TcpStream::from_raw_fd(connection.get_socket()?) });
+            stream.write_all(&frame)?;
+            Ok(frame.len() as i32)
+        },
+        None => {
+            // broadcast requests are not answered
+            if indication.slave == Modbus::BROADCAST_ADDRESS {
+                return Ok(0);
+            }
+            let mut raw_response = Vec::with_capacity(pdu.len() + 1);
+            raw_response.push(indication.slave);
+            raw_response.extend_from_slice(pdu);
+            let len = raw_response.len();
+            connection.send_raw_request(&mut raw_response, len).map(i32::from)
+        },
+    }
+}
+
+/// Answers a received request with the values of a handler, see `ModbusServer::reply_with()`
+pub(crate) fn reply_with(connection: &ModbusConnection, request: &[u8], request_len: i32,
+                         handler: &mut dyn RequestHandler)
+                         -> Result<i32, Error> {
+    let request = request.get(..request_len as usize).ok_or(Error::BufferTooSmall {
+        required: request_len as usize,
+        available: request.len(),
+    })?;
+    let header_length = connection.get_header_length() as usize;
+    let indication = match Indication::from_bytes(request, header_length) {
+        Ok(indication) => indication,
+        // malformed data after the function code, or a function libmodbus does not know the length of
+        Err(_) if request.len() > header_length => {
+            let exception = match FunctionCode::try_from(request[header_length]) {
+                Ok(_) => Exception::IllegalDataValue,
+                Err(_) => Exception::IllegalFunction,
+            };
+            return connection.reply_exception(request, exception);
+        },
+        Err(err) => return Err(err),
+    };
+
+    match dispatch(handler, &indication.request) {
+        Ok(pdu) => send_response(connection, &indication, &pdu),
+        Err(exception) => {
+            let pdu = [indication.request.function() | 0x80, exception as u8];
+            send_response(connection, &indication, &pdu)
+        },
+    }
+}
@@ -1,13 +1,41 @@
-use failure::Error;
+use crate::error::Error;
+use crate::modbus::Exception;
+use crate::modbus_handler::RequestHandler;
+use crate::modbus_read_planner::{Point, Table};
+use crate::modbus_response::Response;
 use libc::{c_int, c_uint};
 use libmodbus_sys as ffi;
+use std::ops::Range;
+use std::slice;
+use std::sync::{Mutex, MutexGuard};
 
 
 /// To handle the mapping of your Modbus data, you must use this struct
 ///
+/// The four tables of the mapping are shared between the server, which reads and writes them in
+/// [`reply()`](struct.ModbusConnection.html#method.reply), and the application, which updates them with
+/// [`lock()`](#method.lock). All accesses are serialized by a lock, so a `ModbusMapping` can be shared between
+/// threads, for example wrapped in an `Arc`.
+///
 #[derive(Debug)]
 pub struct ModbusMapping {
-    pub modbus_mapping: *mut ffi::modbus_mapping_t,
+    modbus_mapping: Mutex<RawMapping>,
+}
+
+/// Owner of the `modbus_mapping_t` allocated by libmodbus
+#[derive(Debug)]
+struct RawMapping(*mut ffi::modbus_mapping_t);
+
+// The mapping is only reachable through the `Mutex` of `ModbusMapping`
+unsafe impl Send for RawMapping {}
+
+/// Locked view of the tables of a [`ModbusMapping`](struct.ModbusMapping.html)
+///
+/// Returned by [`ModbusMapping::lock()`](struct.ModbusMapping.html#method.lock). The lock is held until the guard is
+/// dropped, meanwhile the server can not answer requests with this mapping.
+#[derive(Debug)]
+pub struct ModbusMappingGuard<'a> {
+    guard: MutexGuard<'a, RawMapping>,
 }
 
 impl ModbusMapping {
@@ -48,9 +76,9 @@ impl ModbusMapping {
                                         number_registers as c_int,
                                         number_input_registers as c_int);
             if modbus_mapping.is_null() {
-                bail!(::std::io::Error::last_os_error())
+                Err(Error::last_os_error())
             } else {
-                Ok(ModbusMapping { modbus_mapping: modbus_mapping })
+                Ok(ModbusMapping { modbus_mapping: Mutex::new(RawMapping(modbus_mapping)) })
             }
         }
     }
@@ -115,9 +143,9 @@ impl ModbusMapping {
                                                                        start_input_registers as c_uint,
                                                                        number_input_registers as c_uint);
             if modbus_mapping.is_null() {
-                bail!(::std::io::Error::last_os_error())
+                Err(Error::last_os_error())
             } else {
-                Ok(ModbusMapping { modbus_mapping: modbus_mapping })
+                Ok(ModbusMapping { modbus_mapping: Mutex::new(RawMapping(modbus_mapping)) })
             }
         }
     }
@@ -127,21 +155,71 @@ impl ModbusMapping {
     /// The function shall free the four arrays of `mb_mapping_t` structure and finally the mb_mapping_t referenced by
     /// mb_mapping.
     ///
+    /// The mapping is consumed, so it can not be used or freed again afterwards.
+    ///
     /// **It should not nessesary to call these function. Because rusts drop trait handles that for you!**
     ///
     /// # Examples
     ///
     /// ```
     /// use libmodbus_rs::ModbusMapping;
-    /// let mut modbus_mapping = ModbusMapping::new(500, 500, 500, 500).unwrap();
+    /// let modbus_mapping = ModbusMapping::new(500, 500, 500, 500).unwrap();
     ///
     /// modbus_mapping.free();
     /// ```
-    pub fn free(&mut self) {
-        unsafe {
-            ffi::modbus_mapping_free(self.modbus_mapping);
+    pub fn free(self) {
+        drop(self)
+    }
+
+    /// `lock` - lock the mapping to read or write its tables
+    ///
+    /// The [`lock()`](#method.lock) function blocks until no other thread, and no running
+    /// [`reply()`](struct.ModbusConnection.html#method.reply), accesses the mapping.
+    ///
+    /// # Return value
+    ///
+    /// A [`ModbusMappingGuard`](struct.ModbusMappingGuard.html) giving access to the four tables. The mapping is
+    /// unlocked when the guard is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmodbus_rs::ModbusMapping;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let modbus_mapping = Arc::new(ModbusMapping::new(5, 5, 5, 5).unwrap());
+    ///
+    /// let mapping = modbus_mapping.clone();
+    /// thread::spawn(move || {
+    ///     mapping.lock().get_registers_mut()[0] = 42;
+    /// }).join().unwrap();
+    ///
+    /// assert_eq!(modbus_mapping.lock().get_registers(), [42u16, 0, 0, 0, 0]);
+    /// ```
+    pub fn lock(&self) -> ModbusMappingGuard<'_> {
+        // The tables are plain data, a panic while they were locked can not leave them in an invalid state
+        let guard = self.modbus_mapping.lock().unwrap_or_else(|err| err.into_inner());
+        ModbusMappingGuard { guard }
+    }
+
+    fn raw_mut(&mut self) -> &mut RawMapping {
+        match self.modbus_mapping.get_mut() {
+            Ok(raw) => raw,
+            Err(err) => err.into_inner(),
         }
     }
+}
+
+impl<'a> ModbusMappingGuard<'a> {
+    /// Raw pointer to the locked `modbus_mapping_t`, for use with libmodbus while the guard is alive
+    pub(crate) fn as_raw(&self) -> *mut ffi::modbus_mapping_t {
+        self.guard.0
+    }
+
+    fn raw(&self) -> &ffi::modbus_mapping_t {
+        unsafe { &*self.as_raw() }
+    }
 
     // TODO: Add better documentation
     /// `get_bits` - returns a slice constructed from the `bits` and `nb_bits` member of the orig. `ModbusMapping`
@@ -160,12 +238,10 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_bits(), [0u8, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_bits(), [0u8, 0, 0, 0, 0]);
     /// ```
     pub fn get_bits(&self) -> &[u8] {
-        unsafe {
-            ::std::slice::from_raw_parts((*self.modbus_mapping).tab_bits, (*self.modbus_mapping).nb_bits as usize)
-        }
+        unsafe { table((*self.as_raw()).tab_bits, (*self.as_raw()).nb_bits) }
     }
 
     // TODO: Add better documentation
@@ -185,12 +261,10 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_bits_mut(), [0u8, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_bits_mut(), [0u8, 0, 0, 0, 0]);
     /// ```
-    pub fn get_bits_mut(&self) -> &mut [u8] {
-        unsafe {
-            ::std::slice::from_raw_parts_mut((*self.modbus_mapping).tab_bits, (*self.modbus_mapping).nb_bits as usize)
-        }
+    pub fn get_bits_mut(&mut self) -> &mut [u8] {
+        unsafe { table_mut((*self.as_raw()).tab_bits, (*self.as_raw()).nb_bits) }
     }
 
     // TODO: Add better documentation
@@ -210,13 +284,10 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_input_bits(), [0u8, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_input_bits(), [0u8, 0, 0, 0, 0]);
     /// ```
     pub fn get_input_bits(&self) -> &[u8] {
-        unsafe {
-            ::std::slice::from_raw_parts((*self.modbus_mapping).tab_input_bits,
-                                         (*self.modbus_mapping).nb_input_bits as usize)
-        }
+        unsafe { table((*self.as_raw()).tab_input_bits, (*self.as_raw()).nb_input_bits) }
     }
 
     // TODO: Add better documentation
@@ -236,13 +307,10 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_input_bits_mut(), [0u8, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_input_bits_mut(), [0u8, 0, 0, 0, 0]);
     /// ```
-    pub fn get_input_bits_mut(&self) -> &mut [u8] {
-        unsafe {
-            ::std::slice::from_raw_parts_mut((*self.modbus_mapping).tab_input_bits,
-                                             (*self.modbus_mapping).nb_input_bits as usize)
-        }
+    pub fn get_input_bits_mut(&mut self) -> &mut [u8] {
+        unsafe { table_mut((*self.as_raw()).tab_input_bits, (*self.as_raw()).nb_input_bits) }
     }
 
     // TODO: Add better documentation
@@ -262,13 +330,10 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_input_registers(), [0u16, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_input_registers(), [0u16, 0, 0, 0, 0]);
     /// ```
     pub fn get_input_registers(&self) -> &[u16] {
-        unsafe {
-            ::std::slice::from_raw_parts((*self.modbus_mapping).tab_input_registers,
-                                         (*self.modbus_mapping).nb_input_registers as usize)
-        }
+        unsafe { table((*self.as_raw()).tab_input_registers, (*self.as_raw()).nb_input_registers) }
     }
 
     // TODO: Add better documentation
@@ -288,13 +353,10 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_input_registers_mut(), [0u16, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_input_registers_mut(), [0u16, 0, 0, 0, 0]);
     /// ```
-    pub fn get_input_registers_mut(&self) -> &mut [u16] {
-        unsafe {
-            ::std::slice::from_raw_parts_mut((*self.modbus_mapping).tab_input_registers,
-                                             (*self.modbus_mapping).nb_input_registers as usize)
-        }
+    pub fn get_input_registers_mut(&mut self) -> &mut [u16] {
+        unsafe { table_mut((*self.as_raw()).tab_input_registers, (*self.as_raw()).nb_input_registers) }
     }
 
     // TODO: Add better documentation
@@ -314,13 +376,10 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_registers(), [0u16, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_registers(), [0u16, 0, 0, 0, 0]);
     /// ```
     pub fn get_registers(&self) -> &[u16] {
-        unsafe {
-            ::std::slice::from_raw_parts((*self.modbus_mapping).tab_registers,
-                                         (*self.modbus_mapping).nb_registers as usize)
-        }
+        unsafe { table((*self.as_raw()).tab_registers, (*self.as_raw()).nb_registers) }
     }
 
     // TODO: Add better documentation
@@ -340,18 +399,153 @@ impl ModbusMapping {
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     /// let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
     ///
-    /// assert_eq!(modbus_mapping.get_registers_mut(), [0u16, 0, 0, 0, 0])
+    /// assert_eq!(modbus_mapping.lock().get_registers_mut(), [0u16, 0, 0, 0, 0]);
     /// ```
-    pub fn get_registers_mut(&self) -> &mut [u16] {
-        unsafe {
-            ::std::slice::from_raw_parts_mut((*self.modbus_mapping).tab_registers,
-                                             (*self.modbus_mapping).nb_registers as usize)
+    pub fn get_registers_mut(&mut self) -> &mut [u16] {
+        unsafe { table_mut((*self.as_raw()).tab_registers, (*self.as_raw()).nb_registers) }
+    }
+
+    /// The values of `point`, from any table
+    pub(crate) fn read_point(&mut self, point: &Point) -> Result<Response, Exception> {
+        let (address, count) = (point.address, point.length);
+        match point.table {
+            Table::Coils => self.read_coils(address, count).map(Response::Bits),
+            Table::DiscreteInputs => self.read_discrete_inputs(address, count).map(Response::Bits),
+            Table::HoldingRegisters => self.read_holding_registers(address, count).map(Response::Registers),
+            Table::InputRegisters => self.read_input_registers(address, count).map(Response::Registers),
+        }
+    }
+
+    /// Stores the values of `point` in any table, `Exception::IllegalDataValue` if they do not fit the table
+    pub(crate) fn write_point(&mut self, point: &Point, values: &Response) -> Result<(), Exception> {
+        match (point.table, values) {
+            (Table::Coils, Response::Bits(bits)) => self.write_multiple_coils(point.address, bits),
+            (Table::DiscreteInputs, Response::Bits(bits)) => {
+                let start = self.raw().start_input_bits;
+                let range = table_range(start, self.get_input_bits().len(), point.address, bits.len())?;
+                for (bit, &value) in self.get_input_bits_mut()[range].iter_mut().zip(bits) {
+                    *bit = value as u8;
+                }
+                Ok(())
+            },
+            (Table::HoldingRegisters, Response::Registers(registers)) => {
+                self.write_multiple_registers(point.address, registers)
+            },
+            (Table::InputRegisters, Response::Registers(registers)) => {
+                let start = self.raw().start_input_registers;
+                let range = table_range(start, self.get_input_registers().len(), point.address, registers.len())?;
+                self.get_input_registers_mut()[range].copy_from_slice(registers);
+                Ok(())
+            },
+            _ => Err(Exception::IllegalDataValue),
+        }
+    }
+}
+
+/// Slave id reported by libmodbus when it answers with a mapping
+const REPORT_SLAVE_ID: u8 = 180;
+
+/// Answers requests with the tables of the mapping, the same way [`reply()`](struct.ModbusConnection.html#method.reply)
+/// does
+///
+/// Addresses are relative to the start addresses of the tables, requests outside of a table are answered with
+/// [`Exception::IllegalDataAddress`](enum.Exception.html#variant.IllegalDataAddress). This way a mapping can be
+/// passed to [`reply_with()`](struct.ModbusConnection.html#method.reply_with) as well.
+impl<'a> RequestHandler for ModbusMappingGuard<'a> {
+    fn read_coils(&mut self, address: u16, count: u16) -> Result<Vec<bool>, Exception> {
+        let range = table_range(self.raw().start_bits, self.get_bits().len(), address, count as usize)?;
+        Ok(self.get_bits()[range].iter().map(|&bit| bit != 0).collect())
+    }
+
+    fn read_discrete_inputs(&mut self, address: u16, count: u16) -> Result<Vec<bool>, Exception> {
+        let range = table_range(self.raw().start_input_bits, self.get_input_bits().len(), address, count as usize)?;
+        Ok(self.get_input_bits()[range].iter().map(|&bit| bit != 0).collect())
+    }
+
+    fn read_holding_registers(&mut self, address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+        let range = table_range(self.raw().start_registers, self.get_registers().len(), address, count as usize)?;
+        Ok(self.get_registers()[range].to_vec())
+    }
+
+    fn read_input_registers(&mut self, address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+        let range = table_range(self.raw().start_input_registers,
+                                self.get_input_registers().len(),
+                                address,
+                                count as usize)?;
+        Ok(self.get_input_registers()[range].to_vec())
+    }
+
+    fn write_single_coil(&mut self, address: u16, value: bool) -> Result<(), Exception> {
+        self.write_multiple_coils(address, &[value])
+    }
+
+    fn write_single_register(&mut self, address: u16, value: u16) -> Result<(), Exception> {
+        self.write_multiple_registers(address, &[value])
+    }
+
+    fn write_multiple_coils(&mut self, address: u16, values: &[bool]) -> Result<(), Exception> {
+        let range = table_range(self.raw().start_bits, self.get_bits().len(), address, values.len())?;
+        for (bit, &value) in self.get_bits_mut()[range].iter_mut().zip(values) {
+            *bit = value as u8;
         }
+        Ok(())
+    }
+
+    fn write_multiple_registers(&mut self, address: u16, values: &[u16]) -> Result<(), Exception> {
+        let range = table_range(self.raw().start_registers, self.get_registers().len(), address, values.len())?;
+        self.get_registers_mut()[range].copy_from_slice(values);
+        Ok(())
+    }
+
+    fn report_slave_id(&mut self) -> Result<Vec<u8>, Exception> {
+        let version = ffi::LIBMODBUS_VERSION_STRING.split(|&byte| byte == 0).next().unwrap_or(&[]);
+        let mut data = vec![REPORT_SLAVE_ID, 0xFF];
+        data.extend_from_slice(b"LMB");
+        data.extend_from_slice(version);
+        Ok(data)
+    }
+
+    fn mask_write_register(&mut self, address: u16, and_mask: u16, or_mask: u16) -> Result<(), Exception> {
+        let range = table_range(self.raw().start_registers, self.get_registers().len(), address, 1)?;
+        let register = &mut self.get_registers_mut()[range.start];
+        *register = (*register & and_mask) | (or_mask & !and_mask);
+        Ok(())
     }
+
+    fn write_and_read_registers(&mut self, read_address: u16, read_count: u16, write_address: u16,
+                                values: &[u16])
+                                -> Result<Vec<u16>, Exception> {
+        let len = self.get_registers().len();
+        let read = table_range(self.raw().start_registers, len, read_address, read_count as usize)?;
+        let write = table_range(self.raw().start_registers, len, write_address, values.len())?;
+        self.get_registers_mut()[write].copy_from_slice(values);
+        Ok(self.get_registers()[read].to_vec())
+    }
+}
+
+/// Indices of `count` entries starting at `address` in a table starting at `start` with `len` entries
+fn table_range(start: c_int, len: usize, address: u16, count: usize) -> Result<Range<usize>, Exception> {
+    let offset = i64::from(address) - i64::from(start);
+    if offset < 0 || offset as usize + count > len {
+        return Err(Exception::IllegalDataAddress);
+    }
+    Ok(offset as usize..offset as usize + count)
+}
+
+/// Slice over a table of the mapping, libmodbus leaves the pointer NULL for empty tables
+unsafe fn table<'a, T>(tab: *mut T, nb: c_int) -> &'a [T] {
+    if tab.is_null() || nb <= 0 { &[] } else { slice::from_raw_parts(tab, nb as usize) }
+}
+
+/// Mutable slice over a table of the mapping, libmodbus leaves the pointer NULL for empty tables
+unsafe fn table_mut<'a, T>(tab: *mut T, nb: c_int) -> &'a mut [T] {
+    if tab.is_null() || nb <= 0 { &mut [] } else { slice::from_raw_parts_mut(tab, nb as usize) }
 }
 
 impl Drop for ModbusMapping {
     fn drop(&mut self) {
-        self.free()
+        unsafe {
+            ffi::modbus_mapping_free(self.raw_mut().0);
+        }
     }
 }
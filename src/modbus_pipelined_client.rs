@@ -0,0 +1,258 @@
+use crate::error::Error;
+use crate::frame::tcp;
+use crate::modbus::Modbus;
+use crate::modbus_request::Request;
+use crate::modbus_response::Response;
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+
+/// Response timeout of a new client, the same as the default of libmodbus
+const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_millis(500);
+
+
+/// The outcome of a request sent by a [`PipelinedTcpClient`](struct.PipelinedTcpClient.html)
+#[derive(Debug)]
+pub struct Confirmation {
+    /// Transaction id returned by [`send()`](struct.PipelinedTcpClient.html#method.send)
+    pub transaction_id: u16,
+    /// The request
+    pub request: Request,
+    /// The decoded response, the exception of the device or `Error::Timeout` if no response arrived in time
+    pub result: Result<Response, Error>,
+}
+
+/// A request waiting for its response
+#[derive(Debug)]
+struct Pending {
+    transaction_id: u16,
+    slave: u8,
+    request: Request,
+    deadline: Instant,
+}
+
+/// Modbus TCP client with several requests in flight on one connection
+///
+/// A libmodbus context sends one request and waits for its response before the next request can be sent, on links
+/// with a high latency most of the time is spent waiting. The `PipelinedTcpClient` sends requests with
+/// [`send()`](#method.send) without waiting, each with its own transaction id. The responses are collected with
+/// [`receive()`](#method.receive) and matched to their requests by the transaction id, in the order they arrive.
+///
+/// Every request has its own response timeout. If it expires, the request is confirmed with `Error::Timeout` and a
+/// response arriving later is discarded.
+///
+/// The number of requests in flight is up to the caller, many devices only queue a few requests per connection.
+/// The TCP backend is implemented in Rust, no libmodbus context is involved.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{PipelinedTcpClient, Request, Response};
+///
+/// let mut client = PipelinedTcpClient::connect("127.0.0.1:1502").unwrap();
+/// for address in (0..80).step_by(10) {
+///     client.send(Request::ReadHoldingRegisters { address, count: 10 }).unwrap();
+/// }
+///
+/// while let Some(confirmation) = client.receive().unwrap() {
+///     match (confirmation.request, confirmation.result) {
+///         (Request::ReadHoldingRegisters { address, .. }, Ok(Response::Registers(values))) => {
+///             println!("{}: {:?}", address, values)
+///         },
+///         (request, result) => println!("{:?} failed: {:?}", request, result),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PipelinedTcpClient {
+    stream: TcpStream,
+    decoder: tcp::Decoder,
+    slave: u8,
+    transaction_id: u16,
+    response_timeout: Duration,
+    pending: Vec<Pending>,
+    // set while a request is written, still set if writing did not finish
+    broken: bool,
+}
+
+impl PipelinedTcpClient {
+    /// `connect` - establish a connection to a Modbus TCP server
+    ///
+    /// # Parameters
+    ///
+    /// * `addr`    - address of the server, e.g. `"192.168.0.5:502"`, all resolved addresses are tried in turn
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<PipelinedTcpClient, Error> {
+        PipelinedTcpClient::from_stream(TcpStream::connect(addr)?)
+    }
+
+    /// `from_stream` - create a client on an already connected stream
+    pub fn from_stream(stream: TcpStream) -> Result<PipelinedTcpClient, Error> {
+        // like libmodbus, requests are sent without delay
+        stream.set_nodelay(true)?;
+
+        Ok(PipelinedTcpClient {
+            stream,
+            decoder: tcp::Decoder::new(),
+            slave: Modbus::TCP_SLAVE,
+            transaction_id: 0,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            pending: Vec::new(),
+            broken: false,
+        })
+    }
+
+    /// `get_slave` - get the unit id requests are sent to
+    pub fn get_slave(&self) -> u8 {
+        self.slave
+    }
+
+    /// `set_slave` - set the unit id of the requests sent from now on
+    ///
+    /// The default is [`Modbus::TCP_SLAVE`](struct.Modbus.html#associatedconstant.TCP_SLAVE).
+    pub fn set_slave(&mut self, slave: u8) {
+        self.slave = slave;
+    }
+
+    /// `get_response_timeout` - get the response timeout of requests sent with [`send()`](#method.send)
+    pub fn get_response_timeout(&self) -> Duration {
+        self.response_timeout
+    }
+
+    /// `set_response_timeout` - set the response timeout of requests sent with [`send()`](#method.send), 500 ms by
+    /// default
+    ///
+    /// The timeout is measured for every request from the moment it is sent.
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.response_timeout = timeout;
+    }
+
+    /// Number of requests sent and not yet confirmed
+    pub fn in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// `is_broken` - whether a request was not sent completely
+    ///
+    /// The server could not make sense of anything sent after the partial frame, [`send()`](#method.send) and
+    /// [`receive()`](#method.receive) fail from then on. Connect a new client instead.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// `send` - send a request without waiting for its response
+    ///
+    /// # Return value
+    ///
+    /// The transaction id of the request, it is repeated in the [`Confirmation`](struct.Confirmation.html). Sending
+    /// fails with `Error::TooManyData` if the request does not fit into a frame, or with the Error of the socket.
+    pub fn send(&mut self, request: Request) -> Result<u16, Error> {
+        let timeout = self.response_timeout;
+        self.send_with_timeout(request, timeout)
+    }
+
+    /// `send_with_timeout` - send a request with its own response timeout
+    ///
+    /// Writing the request is limited by the same timeout, a server which does not read its requests can not block
+    /// the client. If the request was not written completely, the client [`is_broken()`](#method.is_broken).
+    pub fn send_with_timeout(&mut self, request: Request, timeout: Duration) -> Result<u16, Error> {
+        if self.broken {
+            return Err(broken());
+        }
+
+        let transaction_id = self.next_transaction_id();
+        let pdu = request.to_pdu();
+        let frame = tcp::Adu::new(transaction_id, self.slave, &pdu).encode()?;
+        self.stream.set_write_timeout(Some(timeout))?;
+        self.broken = true;
+        self.stream.write_all(&frame)?;
+        self.broken = false;
+
+        self.pending.push(Pending {
+            transaction_id,
+            slave: self.slave,
+            request,
+            deadline: Instant::now() + timeout,
+        });
+        Ok(transaction_id)
+    }
+
+    /// `receive` - wait for the next response, or the next request to time out
+    ///
+    /// # Return value
+    ///
+    /// The `Confirmation` of a request, `Ok(None)` if no request is in flight. Errors of single requests are reported
+    /// in the confirmation. An Error is only returned if the connection failed, the requests in flight are lost then.
+    pub fn receive(&mut self) -> Result<Option<Confirmation>, Error> {
+        if self.broken {
+            return Err(broken());
+        }
+
+        loop {
+            let deadline = match self.pending.iter().map(|pending| pending.deadline).min() {
+                Some(deadline) => deadline,
+                None => return Ok(None),
+            };
+            let now = Instant::now();
+            if deadline <= now {
+                let index = self.pending.iter().position(|pending| pending.deadline == deadline).unwrap_or(0);
+                let pending = self.pending.remove(index);
+                return Ok(Some(Confirmation {
+                    transaction_id: pending.transaction_id,
+                    request: pending.request,
+                    result: Err(Error::Timeout),
+                }));
+            }
+
+            self.stream.set_read_timeout(Some(deadline - now))?;
+            let frame = match self.decoder.read_frame(&mut self.stream) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return Err(closed()),
+                Err(Error::Timeout) => continue,
+                Err(Error::IoError(ref err)) if is_timeout(err) => continue,
+                Err(err) => return Err(err),
+            };
+
+            let adu = tcp::Adu::decode(&frame)?;
+            // responses to requests which timed out are dropped
+            let index = match self.pending.iter().position(|pending| pending.transaction_id == adu.transaction_id) {
+                Some(index) => index,
+                None => continue,
+            };
+            let pending = self.pending.remove(index);
+            let result = if adu.unit_id != pending.slave && pending.slave != Modbus::BROADCAST_ADDRESS {
+                Err(Error::BadSlave)
+            } else {
+                Response::decode(&pending.request, adu.pdu)
+            };
+
+            return Ok(Some(Confirmation {
+                transaction_id: pending.transaction_id,
+                request: pending.request,
+                result,
+            }));
+        }
+    }
+
+    /// The next transaction id, skipping ids of requests still in flight
+    fn next_transaction_id(&mut self) -> u16 {
+        loop {
+            self.transaction_id = self.transaction_id.wrapping_add(1);
+            if !self.pending.iter().any(|pending| pending.transaction_id == self.transaction_id) {
+                return self.transaction_id;
+            }
+        }
+    }
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut
+}
+
+fn closed() -> Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by the server").into()
+}
+
+fn broken() -> Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "a previous request was not sent completely").into()
+}
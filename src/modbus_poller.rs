@@ -0,0 +1,369 @@
+use crate::error::Error;
+use crate::modbus::ModbusConnection;
+use crate::modbus_client::ModbusClient;
+use crate::modbus_read_planner::ReadPlanner;
+use crate::modbus_register_map::{RegisterMap, Value};
+use crate::modbus_server::ShutdownHandle;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+
+/// Longest sleep of [`Poller::run()`](struct.Poller.html#method.run) before it checks its shutdown handle
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+
+/// A function registered with [`Poller::on_change()`](struct.Poller.html#method.on_change)
+type Callback = Box<dyn FnMut(&Change) + Send>;
+
+/// Whether the value of a [`Sample`](struct.Sample.html) is current
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Quality {
+    /// The value was read by the last poll of its group
+    Good,
+    /// The last poll of the group failed, the value is the last one read, if any
+    Stale,
+}
+
+/// The last known state of a point of a [`Poller`](struct.Poller.html)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// The last value reported, `None` if no poll succeeded yet. Values within the deadband of it are not reported
+    /// and do not replace it.
+    pub value: Option<Value>,
+    /// `Quality::Stale` if the last poll failed
+    pub quality: Quality,
+    /// When the point was last read, or when it became stale
+    pub timestamp: SystemTime,
+}
+
+/// A point whose value changed beyond its deadband or whose quality changed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// The group returned by [`add_group()`](struct.Poller.html#method.add_group)
+    pub group: usize,
+    /// Name of the point in the map of the group
+    pub name: String,
+    /// The new state of the point
+    pub sample: Sample,
+}
+
+/// Points of one device read together at a fixed interval, added to a [`Poller`](struct.Poller.html)
+///
+/// All readable points of the map are read with the requests planned by a
+/// [`ReadPlanner`](struct.ReadPlanner.html). A change is reported if the value of a point differs by more than the
+/// deadband from the last reported value. Bool values are reported on every change, the default deadband `0.0`
+/// reports every change of numbers too.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{DataType, PollGroup, RegisterMap, RegisterPoint, Table};
+/// use std::time::Duration;
+///
+/// let map = RegisterMap::new(vec![RegisterPoint::new("pressure", Table::InputRegisters, 0, DataType::F32),
+///                                 RegisterPoint::new("level", Table::InputRegisters, 2, DataType::U16)]).unwrap();
+///
+/// let mut group = PollGroup::new(map, Duration::from_millis(100));
+/// group.set_deadband(0.5);
+/// group.set_point_deadband("level", 10.0).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct PollGroup {
+    map: RegisterMap,
+    interval: Duration,
+    planner: ReadPlanner,
+    deadband: f64,
+    point_deadbands: HashMap<String, f64>,
+}
+
+impl PollGroup {
+    /// `new` - read the points of `map` every `interval`
+    pub fn new(map: RegisterMap, interval: Duration) -> PollGroup {
+        PollGroup { map,
+                    interval,
+                    planner: ReadPlanner::new(),
+                    deadband: 0.0,
+                    point_deadbands: HashMap::new() }
+    }
+
+    /// The points of the group
+    pub fn map(&self) -> &RegisterMap {
+        &self.map
+    }
+
+    /// Time between two polls
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Set the deadband of all points without an own deadband
+    pub fn set_deadband(&mut self, deadband: f64) {
+        self.deadband = deadband;
+    }
+
+    /// `set_point_deadband` - set the deadband of the point named `name`
+    ///
+    /// # Return value
+    ///
+    /// `Error::InvalidPoint` if the map has no such point.
+    pub fn set_point_deadband(&mut self, name: &str, deadband: f64) -> Result<(), Error> {
+        if self.map.point(name).is_none() {
+            return Err(Error::InvalidPoint { name: name.to_owned(), reason: "no such point" });
+        }
+        self.point_deadbands.insert(name.to_owned(), deadband);
+        Ok(())
+    }
+
+    /// Set the planner used to read the points, to allow gaps between points or to exclude addresses
+    pub fn set_planner(&mut self, planner: ReadPlanner) {
+        self.planner = planner;
+    }
+
+    fn deadband(&self, name: &str) -> f64 {
+        self.point_deadbands.get(name).cloned().unwrap_or(self.deadband)
+    }
+}
+
+/// A group with its schedule and the state of its points
+#[derive(Debug)]
+struct ScheduledGroup {
+    device: usize,
+    group: PollGroup,
+    due: Instant,
+    samples: HashMap<String, Sample>,
+    last_error: Option<Error>,
+}
+
+/// Reads groups of points from one or more devices at their intervals and reports the changes
+///
+/// Every device is a client, usually a [`ModbusConnection`](struct.ModbusConnection.html), added with
+/// [`add_device()`](#method.add_device). Its groups of points, e.g. alarms every 100 ms and counters every 10 s, are
+/// added with [`add_group()`](#method.add_group). The poller keeps the last value, [`Quality`](enum.Quality.html)
+/// and timestamp of every point. If a poll fails, the points of the group become stale and keep their last value.
+///
+/// Changes are passed to the callbacks registered with [`on_change()`](#method.on_change) and sent to the channels
+/// returned by [`subscribe()`](#method.subscribe). The first value of a point and every change of its quality are
+/// reported too.
+///
+/// The poller reads in the thread calling [`poll()`](#method.poll) or [`run()`](#method.run), one group after the
+/// other. A device that does not answer delays the other groups by its response timeout, so each client keeps the
+/// timeout set with [`set_response_timeout()`](struct.Modbus.html#method.set_response_timeout) before it is added
+/// or later through [`device_mut()`](#method.device_mut).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{DataType, Modbus, ModbusTCP, Poller, PollGroup, RegisterMap, RegisterPoint, ShutdownHandle,
+///                    Table};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+/// modbus.set_response_timeout_duration(Duration::from_millis(200)).unwrap();
+///
+/// let mut poller = Poller::new();
+/// let plc = poller.add_device(modbus.connect().unwrap());
+///
+/// let alarms = RegisterMap::new(vec![RegisterPoint::new("overheat", Table::DiscreteInputs, 0, DataType::Bool)]);
+/// poller.add_group(plc, PollGroup::new(alarms.unwrap(), Duration::from_millis(100)));
+/// let counters = RegisterMap::new(vec![RegisterPoint::new("cycles", Table::InputRegisters, 0, DataType::U32)]);
+/// poller.add_group(plc, PollGroup::new(counters.unwrap(), Duration::from_secs(10)));
+///
+/// poller.on_change(|change| println!("{}: {:?}", change.name, change.sample.value));
+/// let changes = poller.subscribe();
+///
+/// let shutdown = ShutdownHandle::new();
+/// let handle = shutdown.clone();
+/// thread::spawn(move || poller.run(&handle));
+///
+/// for change in changes.iter().take(10) {
+///     println!("{:?}", change);
+/// }
+/// shutdown.shutdown();
+/// ```
+pub struct Poller<C: ModbusClient = ModbusConnection> {
+    devices: Vec<C>,
+    groups: Vec<ScheduledGroup>,
+    callbacks: Vec<Callback>,
+    senders: Vec<Sender<Change>>,
+}
+
+impl<C: ModbusClient> Default for Poller<C> {
+    fn default() -> Self {
+        Poller { devices: Vec::new(), groups: Vec::new(), callbacks: Vec::new(), senders: Vec::new() }
+    }
+}
+
+impl<C: ModbusClient> Poller<C> {
+    /// Create a poller without devices
+    pub fn new() -> Poller<C> {
+        Poller::default()
+    }
+
+    /// `add_device` - add a client the groups of a device are read with
+    ///
+    /// # Return value
+    ///
+    /// The device, to add groups with [`add_group()`](#method.add_group).
+    pub fn add_device(&mut self, client: C) -> usize {
+        self.devices.push(client);
+        self.devices.len() - 1
+    }
+
+    /// The client of `device`
+    pub fn device(&self, device: usize) -> Option<&C> {
+        self.devices.get(device)
+    }
+
+    /// The client of `device`, e.g. to change its response timeout
+    pub fn device_mut(&mut self, device: usize) -> Option<&mut C> {
+        self.devices.get_mut(device)
+    }
+
+    /// `add_group` - read `group` from `device`, the first time with the next poll
+    ///
+    /// # Return value
+    ///
+    /// The group, as reported in [`Change`](struct.Change.html).
+    ///
+    /// # Panics
+    ///
+    /// If `device` was not returned by [`add_device()`](#method.add_device).
+    pub fn add_group(&mut self, device: usize, group: PollGroup) -> usize {
+        assert!(device < self.devices.len(), "unknown device {}", device);
+        self.groups.push(ScheduledGroup { device,
+                                          group,
+                                          due: Instant::now(),
+                                          samples: HashMap::new(),
+                                          last_error: None });
+        self.groups.len() - 1
+    }
+
+    /// Call `callback` with every change, in the thread polling
+    pub fn on_change<F: FnMut(&Change) + Send + 'static>(&mut self, callback: F) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// `subscribe` - receive every change through a channel
+    ///
+    /// The poller stops sending once the receiver is dropped.
+    pub fn subscribe(&mut self) -> Receiver<Change> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// The last known state of the point `name` of `group`, `None` before its first poll
+    pub fn sample(&self, group: usize, name: &str) -> Option<&Sample> {
+        self.groups.get(group).and_then(|scheduled| scheduled.samples.get(name))
+    }
+
+    /// The Error of the last poll of `group`, `None` if it succeeded
+    pub fn last_error(&self, group: usize) -> Option<&Error> {
+        self.groups.get(group).and_then(|scheduled| scheduled.last_error.as_ref())
+    }
+
+    /// `poll` - read all groups that are due and report the changes
+    ///
+    /// A group is due again one interval after it was due before. If the poll took longer than that, the missed
+    /// polls are skipped.
+    ///
+    /// # Return value
+    ///
+    /// The time until the next group is due, `Duration::MAX` without groups.
+    pub fn poll(&mut self) -> Duration {
+        let Poller { ref devices, ref mut groups, ref mut callbacks, ref mut senders } = *self;
+
+        for (index, scheduled) in groups.iter_mut().enumerate() {
+            if scheduled.due > Instant::now() {
+                continue;
+            }
+            for change in scheduled.poll(index, &devices[scheduled.device]) {
+                for callback in callbacks.iter_mut() {
+                    callback(&change);
+                }
+                senders.retain(|sender| sender.send(change.clone()).is_ok());
+            }
+
+            scheduled.due += scheduled.group.interval;
+            let now = Instant::now();
+            if scheduled.due <= now {
+                scheduled.due = now + scheduled.group.interval;
+            }
+        }
+
+        let now = Instant::now();
+        groups.iter()
+              .map(|scheduled| scheduled.due.saturating_duration_since(now))
+              .min()
+              .unwrap_or(Duration::MAX)
+    }
+
+    /// `run` - poll until `shutdown` is triggered
+    ///
+    /// Between polls the thread sleeps, the handle is checked at least every 100 ms.
+    pub fn run(&mut self, shutdown: &ShutdownHandle) {
+        while !shutdown.is_shutdown() {
+            let next = self.poll();
+            if !shutdown.is_shutdown() {
+                thread::sleep(next.min(SHUTDOWN_CHECK_INTERVAL));
+            }
+        }
+    }
+}
+
+impl ScheduledGroup {
+    /// Read the group and update the samples, returns the changes
+    fn poll<C: ModbusClient>(&mut self, index: usize, client: &C) -> Vec<Change> {
+        let result = self.group.map.read_all_with(client, &self.group.planner).map(|values| {
+            values.into_iter().map(|(name, value)| (name.to_owned(), value)).collect::<Vec<_>>()
+        });
+        let timestamp = SystemTime::now();
+        let mut changes = Vec::new();
+
+        match result {
+            Ok(values) => {
+                self.last_error = None;
+                for (name, value) in values {
+                    let sample = Sample { value: Some(value), quality: Quality::Good, timestamp };
+                    let changed = match self.samples.get(&name) {
+                        Some(&Sample { value: Some(previous), quality: Quality::Good, .. }) => {
+                            exceeds_deadband(previous, value, self.group.deadband(&name))
+                        },
+                        _ => true,
+                    };
+                    if changed {
+                        self.samples.insert(name.clone(), sample);
+                        changes.push(Change { group: index, name, sample });
+                    } else if let Some(previous) = self.samples.get_mut(&name) {
+                        // the last reported value is kept, so slow drifts are reported once they exceed the deadband
+                        previous.timestamp = timestamp;
+                    }
+                }
+            },
+            Err(error) => {
+                self.last_error = Some(error);
+                for point in self.group.map.points().iter().filter(|point| point.is_readable()) {
+                    let previous = self.samples.get(&point.name).and_then(|sample| sample.value);
+                    let sample = Sample { value: previous, quality: Quality::Stale, timestamp };
+                    if self.samples.get(&point.name).map(|sample| sample.quality) != Some(Quality::Stale) {
+                        self.samples.insert(point.name.clone(), sample);
+                        changes.push(Change { group: index, name: point.name.clone(), sample });
+                    }
+                }
+            },
+        }
+
+        changes
+    }
+}
+
+/// Whether `value` differs from `previous` by more than `deadband`
+fn exceeds_deadband(previous: Value, value: Value, deadband: f64) -> bool {
+    match (previous, value) {
+        (Value::Bool(previous), Value::Bool(value)) => previous != value,
+        (previous, value) => (value.as_f64() - previous.as_f64()).abs() > deadband,
+    }
+}
@@ -0,0 +1,306 @@
+use crate::error::Error;
+use crate::modbus::Modbus;
+use crate::modbus_client::{chunk_failed, ModbusClient};
+use crate::modbus_request::Request;
+use crate::modbus_response::Response;
+use std::ops::Range;
+
+
+/// The data table a [`Point`](struct.Point.html) is read from
+///
+/// With the `serde` feature a table is deserialized from `"coil"`, `"discrete"`, `"holding"` or `"input"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub enum Table {
+    /// Coils, read with function code 0x01
+    #[cfg_attr(feature = "serde", serde(rename = "coil"))]
+    Coils,
+    /// Discrete inputs, read with function code 0x02
+    #[cfg_attr(feature = "serde", serde(rename = "discrete"))]
+    DiscreteInputs,
+    /// Holding registers, read with function code 0x03
+    #[cfg_attr(feature = "serde", serde(rename = "holding"))]
+    HoldingRegisters,
+    /// Input registers, read with function code 0x04
+    #[cfg_attr(feature = "serde", serde(rename = "input"))]
+    InputRegisters,
+}
+
+impl Table {
+    /// Maximal number of values of one read request
+    fn max_read(self) -> u32 {
+        match self {
+            Table::Coils | Table::DiscreteInputs => Modbus::MAX_READ_BITS,
+            Table::HoldingRegisters | Table::InputRegisters => Modbus::MAX_READ_REGISTERS,
+        }
+    }
+
+    /// The read request of `count` values starting at `address`
+    fn request(self, address: u16, count: u16) -> Request {
+        match self {
+            Table::Coils => Request::ReadCoils { address, count },
+            Table::DiscreteInputs => Request::ReadDiscreteInputs { address, count },
+            Table::HoldingRegisters => Request::ReadHoldingRegisters { address, count },
+            Table::InputRegisters => Request::ReadInputRegisters { address, count },
+        }
+    }
+}
+
+/// A value to read, `length` bits or registers starting at `address`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Point {
+    /// The table holding the value
+    pub table: Table,
+    /// Address of the first bit or register
+    pub address: u16,
+    /// Number of bits or registers, e.g. `2` for a 32 bit value in holding registers
+    pub length: u16,
+}
+
+impl Point {
+    /// Create a new point of `length` values starting at `address`
+    pub fn new(table: Table, address: u16, length: u16) -> Point {
+        Point { table, address, length }
+    }
+
+    /// The addresses of the point, as `u32` so the range can end behind address `0xFFFF`
+    fn range(&self) -> Range<u32> {
+        u32::from(self.address)..u32::from(self.address) + u32::from(self.length)
+    }
+}
+
+/// Merges scattered points into as few read requests as possible
+///
+/// Reading many single values one by one spends most of the time on request overhead and turnaround, on a serial
+/// line with a low baud rate this limits the number of values polled per second. The `ReadPlanner` sorts the points
+/// of each [`Table`](enum.Table.html) by address and merges neighbouring points into one request, as long as the
+/// request stays within [`Modbus::MAX_READ_BITS`](struct.Modbus.html#associatedconstant.MAX_READ_BITS) or
+/// [`Modbus::MAX_READ_REGISTERS`](struct.Modbus.html#associatedconstant.MAX_READ_REGISTERS).
+///
+/// Points are only merged if at most [`max_gap`](#method.set_max_gap) unused addresses lie between them. Many
+/// devices answer reads of undefined addresses with an exception, these addresses are excluded with
+/// [`add_forbidden_range()`](#method.add_forbidden_range) and never covered by a request.
+///
+/// The resulting [`ReadPlan`](struct.ReadPlan.html) holds the requests and scatters their responses back to the
+/// points.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{Point, ReadPlanner, Request, Table};
+///
+/// let mut planner = ReadPlanner::new();
+/// planner.set_max_gap(4);
+/// planner.add_forbidden_range(Table::HoldingRegisters, 20, 10);
+///
+/// let points = [Point::new(Table::HoldingRegisters, 0, 2),
+///               Point::new(Table::HoldingRegisters, 5, 1),
+///               Point::new(Table::HoldingRegisters, 30, 2),
+///               Point::new(Table::Coils, 3, 1)];
+/// let plan = planner.plan(&points).unwrap();
+/// assert_eq!(plan.requests(),
+///            &[Request::ReadCoils { address: 3, count: 1 },
+///              Request::ReadHoldingRegisters { address: 0, count: 6 },
+///              Request::ReadHoldingRegisters { address: 30, count: 2 }]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ReadPlanner {
+    max_gap: u16,
+    forbidden: Vec<(Table, Range<u32>)>,
+}
+
+impl ReadPlanner {
+    /// Create a planner which merges adjacent and overlapping points only
+    pub fn new() -> ReadPlanner {
+        ReadPlanner::default()
+    }
+
+    /// `get_max_gap` - get the maximal number of unused addresses read between two points
+    pub fn get_max_gap(&self) -> u16 {
+        self.max_gap
+    }
+
+    /// `set_max_gap` - set the maximal number of unused addresses read between two points, `0` by default
+    ///
+    /// Reading a few unused registers is usually cheaper than another request, on RTU a request costs about as much
+    /// as four registers plus the turnaround time of the device.
+    pub fn set_max_gap(&mut self, max_gap: u16) {
+        self.max_gap = max_gap;
+    }
+
+    /// `add_forbidden_range` - exclude `count` addresses of `table` starting at `address` from all requests
+    pub fn add_forbidden_range(&mut self, table: Table, address: u16, count: u16) {
+        let start = u32::from(address);
+        self.forbidden.push((table, start..start + u32::from(count)));
+    }
+
+    /// `plan` - merge the points into read requests
+    ///
+    /// # Return value
+    ///
+    /// The plan, or `Error::TooManyData` if a point is longer than one request may be or extends beyond address
+    /// `0xFFFF`, and `Error::BadData` if a point is empty or overlaps a forbidden range.
+    pub fn plan(&self, points: &[Point]) -> Result<ReadPlan, Error> {
+        for point in points {
+            // an empty point would be planned as a request for zero values, which the devices reject
+            if point.length == 0 {
+                return Err(Error::BadData);
+            }
+            let range = point.range();
+            if range.end > 0x10000 || u32::from(point.length) > point.table.max_read() {
+                return Err(Error::TooManyData);
+            }
+            if self.is_forbidden(point.table, &range) {
+                return Err(Error::BadData);
+            }
+        }
+
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        order.sort_by_key(|&index| (points[index].table, points[index].address));
+
+        let mut spans: Vec<(Table, Range<u32>)> = Vec::new();
+        let mut locations = vec![(0, 0); points.len()];
+        for index in order {
+            let point = &points[index];
+            let range = point.range();
+
+            let merge = match spans.last() {
+                Some(&(table, ref span)) => {
+                    table == point.table &&
+                    range.start <= span.end + u32::from(self.max_gap) &&
+                    range.end.max(span.end) - span.start <= point.table.max_read() &&
+                    !self.is_forbidden(table, &(span.end..range.start))
+                },
+                None => false,
+            };
+            if merge {
+                let span = &mut spans.last_mut().unwrap().1;
+                span.end = span.end.max(range.end);
+            } else {
+                spans.push((point.table, range.clone()));
+            }
+
+            let span = &spans.last().unwrap().1;
+            locations[index] = (spans.len() - 1, (range.start - span.start) as usize);
+        }
+
+        Ok(ReadPlan {
+            requests: spans.into_iter()
+                           .map(|(table, span)| table.request(span.start as u16, (span.end - span.start) as u16))
+                           .collect(),
+            points: points.iter()
+                          .zip(locations)
+                          .map(|(point, (request, offset))| (request, offset..offset + point.length as usize))
+                          .collect(),
+        })
+    }
+
+    /// Whether `range` of `table` overlaps a forbidden range, empty ranges never do
+    fn is_forbidden(&self, table: Table, range: &Range<u32>) -> bool {
+        range.start < range.end &&
+        self.forbidden.iter().any(|&(forbidden_table, ref forbidden)| {
+            forbidden_table == table && forbidden.start < range.end && range.start < forbidden.end
+        })
+    }
+}
+
+/// The read requests of a [`ReadPlanner`](struct.ReadPlanner.html) and where each point is found in their responses
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadPlan {
+    requests: Vec<Request>,
+    /// Per point the index of its request and the values in the response
+    points: Vec<(usize, Range<usize>)>,
+}
+
+impl ReadPlan {
+    /// The read requests, ordered by table and address
+    pub fn requests(&self) -> &[Request] {
+        &self.requests
+    }
+
+    /// `scatter` - split the responses to the requests into the values of the points
+    ///
+    /// The responses must be in the order of [`requests()`](#method.requests), e.g. collected from a
+    /// [`PipelinedTcpClient`](struct.PipelinedTcpClient.html).
+    ///
+    /// # Return value
+    ///
+    /// A `Response::Bits` or `Response::Registers` for every point, in the order the points were passed to
+    /// [`plan()`](struct.ReadPlanner.html#method.plan). `Error::BadData` if the number of responses does not match
+    /// or a response holds too few values.
+    pub fn scatter(&self, responses: &[Response]) -> Result<Vec<Response>, Error> {
+        if responses.len() != self.requests.len() {
+            return Err(Error::BadData);
+        }
+
+        self.points
+            .iter()
+            .map(|&(request, ref values)| match responses[request] {
+                Response::Bits(ref bits) => {
+                    bits.get(values.clone()).map(|bits| Response::Bits(bits.to_vec())).ok_or(Error::BadData)
+                },
+                Response::Registers(ref registers) => {
+                    registers.get(values.clone())
+                             .map(|registers| Response::Registers(registers.to_vec()))
+                             .ok_or(Error::BadData)
+                },
+                _ => Err(Error::BadData),
+            })
+            .collect()
+    }
+
+    /// `read` - send the requests one by one and scatter the responses to the points
+    ///
+    /// # Return value
+    ///
+    /// The values of the points as by [`scatter()`](#method.scatter). If a request fails, the Error is
+    /// [`Error::ChunkFailed`](enum.Error.html#variant.ChunkFailed) with its address and number of values, the
+    /// remaining requests are not sent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusTCP, Point, ReadPlanner, Response, Table};
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+    ///
+    /// let points: Vec<Point> = (0..300).map(|i| Point::new(Table::HoldingRegisters, i * 7, 2)).collect();
+    /// let mut planner = ReadPlanner::new();
+    /// planner.set_max_gap(5);
+    /// let plan = planner.plan(&points).unwrap();
+    ///
+    /// for (point, value) in points.iter().zip(plan.read(&modbus).unwrap()) {
+    ///     if let Response::Registers(registers) = value {
+    ///         println!("{}: {:?}", point.address, registers);
+    ///     }
+    /// }
+    /// ```
+    pub fn read<C: ModbusClient>(&self, client: &C) -> Result<Vec<Response>, Error> {
+        let mut responses = Vec::with_capacity(self.requests.len());
+        for request in &self.requests {
+            let response = match *request {
+                Request::ReadCoils { address, count } => {
+                    client.read_bits_vec(address, count).map(Response::Bits).map_err(chunk_failed(address, count))?
+                },
+                Request::ReadDiscreteInputs { address, count } => {
+                    client.read_input_bits_vec(address, count)
+                          .map(Response::Bits)
+                          .map_err(chunk_failed(address, count))?
+                },
+                Request::ReadHoldingRegisters { address, count } => {
+                    client.read_registers_vec(address, count)
+                          .map(Response::Registers)
+                          .map_err(chunk_failed(address, count))?
+                },
+                Request::ReadInputRegisters { address, count } => {
+                    client.read_input_registers_vec(address, count)
+                          .map(Response::Registers)
+                          .map_err(chunk_failed(address, count))?
+                },
+                _ => unreachable!("a read plan holds read requests only"),
+            };
+            responses.push(response);
+        }
+
+        self.scatter(&responses)
+    }
+}
@@ -0,0 +1,475 @@
+use crate::error::Error;
+use crate::modbus_client::ModbusClient;
+use crate::modbus_read_planner::{Point, ReadPlanner, Table};
+use crate::modbus_response::Response;
+use crate::modbus_value::{ByteOrder, RegisterValue, WordOrder};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+
+/// Data type of a [`RegisterPoint`](struct.RegisterPoint.html)
+///
+/// With the `serde` feature the type is deserialized from its lower case name, e.g. `"f32"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum DataType {
+    /// A coil or discrete input
+    Bool,
+    /// 1 register
+    U16,
+    /// 1 register
+    I16,
+    /// 2 registers
+    U32,
+    /// 2 registers
+    I32,
+    /// 4 registers
+    U64,
+    /// 4 registers
+    I64,
+    /// 2 registers
+    F32,
+    /// 4 registers
+    F64,
+}
+
+impl DataType {
+    /// Number of bits or registers
+    fn length(self) -> u16 {
+        match self {
+            DataType::Bool | DataType::U16 | DataType::I16 => 1,
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+            DataType::U64 | DataType::I64 | DataType::F64 => 4,
+        }
+    }
+}
+
+/// Whether a [`RegisterPoint`](struct.RegisterPoint.html) is read, written or both
+///
+/// With the `serde` feature the access is deserialized from `"read"`, `"write"` or `"read_write"`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Access {
+    /// Read only, the default
+    #[default]
+    Read,
+    /// Write only
+    Write,
+    /// Read and write
+    ReadWrite,
+}
+
+/// An engineering value read from or written to a [`RegisterMap`](struct.RegisterMap.html)
+///
+/// Points with a scale or an offset, and `f32` and `f64` points, are read as `Float`. Integer points without
+/// scaling keep their exact value as `Unsigned` or `Signed`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    /// Value of a `bool` point
+    Bool(bool),
+    /// Value of an unsigned integer point
+    Unsigned(u64),
+    /// Value of a signed integer point
+    Signed(i64),
+    /// Value of a floating point or scaled point
+    Float(f64),
+}
+
+impl Value {
+    /// The value as `f64`, `1.0` and `0.0` for `Bool`
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Value::Bool(value) => f64::from(u8::from(value)),
+            Value::Unsigned(value) => value as f64,
+            Value::Signed(value) => value as f64,
+            Value::Float(value) => value,
+        }
+    }
+}
+
+/// A named value of a device, the entry of a [`RegisterMap`](struct.RegisterMap.html)
+///
+/// The engineering value is `raw * scale + offset`, where `raw` is the value stored in the device.
+///
+/// With the `serde` feature a point is deserialized from a table with the fields of this struct, the data type is
+/// named `type`. Only `name`, `table`, `address` and `type` are required, the other fields default as in
+/// [`new()`](#method.new).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct RegisterPoint {
+    /// Unique name of the point
+    pub name: String,
+    /// The table holding the point
+    pub table: Table,
+    /// Address of the first bit or register
+    pub address: u16,
+    /// Data type of the raw value
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub data_type: DataType,
+    /// Order of the bytes within each register
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub byte_order: ByteOrder,
+    /// Order of the registers of values spanning several registers
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub word_order: WordOrder,
+    /// Factor of the raw value
+    #[cfg_attr(feature = "serde", serde(default = "default_scale"))]
+    pub scale: f64,
+    /// Added to the scaled raw value
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: f64,
+    /// Unit of the engineering value, for display only
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unit: Option<String>,
+    /// Whether the point is read, written or both
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub access: Access,
+}
+
+#[cfg(feature = "serde")]
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl RegisterPoint {
+    /// Create a read only point with big endian byte and word order, no scaling and no unit
+    pub fn new(name: &str, table: Table, address: u16, data_type: DataType) -> RegisterPoint {
+        RegisterPoint {
+            name: name.to_owned(),
+            table,
+            address,
+            data_type,
+            byte_order: ByteOrder::BigEndian,
+            word_order: WordOrder::BigEndian,
+            scale: 1.0,
+            offset: 0.0,
+            unit: None,
+            access: Access::Read,
+        }
+    }
+
+    pub(crate) fn is_readable(&self) -> bool {
+        self.access != Access::Write
+    }
+
+    fn is_writable(&self) -> bool {
+        self.access != Access::Read
+    }
+
+    fn is_scaled(&self) -> bool {
+        self.scale != 1.0 || self.offset != 0.0
+    }
+
+    fn invalid(&self, reason: &'static str) -> Error {
+        Error::InvalidPoint { name: self.name.clone(), reason }
+    }
+
+    /// Checks the point on its own, the reason is reported as `Error::InvalidRegisterMap`
+    fn validate(&self) -> Result<(), String> {
+        let bits = self.table == Table::Coils || self.table == Table::DiscreteInputs;
+        if bits != (self.data_type == DataType::Bool) {
+            return Err(format!("point {}: type {:?} does not fit table {:?}", self.name, self.data_type, self.table));
+        }
+        if self.is_writable() && (self.table == Table::DiscreteInputs || self.table == Table::InputRegisters) {
+            return Err(format!("point {}: table {:?} is read only", self.name, self.table));
+        }
+        if u32::from(self.address) + u32::from(self.data_type.length()) > 0x10000 {
+            return Err(format!("point {}: address {} out of range", self.name, self.address));
+        }
+        if self.scale == 0.0 || !self.scale.is_finite() || !self.offset.is_finite() {
+            return Err(format!("point {}: invalid scale or offset", self.name));
+        }
+        Ok(())
+    }
+
+    /// The engineering value of the response to a read of the point
+    fn decode(&self, response: &Response) -> Result<Value, Error> {
+        let registers = match *response {
+            Response::Bits(ref bits) => {
+                return bits.first().map(|&bit| Value::Bool(bit)).ok_or(Error::BadData);
+            },
+            Response::Registers(ref registers) => registers,
+            _ => return Err(Error::BadData),
+        };
+
+        let (byte_order, word_order) = (self.byte_order, self.word_order);
+        let raw = match self.data_type {
+            DataType::Bool => return Err(Error::BadData),
+            DataType::U16 => Value::Unsigned(u16::from_registers(registers, byte_order, word_order)?.into()),
+            DataType::I16 => Value::Signed(i16::from_registers(registers, byte_order, word_order)?.into()),
+            DataType::U32 => Value::Unsigned(u32::from_registers(registers, byte_order, word_order)?.into()),
+            DataType::I32 => Value::Signed(i32::from_registers(registers, byte_order, word_order)?.into()),
+            DataType::U64 => Value::Unsigned(u64::from_registers(registers, byte_order, word_order)?),
+            DataType::I64 => Value::Signed(i64::from_registers(registers, byte_order, word_order)?),
+            DataType::F32 => Value::Float(f32::from_registers(registers, byte_order, word_order)?.into()),
+            DataType::F64 => Value::Float(f64::from_registers(registers, byte_order, word_order)?),
+        };
+
+        if self.is_scaled() {
+            Ok(Value::Float(raw.as_f64() * self.scale + self.offset))
+        } else {
+            Ok(raw)
+        }
+    }
+
+    /// The registers holding the engineering value `value`
+    fn encode(&self, value: Value) -> Result<Vec<u16>, Error> {
+        if let Value::Bool(_) = value {
+            return Err(self.invalid("a bool can not be written to registers"));
+        }
+        let raw = (value.as_f64() - self.offset) / self.scale;
+        let integer = || -> Result<i128, Error> {
+            match value {
+                Value::Unsigned(value) if !self.is_scaled() => Ok(i128::from(value)),
+                Value::Signed(value) if !self.is_scaled() => Ok(i128::from(value)),
+                _ if raw.is_finite() => Ok(raw.round() as i128),
+                _ => Err(self.invalid("value out of range")),
+            }
+        };
+        let out_of_range = |_| self.invalid("value out of range");
+
+        let mut registers = vec![0u16; self.data_type.length() as usize];
+        let (dest, byte_order, word_order) = (&mut registers[..], self.byte_order, self.word_order);
+        match self.data_type {
+            DataType::Bool => return Err(self.invalid("a number can not be written to a bit")),
+            DataType::U16 => u16::try_from(integer()?).map_err(out_of_range)?.to_registers(dest, byte_order, word_order),
+            DataType::I16 => i16::try_from(integer()?).map_err(out_of_range)?.to_registers(dest, byte_order, word_order),
+            DataType::U32 => u32::try_from(integer()?).map_err(out_of_range)?.to_registers(dest, byte_order, word_order),
+            DataType::I32 => i32::try_from(integer()?).map_err(out_of_range)?.to_registers(dest, byte_order, word_order),
+            DataType::U64 => u64::try_from(integer()?).map_err(out_of_range)?.to_registers(dest, byte_order, word_order),
+            DataType::I64 => i64::try_from(integer()?).map_err(out_of_range)?.to_registers(dest, byte_order, word_order),
+            DataType::F32 => (raw as f32).to_registers(dest, byte_order, word_order),
+            DataType::F64 => raw.to_registers(dest, byte_order, word_order),
+        }?;
+        Ok(registers)
+    }
+}
+
+/// The named points of a device, read and written by name
+///
+/// Instead of calling [`read_registers()`](struct.ModbusConnection.html#method.read_registers) with addresses and
+/// decoding the registers by hand, the points of a device are described once, in code or, with the `toml` and
+/// `serde_json` cargo features, in a TOML or JSON document. The map reads a point with the
+/// [`ModbusClient`](trait.ModbusClient.html) functions and decodes it into an engineering [`Value`](enum.Value.html),
+/// [`read_all()`](#method.read_all) reads all points with the requests of a
+/// [`ReadPlanner`](struct.ReadPlanner.html).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{Access, DataType, Modbus, ModbusTCP, RegisterMap, RegisterPoint, Table, Value, WordOrder};
+///
+/// let mut voltage = RegisterPoint::new("voltage", Table::InputRegisters, 0, DataType::U16);
+/// voltage.scale = 0.1;
+/// voltage.unit = Some("V".to_owned());
+/// let mut energy = RegisterPoint::new("energy", Table::InputRegisters, 10, DataType::U64);
+/// energy.word_order = WordOrder::LittleEndian;
+/// let mut setpoint = RegisterPoint::new("setpoint", Table::HoldingRegisters, 100, DataType::F32);
+/// setpoint.access = Access::ReadWrite;
+/// let map = RegisterMap::new(vec![voltage, energy, setpoint]).unwrap();
+///
+/// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+/// println!("{:?} V", map.read(&modbus, "voltage").unwrap());
+/// map.write(&modbus, "setpoint", Value::Float(21.5)).unwrap();
+/// for (name, value) in map.read_all(&modbus).unwrap() {
+///     println!("{}: {:?}", name, value);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterMap {
+    points: Vec<RegisterPoint>,
+}
+
+/// The document `RegisterMap::from_toml()` and `from_json()` parse
+#[cfg(any(feature = "toml", feature = "serde_json"))]
+#[derive(serde::Deserialize)]
+struct Document {
+    points: Vec<RegisterPoint>,
+}
+
+impl RegisterMap {
+    /// `new` - create a map of the given points
+    ///
+    /// # Return value
+    ///
+    /// The map, or `Error::InvalidRegisterMap` if two points have the same name, the data type does not fit the table
+    /// (`bool` for coils and discrete inputs only), a point in a read only table is writable, a point extends beyond
+    /// address `0xFFFF` or its scale is zero.
+    pub fn new(points: Vec<RegisterPoint>) -> Result<RegisterMap, Error> {
+        let mut names = HashSet::new();
+        for point in &points {
+            point.validate().map_err(Error::InvalidRegisterMap)?;
+            if !names.insert(point.name.as_str()) {
+                return Err(Error::InvalidRegisterMap(format!("duplicate point {}", point.name)));
+            }
+        }
+
+        Ok(RegisterMap { points })
+    }
+
+    /// `from_toml` - parse a map from TOML, available with the `toml` feature
+    ///
+    /// The points are an array of tables named `points`, see [`RegisterPoint`](struct.RegisterPoint.html) for the
+    /// fields. Syntax errors are reported as `Error::InvalidRegisterMap` as well.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{RegisterMap, Table, WordOrder};
+    ///
+    /// let map = RegisterMap::from_toml(r#"
+    ///     [[points]]
+    ///     name = "voltage"
+    ///     table = "input"
+    ///     address = 0
+    ///     type = "u16"
+    ///     scale = 0.1
+    ///     unit = "V"
+    ///
+    ///     [[points]]
+    ///     name = "energy"
+    ///     table = "input"
+    ///     address = 10
+    ///     type = "u64"
+    ///     word_order = "little_endian"
+    ///
+    ///     [[points]]
+    ///     name = "setpoint"
+    ///     table = "holding"
+    ///     address = 100
+    ///     type = "f32"
+    ///     access = "read_write"
+    /// "#).unwrap();
+    ///
+    /// assert_eq!(map.point("energy").unwrap().word_order, WordOrder::LittleEndian);
+    /// assert_eq!(map.point("setpoint").unwrap().table, Table::HoldingRegisters);
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn from_toml(document: &str) -> Result<RegisterMap, Error> {
+        let document: Document = toml::from_str(document).map_err(|err| Error::InvalidRegisterMap(err.to_string()))?;
+        RegisterMap::new(document.points)
+    }
+
+    /// `from_json` - parse a map from JSON, available with the `serde_json` feature
+    ///
+    /// The document is an object with an array `points`, see [`RegisterPoint`](struct.RegisterPoint.html) for the
+    /// fields. Syntax errors are reported as `Error::InvalidRegisterMap` as well.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{DataType, RegisterMap};
+    ///
+    /// let map = RegisterMap::from_json(r#"{
+    ///     "points": [
+    ///         { "name": "temperature", "table": "input", "address": 4, "type": "i16", "scale": 0.1, "unit": "°C" },
+    ///         { "name": "pump", "table": "coil", "address": 0, "type": "bool", "access": "read_write" }
+    ///     ]
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(map.point("pump").unwrap().data_type, DataType::Bool);
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn from_json(document: &str) -> Result<RegisterMap, Error> {
+        let document: Document =
+            serde_json::from_str(document).map_err(|err| Error::InvalidRegisterMap(err.to_string()))?;
+        RegisterMap::new(document.points)
+    }
+
+    /// All points, in the order they were defined
+    pub fn points(&self) -> &[RegisterPoint] {
+        &self.points
+    }
+
+    /// The point named `name`
+    pub fn point(&self, name: &str) -> Option<&RegisterPoint> {
+        self.points.iter().find(|point| point.name == name)
+    }
+
+    /// `read` - read the point named `name`
+    ///
+    /// # Return value
+    ///
+    /// The engineering value, `Error::InvalidPoint` if there is no such point or it is write only, or the Error of
+    /// the client.
+    pub fn read<C: ModbusClient>(&self, client: &C, name: &str) -> Result<Value, Error> {
+        let point = self.readable_point(name)?;
+        let (address, length) = (point.address, point.data_type.length());
+        let response = match point.table {
+            Table::Coils => Response::Bits(client.read_bits_vec(address, length)?),
+            Table::DiscreteInputs => Response::Bits(client.read_input_bits_vec(address, length)?),
+            Table::HoldingRegisters => Response::Registers(client.read_registers_vec(address, length)?),
+            Table::InputRegisters => Response::Registers(client.read_input_registers_vec(address, length)?),
+        };
+
+        point.decode(&response)
+    }
+
+    /// `read_all` - read all readable points
+    ///
+    /// Neighbouring points are read with one request, see [`ReadPlanner`](struct.ReadPlanner.html). Use
+    /// [`read_all_with()`](#method.read_all_with) to allow gaps or to exclude addresses.
+    ///
+    /// # Return value
+    ///
+    /// The names and values of the points, in the order they were defined. If a request fails, the Error is
+    /// [`Error::ChunkFailed`](enum.Error.html#variant.ChunkFailed).
+    pub fn read_all<C: ModbusClient>(&self, client: &C) -> Result<Vec<(&str, Value)>, Error> {
+        self.read_all_with(client, &ReadPlanner::new())
+    }
+
+    /// `read_all_with` - read all readable points with the requests planned by `planner`
+    pub fn read_all_with<C: ModbusClient>(&self, client: &C, planner: &ReadPlanner)
+                                          -> Result<Vec<(&str, Value)>, Error> {
+        let points: Vec<&RegisterPoint> = self.points.iter().filter(|point| point.is_readable()).collect();
+        let plan = planner.plan(&points.iter()
+                                       .map(|point| Point::new(point.table, point.address, point.data_type.length()))
+                                       .collect::<Vec<_>>())?;
+
+        points.iter()
+              .zip(plan.read(client)?)
+              .map(|(point, response)| Ok((point.name.as_str(), point.decode(&response)?)))
+              .collect()
+    }
+
+    /// `write` - write `value` to the point named `name`
+    ///
+    /// The value is converted to the raw value `(value - offset) / scale`, rounded for integer types. A coil is
+    /// written with [`write_bit()`](struct.ModbusConnection.html#method.write_bit), a single register with
+    /// [`write_register()`](struct.ModbusConnection.html#method.write_register) and larger values with
+    /// [`write_registers()`](struct.ModbusConnection.html#method.write_registers).
+    ///
+    /// # Return value
+    ///
+    /// `Error::InvalidPoint` if there is no such point, it is read only, the value does not fit its data type or
+    /// a `Value::Bool` is written to registers, or the Error of the client.
+    pub fn write<C: ModbusClient>(&self, client: &C, name: &str, value: Value) -> Result<(), Error> {
+        let point = self.point(name).ok_or_else(|| unknown(name))?;
+        if !point.is_writable() {
+            return Err(point.invalid("read only"));
+        }
+
+        if point.table == Table::Coils {
+            return match value {
+                Value::Bool(status) => client.write_bit(point.address, status),
+                _ => Err(point.invalid("a number can not be written to a bit")),
+            };
+        }
+        match point.encode(value)?[..] {
+            [register] => client.write_register(point.address, register),
+            ref registers => client.write_registers(point.address, registers.len() as u16, registers).map(|_| ()),
+        }
+    }
+
+    fn readable_point(&self, name: &str) -> Result<&RegisterPoint, Error> {
+        match self.point(name) {
+            Some(point) if point.is_readable() => Ok(point),
+            Some(point) => Err(point.invalid("write only")),
+            None => Err(unknown(name)),
+        }
+    }
+}
+
+fn unknown(name: &str) -> Error {
+    Error::InvalidPoint { name: name.to_owned(), reason: "no such point" }
+}
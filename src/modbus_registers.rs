@@ -0,0 +1,187 @@
+use crate::error::Error;
+use crate::modbus_client::{chunk_failed, ModbusClient};
+use crate::modbus_mapping::ModbusMapping;
+use crate::modbus_read_planner::{Point, ReadPlanner, Table};
+use crate::modbus_response::Response;
+use crate::modbus_value::{ByteOrder, RegisterValue, WordOrder};
+
+
+/// A field of a [`ModbusRegisters`](trait.ModbusRegisters.html) struct, a `bool` in a bit table or a
+/// [`RegisterValue`](trait.RegisterValue.html) in a register table
+pub trait FieldValue: Sized {
+    /// Number of bits or registers the field occupies
+    const LENGTH: u16;
+
+    /// `decode` - the field from the values read at its point
+    ///
+    /// Fails with `Error::BadData` if the response holds bits instead of registers or vice versa, or too few values.
+    fn decode(response: &Response, byte_order: ByteOrder, word_order: WordOrder) -> Result<Self, Error>;
+
+    /// `encode` - the values to write at the point of the field
+    fn encode(&self, byte_order: ByteOrder, word_order: WordOrder) -> Response;
+}
+
+impl FieldValue for bool {
+    const LENGTH: u16 = 1;
+
+    fn decode(response: &Response, _: ByteOrder, _: WordOrder) -> Result<bool, Error> {
+        match *response {
+            Response::Bits(ref bits) => bits.first().cloned().ok_or(Error::BadData),
+            _ => Err(Error::BadData),
+        }
+    }
+
+    fn encode(&self, _: ByteOrder, _: WordOrder) -> Response {
+        Response::Bits(vec![*self])
+    }
+}
+
+macro_rules! register_field {
+    ($($t:ty),*) => {$(
+        impl FieldValue for $t {
+            const LENGTH: u16 = <$t as RegisterValue>::REGISTERS as u16;
+
+            fn decode(response: &Response, byte_order: ByteOrder, word_order: WordOrder) -> Result<Self, Error> {
+                match *response {
+                    Response::Registers(ref registers) => {
+                        <$t>::from_registers(registers, byte_order, word_order).map_err(|_| Error::BadData)
+                    },
+                    _ => Err(Error::BadData),
+                }
+            }
+
+            fn encode(&self, byte_order: ByteOrder, word_order: WordOrder) -> Response {
+                let mut registers = vec![0u16; <$t as RegisterValue>::REGISTERS];
+                // the vector has the length of the value, encoding can not fail
+                let _ = self.to_registers(&mut registers, byte_order, word_order);
+                Response::Registers(registers)
+            }
+        }
+    )*}
+}
+
+register_field!(u16, i16, u32, i32, u64, i64, f32, f64);
+
+/// A struct whose fields are stored at fixed addresses of a device
+///
+/// The trait is implemented with `#[derive(ModbusRegisters)]`, available with the `derive` cargo feature. Every field
+/// names its table and address in a `#[modbus(...)]` attribute:
+///
+/// * `coil = <address>`, `discrete = <address>` for `bool` fields
+/// * `holding = <address>`, `input = <address>` for `u16`, `i16`, `u32`, `i32`, `u64`, `i64`, `f32` and `f64` fields
+/// * `ty = "<type>_<order>"` optionally names the byte and word order, e.g. `"f32_cdab"`. The letters mean the same
+///   as for the `get_float_*` functions of the [`prelude`](prelude/index.html): `"f32_cdab"` is decoded like
+///   `get_float_cdab()`, the table at [`RegisterValue`](trait.RegisterValue.html) lists the matching orders. `a` is
+///   the least significant byte and the letters follow the order the bytes are stored in, `"u32_dcba"` is big endian.
+///   64 bit values use eight letters, `"u64_badcfehg"` stores the least significant register first. Without an
+///   order values are big endian.
+/// * `byte_order = "little_endian"` and `word_order = "little_endian"` name the orders instead of `ty`, like the
+///   `ByteOrder` and `WordOrder` of a [`RegisterPoint`](struct.RegisterPoint.html). Both are `"big_endian"` by default.
+///
+/// The struct is read from a device with [`read_from()`](#method.read_from) and written with
+/// [`write_to()`](#method.write_to). A server loads it into its [`ModbusMapping`](struct.ModbusMapping.html) with
+/// [`to_mapping()`](#method.to_mapping) and takes written values out with [`from_mapping()`](#method.from_mapping).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "derive")]
+/// # fn example() {
+/// use libmodbus_rs::{Modbus, ModbusRegisters, ModbusTCP};
+///
+/// #[derive(Debug, ModbusRegisters)]
+/// struct Drive {
+///     #[modbus(coil = 0)]
+///     enabled: bool,
+///     #[modbus(holding = 100, ty = "f32_cdab")]
+///     speed: f32,
+///     #[modbus(input = 10)]
+///     temperature: i16,
+///     #[modbus(input = 12, ty = "u64_badcfehg")]
+///     hours: u64,
+/// }
+///
+/// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+/// let mut drive = Drive::read_from(&modbus).unwrap();
+/// drive.speed = 1450.0;
+/// drive.write_to(&modbus).unwrap();
+/// # }
+/// ```
+pub trait ModbusRegisters: Sized {
+    /// The point of every field, in the order of the fields
+    const POINTS: &'static [Point];
+
+    /// `decode` - build the struct from the values of [`POINTS`](#associatedconstant.POINTS), in the same order
+    fn decode(values: &[Response]) -> Result<Self, Error>;
+
+    /// `encode` - the values of [`POINTS`](#associatedconstant.POINTS), in the same order
+    fn encode(&self) -> Vec<Response>;
+
+    /// `read_from` - read all fields from a device
+    ///
+    /// Neighbouring fields are read with one request, see [`ReadPlanner`](struct.ReadPlanner.html).
+    ///
+    /// # Return value
+    ///
+    /// The struct, or [`Error::ChunkFailed`](enum.Error.html#variant.ChunkFailed) with the failed request.
+    fn read_from<C: ModbusClient>(client: &C) -> Result<Self, Error> {
+        let plan = ReadPlanner::new().plan(Self::POINTS)?;
+        Self::decode(&plan.read(client)?)
+    }
+
+    /// `write_to` - write all fields of a device, the fields in coils and holding registers
+    ///
+    /// Every field is written with its own request, fields of discrete inputs and input registers are skipped.
+    ///
+    /// # Return value
+    ///
+    /// An Ok Result if all fields were written, otherwise [`Error::ChunkFailed`](enum.Error.html#variant.ChunkFailed)
+    /// with the address of the failed field. The fields before it were written.
+    fn write_to<C: ModbusClient>(&self, client: &C) -> Result<(), Error> {
+        for (point, value) in Self::POINTS.iter().zip(self.encode()) {
+            let result = match (point.table, value) {
+                (Table::Coils, Response::Bits(ref bits)) if bits.len() == 1 => client.write_bit(point.address, bits[0]),
+                (Table::HoldingRegisters, Response::Registers(ref registers)) if registers.len() == 1 => {
+                    client.write_register(point.address, registers[0])
+                },
+                (Table::HoldingRegisters, Response::Registers(ref registers)) => {
+                    client.write_registers(point.address, registers.len() as u16, registers).map(|_| ())
+                },
+                (Table::Coils, _) | (Table::HoldingRegisters, _) => Err(Error::BadData),
+                (Table::DiscreteInputs, _) | (Table::InputRegisters, _) => continue,
+            };
+            result.map_err(chunk_failed(point.address, point.length))?;
+        }
+        Ok(())
+    }
+
+    /// `from_mapping` - take all fields out of the tables of a mapping
+    ///
+    /// # Return value
+    ///
+    /// The struct, or `Error::Exception(Exception::IllegalDataAddress)` if a field lies outside its table.
+    fn from_mapping(mapping: &ModbusMapping) -> Result<Self, Error> {
+        let mut guard = mapping.lock();
+        let values = Self::POINTS.iter()
+                                 .map(|point| guard.read_point(point).map_err(Error::Exception))
+                                 .collect::<Result<Vec<_>, Error>>()?;
+        Self::decode(&values)
+    }
+
+    /// `to_mapping` - store all fields in the tables of a mapping, including discrete inputs and input registers
+    ///
+    /// # Return value
+    ///
+    /// An Ok Result, or `Error::Exception(Exception::IllegalDataAddress)` if a field lies outside its table. The
+    /// mapping is not modified then.
+    fn to_mapping(&self, mapping: &ModbusMapping) -> Result<(), Error> {
+        let mut guard = mapping.lock();
+        for point in Self::POINTS {
+            guard.read_point(point).map_err(Error::Exception)?;
+        }
+        for (point, value) in Self::POINTS.iter().zip(self.encode()) {
+            guard.write_point(point, &value).map_err(Error::Exception)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,350 @@
+use crate::error::Error;
+use crate::frame::{rtu, tcp};
+use crate::modbus::{Exception, FunctionCode, Modbus};
+use std::convert::TryFrom;
+
+
+/// A request as received by [`receive()`](struct.ModbusConnection.html#method.receive), decoded from its PDU
+///
+/// Addresses and counts are taken as sent by the client, they are not checked against a
+/// [`ModbusMapping`](struct.ModbusMapping.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    /// 0x01 Read Coils
+    ReadCoils { address: u16, count: u16 },
+    /// 0x02 Read Discrete Inputs
+    ReadDiscreteInputs { address: u16, count: u16 },
+    /// 0x03 Read Multiple Holding Registers
+    ReadHoldingRegisters { address: u16, count: u16 },
+    /// 0x04 Read Input Registers
+    ReadInputRegisters { address: u16, count: u16 },
+    /// 0x05 Write Single Coil
+    WriteSingleCoil { address: u16, value: bool },
+    /// 0x06 Write Single Holding Register
+    WriteSingleRegister { address: u16, value: u16 },
+    /// 0x0F Write Multiple Coils
+    WriteMultipleCoils { address: u16, values: Vec<bool> },
+    /// 0x10 Write Multiple Holding Registers
+    WriteMultipleRegisters { address: u16, values: Vec<u16> },
+    /// 0x11 Report Slave ID
+    ReportSlaveId,
+    /// 0x16 Mask Write Register, the new value is `(current & and_mask) | (or_mask & !and_mask)`
+    MaskWriteRegister { address: u16, and_mask: u16, or_mask: u16 },
+    /// 0x17 Read/Write Multiple Registers, the write is performed before the read
+    WriteAndReadRegisters {
+        read_address: u16,
+        read_count: u16,
+        write_address: u16,
+        values: Vec<u16>,
+    },
+    /// Any other function code with the raw data following it
+    Other { function: u8, data: Vec<u8> },
+}
+
+impl Request {
+    /// `from_pdu` - decode a request PDU, function code followed by the data
+    ///
+    /// # Return value
+    ///
+    /// The request, `Error::IncompleteFrame` if the PDU is shorter than its function code requires or `Error::BadData`
+    /// if byte count and number of values do not match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::Request;
+    ///
+    /// let request = Request::from_pdu(&[0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02]).unwrap();
+    /// assert_eq!(request, Request::WriteMultipleRegisters { address: 1, values: vec![0x000A, 0x0102] });
+    /// ```
+    pub fn from_pdu(pdu: &[u8]) -> Result<Request, Error> {
+        let (&function, data) = pdu.split_first().ok_or(Error::IncompleteFrame)?;
+        let word = |index: usize| -> Result<u16, Error> {
+            match data.get(index..index + 2) {
+                Some(bytes) => Ok(u16::from(bytes[0]) << 8 | u16::from(bytes[1])),
+                None => Err(Error::IncompleteFrame),
+            }
+        };
+
+        let request = match FunctionCode::try_from(function) {
+            Ok(FunctionCode::ReadCoils) => Request::ReadCoils { address: word(0)?, count: word(2)? },
+            Ok(FunctionCode::ReadDiscreteInputs) => Request::ReadDiscreteInputs { address: word(0)?, count: word(2)? },
+            Ok(FunctionCode::ReadHoldingRegisters) => {
+                Request::ReadHoldingRegisters { address: word(0)?, count: word(2)? }
+            },
+            Ok(FunctionCode::ReadInputRegisters) => Request::ReadInputRegisters { address: word(0)?, count: word(2)? },
+            Ok(FunctionCode::WriteSingleCoil) => {
+                let value = match word(2)? {
+                    0xFF00 => true,
+                    0x0000 => false,
+                    _ => return Err(Error::BadData),
+                };
+                Request::WriteSingleCoil { address: word(0)?, value }
+            },
+            Ok(FunctionCode::WriteSingleRegister) => {
+                Request::WriteSingleRegister { address: word(0)?, value: word(2)? }
+            },
+            Ok(FunctionCode::WriteMultipleCoils) => {
+                let values = bit_values(data, 4, word(2)? as usize)?;
+                Request::WriteMultipleCoils { address: word(0)?, values }
+            },
+            Ok(FunctionCode::WriteMultipleRegisters) => {
+                let values = register_values(data, 4, word(2)? as usize)?;
+                Request::WriteMultipleRegisters { address: word(0)?, values }
+            },
+            Ok(FunctionCode::ReportSlaveId) => Request::ReportSlaveId,
+            Ok(FunctionCode::MaskWriteRegister) => {
+                Request::MaskWriteRegister { address: word(0)?, and_mask: word(2)?, or_mask: word(4)? }
+            },
+            Ok(FunctionCode::WriteAndReadRegisters) => {
+                let values = register_values(data, 8, word(6)? as usize)?;
+                Request::WriteAndReadRegisters {
+                    read_address: word(0)?,
+                    read_count: word(2)?,
+                    write_address: word(4)?,
+                    values,
+                }
+            },
+            _ => Request::Other { function, data: data.to_vec() },
+        };
+
+        Ok(request)
+    }
+
+    /// `to_pdu` - encode the request PDU, as sent by a client
+    ///
+    /// The number of values is not checked against the protocol limits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::Request;
+    ///
+    /// let request = Request::WriteMultipleCoils { address: 0x13, values: vec![true, false, true, true] };
+    /// assert_eq!(request.to_pdu(), vec![0x0F, 0x00, 0x13, 0x00, 0x04, 0x01, 0x0D]);
+    /// assert_eq!(Request::from_pdu(&request.to_pdu()).unwrap(), request);
+    /// ```
+    pub fn to_pdu(&self) -> Vec<u8> {
+        let mut pdu = vec![self.function()];
+
+        match *self {
+            Request::ReadCoils { address, count } |
+            Request::ReadDiscreteInputs { address, count } |
+            Request::ReadHoldingRegisters { address, count } |
+            Request::ReadInputRegisters { address, count } => {
+                push_word(&mut pdu, address);
+                push_word(&mut pdu, count);
+            },
+            Request::WriteSingleCoil { address, value } => {
+                push_word(&mut pdu, address);
+                push_word(&mut pdu, if value { 0xFF00 } else { 0x0000 });
+            },
+            Request::WriteSingleRegister { address, value } => {
+                push_word(&mut pdu, address);
+                push_word(&mut pdu, value);
+            },
+            Request::WriteMultipleCoils { address, ref values } => {
+                push_word(&mut pdu, address);
+                push_word(&mut pdu, values.len() as u16);
+                push_bits(&mut pdu, values);
+            },
+            Request::WriteMultipleRegisters { address, ref values } => {
+                push_word(&mut pdu, address);
+                push_word(&mut pdu, values.len() as u16);
+                push_registers(&mut pdu, values);
+            },
+            Request::ReportSlaveId => {},
+            Request::MaskWriteRegister { address, and_mask, or_mask } => {
+                push_word(&mut pdu, address);
+                push_word(&mut pdu, and_mask);
+                push_word(&mut pdu, or_mask);
+            },
+            Request::WriteAndReadRegisters { read_address, read_count, write_address, ref values } => {
+                push_word(&mut pdu, read_address);
+                push_word(&mut pdu, read_count);
+                push_word(&mut pdu, write_address);
+                push_word(&mut pdu, values.len() as u16);
+                push_registers(&mut pdu, values);
+            },
+            Request::Other { ref data, .. } => pdu.extend_from_slice(data),
+        }
+
+        pdu
+    }
+
+    /// Checks a response PDU to this request and returns its data, without the function code
+    ///
+    /// An exception response is returned as `Error::Exception`, a response of another function or a write response
+    /// not repeating the written address and values as `Error::BadData`. The byte counts of read responses are
+    /// checked when the values are decoded.
+    pub(crate) fn check_response<'a>(&self, pdu: &'a [u8]) -> Result<&'a [u8], Error> {
+        let (&function, data) = pdu.split_first().ok_or(Error::IncompleteFrame)?;
+        if function == self.function() | 0x80 {
+            let code = *data.first().ok_or(Error::IncompleteFrame)?;
+            return Err(Error::Exception(Exception::try_from(code)?));
+        }
+        if function != self.function() {
+            return Err(Error::BadData);
+        }
+
+        let echo_length = match *self {
+            Request::WriteSingleCoil { .. } |
+            Request::WriteSingleRegister { .. } |
+            Request::WriteMultipleCoils { .. } |
+            Request::WriteMultipleRegisters { .. } => 4,
+            Request::MaskWriteRegister { .. } => 6,
+            _ => return Ok(data),
+        };
+        // the response repeats the address and the value or number of values of the request
+        if data.len() != echo_length || data[..] != self.to_pdu()[1..1 + echo_length] {
+            return Err(Error::BadData);
+        }
+
+        Ok(data)
+    }
+
+    /// Function code of the request
+    pub fn function(&self) -> u8 {
+        match *self {
+            Request::ReadCoils { .. } => FunctionCode::ReadCoils as u8,
+            Request::ReadDiscreteInputs { .. } => FunctionCode::ReadDiscreteInputs as u8,
+            Request::ReadHoldingRegisters { .. } => FunctionCode::ReadHoldingRegisters as u8,
+            Request::ReadInputRegisters { .. } => FunctionCode::ReadInputRegisters as u8,
+            Request::WriteSingleCoil { .. } => FunctionCode::WriteSingleCoil as u8,
+            Request::WriteSingleRegister { .. } => FunctionCode::WriteSingleRegister as u8,
+            Request::WriteMultipleCoils { .. } => FunctionCode::WriteMultipleCoils as u8,
+            Request::WriteMultipleRegisters { .. } => FunctionCode::WriteMultipleRegisters as u8,
+            Request::ReportSlaveId => FunctionCode::ReportSlaveId as u8,
+            Request::MaskWriteRegister { .. } => FunctionCode::MaskWriteRegister as u8,
+            Request::WriteAndReadRegisters { .. } => FunctionCode::WriteAndReadRegisters as u8,
+            Request::Other { function, .. } => function,
+        }
+    }
+}
+
+/// Appends a big endian word
+pub(crate) fn push_word(pdu: &mut Vec<u8>, value: u16) {
+    pdu.push((value >> 8) as u8);
+    pdu.push(value as u8);
+}
+
+/// Appends the byte count and the values packed into bytes, the first value in the least significant bit
+pub(crate) fn push_bits(pdu: &mut Vec<u8>, values: &[bool]) {
+    pdu.push(values.len().div_ceil(8) as u8);
+    for chunk in values.chunks(8) {
+        pdu.push(chunk.iter().enumerate().fold(0, |byte, (i, &bit)| byte | (bit as u8) << i));
+    }
+}
+
+/// Appends the byte count and the big endian registers
+pub(crate) fn push_registers(pdu: &mut Vec<u8>, values: &[u16]) {
+    pdu.push((values.len() * 2) as u8);
+    for &value in values {
+        push_word(pdu, value);
+    }
+}
+
+/// Checks the byte count at `data[offset]` and returns the `len` bytes following it
+pub(crate) fn byte_values(data: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    let byte_count = *data.get(offset).ok_or(Error::IncompleteFrame)? as usize;
+    if byte_count != len {
+        return Err(Error::BadData);
+    }
+    data.get(offset + 1..offset + 1 + len).ok_or(Error::IncompleteFrame)
+}
+
+/// Checks the byte count at `data[offset]` and unpacks the `count` bits following it
+pub(crate) fn bit_values(data: &[u8], offset: usize, count: usize) -> Result<Vec<bool>, Error> {
+    let bytes = byte_values(data, offset, count.div_ceil(8))?;
+    Ok((0..count).map(|i| bytes[i / 8] & (1 << (i % 8)) != 0).collect())
+}
+
+/// Checks the byte count at `data[offset]` and decodes the `count` big endian registers following it
+pub(crate) fn register_values(data: &[u8], offset: usize, count: usize) -> Result<Vec<u16>, Error> {
+    let bytes = byte_values(data, offset, count * 2)?;
+    Ok(bytes.chunks(2).map(|word| u16::from(word[0]) << 8 | u16::from(word[1])).collect())
+}
+
+/// A received request together with the addressing information of its frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Indication {
+    /// Slave address (RTU) or unit id (TCP) the request was sent to
+    pub slave: u8,
+    /// Transaction id of a TCP request, `None` for RTU
+    pub transaction_id: Option<u16>,
+    /// The decoded request
+    pub request: Request,
+}
+
+impl Indication {
+    /// `parse` - decode the data returned by [`receive()`](struct.ModbusConnection.html#method.receive)
+    ///
+    /// The backend is determined with [`get_header_length()`](struct.Modbus.html#method.get_header_length) of the
+    /// context, which received the request.
+    ///
+    /// # Parameters
+    ///
+    /// * `modbus`  - the context the request was received with
+    /// * `query`   - the received data, truncated to the length returned by `receive()`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Indication, Modbus, ModbusServer, ModbusTCP, Request};
+    ///
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = modbus.tcp_listen(1).unwrap().tcp_accept().unwrap();
+    /// let mut query = vec![0; Modbus::MAX_ADU_LENGTH];
+    ///
+    /// let len = modbus.receive(&mut query).unwrap();
+    /// match Indication::parse(&modbus, &query[..len as usize]).unwrap().request {
+    ///     Request::ReadHoldingRegisters { address, count } => println!("read {} registers at {}", count, address),
+    ///     other => println!("{:?}", other),
+    /// }
+    /// ```
+    pub fn parse(modbus: &Modbus, query: &[u8]) -> Result<Indication, Error> {
+        Indication::from_bytes(query, modbus.get_header_length() as usize)
+    }
+
+    /// `from_bytes` - decode a received request with the given header length
+    ///
+    /// A header length of [`frame::tcp::HEADER_LENGTH`](frame/tcp/constant.HEADER_LENGTH.html) selects TCP framing,
+    /// the PDU ends at the length announced in the MBAP header. Otherwise the frame is a RTU frame, the first byte is
+    /// the slave address and the CRC is removed from the end.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Indication, Request};
+    ///
+    /// let query = [0x00, 0x2A, 0x00, 0x00, 0x00, 0x06, 0xFF, 0x01, 0x00, 0x13, 0x00, 0x25];
+    /// let indication = Indication::from_bytes(&query, 7).unwrap();
+    ///
+    /// assert_eq!(indication.transaction_id, Some(0x2A));
+    /// assert_eq!(indication.slave, 0xFF);
+    /// assert_eq!(indication.request, Request::ReadCoils { address: 0x13, count: 0x25 });
+    /// ```
+    pub fn from_bytes(query: &[u8], header_length: usize) -> Result<Indication, Error> {
+        if header_length == tcp::HEADER_LENGTH {
+            let header = tcp::Header::decode(query)?;
+            let pdu = query.get(tcp::HEADER_LENGTH..header.frame_length()).ok_or(Error::IncompleteFrame)?;
+
+            Ok(Indication {
+                slave: header.unit_id,
+                transaction_id: Some(header.transaction_id),
+                request: Request::from_pdu(pdu)?,
+            })
+        } else {
+            if query.len() < header_length + rtu::CRC_LENGTH || header_length == 0 {
+                return Err(Error::IncompleteFrame);
+            }
+            let pdu = &query[header_length..query.len() - rtu::CRC_LENGTH];
+
+            Ok(Indication {
+                slave: query[header_length - 1],
+                transaction_id: None,
+                request: Request::from_pdu(pdu)?,
+            })
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error::Error;
+use crate::modbus_request::{bit_values, byte_values, register_values, Request};
+
+
+/// A response received by a client, decoded according to the [`Request`](enum.Request.html) it answers
+///
+/// Write responses only repeat the request, they are checked and reported as `Written`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// Values of a Read Coils or Read Discrete Inputs request
+    Bits(Vec<bool>),
+    /// Values of a Read Holding Registers, Read Input Registers or Read/Write Multiple Registers request
+    Registers(Vec<u16>),
+    /// Data following the byte count of a Report Slave ID response, or the raw data of any other function
+    Data(Vec<u8>),
+    /// A write request was performed
+    Written,
+}
+
+impl Response {
+    /// `decode` - check and decode the response PDU to `request`
+    ///
+    /// # Return value
+    ///
+    /// The response, `Error::Exception` for an exception response and `Error::BadData` if the function code, byte
+    /// count or number of values do not match the request, or a write response does not repeat the request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::{Error, Exception, Request, Response};
+    ///
+    /// let request = Request::ReadHoldingRegisters { address: 0x6B, count: 2 };
+    /// assert_eq!(Response::decode(&request, &[0x03, 0x04, 0x02, 0x2B, 0x00, 0x00]).unwrap(),
+    ///            Response::Registers(vec![0x022B, 0x0000]));
+    ///
+    /// match Response::decode(&request, &[0x83, 0x02]) {
+    ///     Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+    ///     _ => panic!("exception not detected"),
+    /// }
+    /// ```
+    pub fn decode(request: &Request, pdu: &[u8]) -> Result<Response, Error> {
+        let data = request.check_response(pdu)?;
+
+        let response = match *request {
+            Request::ReadCoils { count, .. } | Request::ReadDiscreteInputs { count, .. } => {
+                Response::Bits(bit_values(data, 0, count as usize)?)
+            },
+            Request::ReadHoldingRegisters { count, .. } | Request::ReadInputRegisters { count, .. } => {
+                Response::Registers(register_values(data, 0, count as usize)?)
+            },
+            Request::WriteAndReadRegisters { read_count, .. } => {
+                Response::Registers(register_values(data, 0, read_count as usize)?)
+            },
+            Request::ReportSlaveId => {
+                let byte_count = *data.first().ok_or(Error::IncompleteFrame)? as usize;
+                Response::Data(byte_values(data, 0, byte_count)?.to_vec())
+            },
+            Request::Other { .. } => Response::Data(data.to_vec()),
+            Request::WriteSingleCoil { .. } |
+            Request::WriteSingleRegister { .. } |
+            Request::WriteMultipleCoils { .. } |
+            Request::WriteMultipleRegisters { .. } |
+            Request::MaskWriteRegister { .. } => Response::Written,
+        };
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,386 @@
+use crate::error::{Error, IntoInnerError};
+use crate::modbus::{Modbus, ModbusConnection};
+use crate::modbus_client::ModbusClient;
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+
+/// Decides whether a failed attempt is retried
+type RetryOn = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// How often and how fast a failed operation is retried
+///
+/// An operation is attempted up to [`get_max_attempts()`](#method.get_max_attempts) times. Between two attempts the
+/// policy waits an exponentially growing backoff: the initial backoff before the first retry, multiplied by the
+/// multiplier before every further retry, at most the maximum backoff. The jitter shortens every backoff by a random
+/// fraction, so many clients started at the same time do not retry in lockstep.
+///
+/// Only the errors accepted by the retry predicate are retried, by default the
+/// [transient errors](enum.Error.html#method.is_transient).
+///
+/// | Setting       | Default  |
+/// |---------------|----------|
+/// | max attempts  | 3        |
+/// | backoff       | 100 ms   |
+/// | max backoff   | 10 s     |
+/// | multiplier    | 2.0      |
+/// | jitter        | 0.1      |
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{Error, Modbus, ModbusClient, ModbusTCP, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let mut policy = RetryPolicy::new();
+/// policy.set_max_attempts(5);
+/// policy.set_backoff(Duration::from_millis(200), Duration::from_secs(5));
+/// policy.set_retry_on(|error| match *error {
+///     Error::Timeout => true,
+///     _ => false,
+/// });
+///
+/// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap().connect().unwrap();
+/// let registers = policy.retry(|| modbus.read_registers_vec(0, 10)).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retry_on: RetryOn,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_attempts: 3,
+                      backoff: Duration::from_millis(100),
+                      max_backoff: Duration::from_secs(10),
+                      multiplier: 2.0,
+                      jitter: 0.1,
+                      retry_on: Arc::new(Error::is_transient) }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+         .field("max_attempts", &self.max_attempts)
+         .field("backoff", &self.backoff)
+         .field("max_backoff", &self.max_backoff)
+         .field("multiplier", &self.multiplier)
+         .field("jitter", &self.jitter)
+         .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy with the default settings
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// A policy attempting every operation once
+    pub fn never() -> RetryPolicy {
+        RetryPolicy { max_attempts: 1, ..RetryPolicy::default() }
+    }
+
+    /// Number of attempts, including the first one
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Set the number of attempts, including the first one. `0` is treated as `1`.
+    pub fn set_max_attempts(&mut self, max_attempts: u32) {
+        self.max_attempts = max_attempts.max(1);
+    }
+
+    /// Set the backoff before the first retry and the longest backoff
+    pub fn set_backoff(&mut self, backoff: Duration, max_backoff: Duration) {
+        self.backoff = backoff;
+        self.max_backoff = max_backoff;
+    }
+
+    /// Set the factor the backoff grows with every retry, values below `1.0` are treated as `1.0`
+    pub fn set_multiplier(&mut self, multiplier: f64) {
+        self.multiplier = multiplier.max(1.0);
+    }
+
+    /// Set the largest fraction every backoff is shortened by, between `0.0` (no jitter) and `1.0`
+    pub fn set_jitter(&mut self, jitter: f64) {
+        self.jitter = if jitter > 0.0 { jitter.min(1.0) } else { 0.0 };
+    }
+
+    /// Set the predicate deciding which errors are retried
+    pub fn set_retry_on<F: Fn(&Error) -> bool + Send + Sync + 'static>(&mut self, retry_on: F) {
+        self.retry_on = Arc::new(retry_on);
+    }
+
+    /// `backoff` - the backoff before retry number `retry`, starting with `1`, without jitter
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libmodbus_rs::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy::new();
+    /// assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    /// assert_eq!(policy.backoff(3), Duration::from_millis(400));
+    /// assert_eq!(policy.backoff(20), Duration::from_secs(10));
+    /// ```
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::from_secs_f64(backoff.min(self.max_backoff.as_secs_f64()))
+    }
+
+    /// `retry` - call `operation` until it succeeds, fails with an Error that is not retried or the attempts are used
+    ///
+    /// # Return value
+    ///
+    /// The result of the last attempt.
+    pub fn retry<T, F>(&self, mut operation: F) -> Result<T, Error>
+        where F: FnMut() -> Result<T, Error>
+    {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(ref error) if attempt < self.max_attempts && (self.retry_on)(error) => {},
+                result => return result,
+            }
+            thread::sleep(self.backoff(attempt).mul_f64(1.0 - self.jitter * rand::random::<f64>()));
+            attempt += 1;
+        }
+    }
+}
+
+/// The libmodbus context of a [`ReconnectingClient`](struct.ReconnectingClient.html), connected or not
+#[derive(Debug)]
+enum Link {
+    Disconnected(Modbus),
+    Connected(ModbusConnection),
+    // only while the context is moved between the other states
+    Closing,
+}
+
+impl Link {
+    fn connection(&mut self) -> Result<&ModbusConnection, Error> {
+        if let Link::Disconnected(_) = *self {
+            let modbus = match mem::replace(self, Link::Closing) {
+                Link::Disconnected(modbus) => modbus,
+                _ => unreachable!(),
+            };
+            match modbus.connect().map_err(IntoInnerError::into_parts) {
+                Ok(connection) => *self = Link::Connected(connection),
+                Err((error, modbus)) => {
+                    *self = Link::Disconnected(modbus);
+                    return Err(error);
+                },
+            }
+        }
+
+        match *self {
+            Link::Connected(ref connection) => Ok(connection),
+            _ => unreachable!(),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        *self = match mem::replace(self, Link::Closing) {
+            Link::Connected(connection) => Link::Disconnected(connection.close()),
+            link => link,
+        };
+    }
+
+    fn modbus_mut(&mut self) -> &mut Modbus {
+        match *self {
+            Link::Disconnected(ref mut modbus) => modbus,
+            Link::Connected(ref mut connection) => connection,
+            Link::Closing => unreachable!(),
+        }
+    }
+}
+
+/// A client that connects on demand and retries failed requests with a [`RetryPolicy`](struct.RetryPolicy.html)
+///
+/// The client owns a libmodbus context that is not yet connected, it connects with the first request. If a request
+/// fails with an I/O error or a timeout the connection is closed, the next attempt connects again. A failing connect
+/// is an attempt as well, so the policy also limits the connection attempts. The timeout closes the connection as a
+/// link that silently dropped, e.g. by a cellular modem, often only shows as timeout, and a late response must not be
+/// taken for the response of the next request.
+///
+/// Every request of [`ModbusClient`](trait.ModbusClient.html) is retried as a whole, the requests of a range read or
+/// write are retried one by one, chunks already done are not sent again. Only
+/// [`send_raw_request()`](#method.send_raw_request) and [`receive_confirmation()`](#method.receive_confirmation) are
+/// attempted once, they belong to one exchange.
+///
+/// Compared to [`set_error_recovery()`](struct.Modbus.html#method.set_error_recovery) with
+/// `ErrorRecoveryMode::Link`, which reconnects within one call of libmodbus, the attempts, backoff and retried
+/// errors are configurable. The context should not enable the link recovery of libmodbus as well.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP, ReconnectingClient, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let mut modbus = Modbus::new_tcp("10.0.0.20", 502).unwrap();
+/// modbus.set_response_timeout_duration(Duration::from_secs(2)).unwrap();
+///
+/// let mut policy = RetryPolicy::new();
+/// policy.set_max_attempts(10);
+/// policy.set_backoff(Duration::from_secs(1), Duration::from_secs(60));
+///
+/// let client = ReconnectingClient::new(modbus, policy);
+/// let registers = client.read_registers_vec(0, 10).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ReconnectingClient {
+    link: RefCell<Link>,
+    policy: RetryPolicy,
+}
+
+impl ReconnectingClient {
+    /// `new` - create a client for `modbus`, a context that is not connected
+    ///
+    /// No connection is made until the first request.
+    pub fn new(modbus: Modbus, policy: RetryPolicy) -> ReconnectingClient {
+        ReconnectingClient { link: RefCell::new(Link::Disconnected(modbus)), policy }
+    }
+
+    /// The policy requests are retried with
+    pub fn get_policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Set the policy requests are retried with
+    pub fn set_policy(&mut self, policy: RetryPolicy) {
+        self.policy = policy;
+    }
+
+    /// `true` while the client has a connection
+    pub fn is_connected(&self) -> bool {
+        matches!(*self.link.borrow(), Link::Connected(_))
+    }
+
+    /// `connect` - connect now, instead of with the next request
+    ///
+    /// The connection is attempted according to the policy, an existing connection is kept.
+    pub fn connect(&self) -> Result<(), Error> {
+        self.policy.retry(|| self.link.borrow_mut().connection().map(|_| ()))
+    }
+
+    /// Close the connection, the next request connects again
+    pub fn disconnect(&self) {
+        self.link.borrow_mut().disconnect();
+    }
+
+    /// The context, to change its settings like the slave or the response timeout
+    pub fn modbus_mut(&mut self) -> &mut Modbus {
+        self.link.get_mut().modbus_mut()
+    }
+
+    /// `into_inner` - close the connection and return the context
+    pub fn into_inner(self) -> Modbus {
+        let mut link = self.link.into_inner();
+        link.disconnect();
+        match link {
+            Link::Disconnected(modbus) => modbus,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Attempts `request` according to the policy, the connection is closed after an Error of the link
+    fn request<T, F>(&self, mut request: F) -> Result<T, Error>
+        where F: FnMut(&ModbusConnection) -> Result<T, Error>
+    {
+        self.policy.retry(|| self.attempt(&mut request))
+    }
+
+    fn attempt<T, F>(&self, request: &mut F) -> Result<T, Error>
+        where F: FnMut(&ModbusConnection) -> Result<T, Error>
+    {
+        let mut link = self.link.borrow_mut();
+        let result = request(link.connection()?);
+        if let Err(ref error) = result {
+            if drops_link(error) {
+                link.disconnect();
+            }
+        }
+        result
+    }
+}
+
+/// Whether the connection is closed after `error`
+fn drops_link(error: &Error) -> bool {
+    match *error {
+        Error::Timeout | Error::IoError(_) => true,
+        Error::ChunkFailed { ref error, .. } => drops_link(error),
+        _ => false,
+    }
+}
+
+impl ModbusClient for ReconnectingClient {
+    fn read_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_bits(address, num, dest))
+    }
+
+    fn read_input_bits(&self, address: u16, num: u16, dest: &mut [u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_input_bits(address, num, dest))
+    }
+
+    fn read_registers(&self, address: u16, num: u16, dest: &mut [u16]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_registers(address, num, dest))
+    }
+
+    fn read_input_registers(&self, address: u16, num: u16, dest: &mut [u16]) -> Result<u16, Error> {
+        self.request(|connection| connection.read_input_registers(address, num, dest))
+    }
+
+    fn report_slave_id(&self, max_dest: usize, dest: &mut [u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.report_slave_id(max_dest, dest))
+    }
+
+    fn write_bit(&self, address: u16, status: bool) -> Result<(), Error> {
+        self.request(|connection| connection.write_bit(address, status))
+    }
+
+    fn write_bits(&self, address: u16, num: u16, src: &[u8]) -> Result<u16, Error> {
+        self.request(|connection| connection.write_bits(address, num, src))
+    }
+
+    fn write_register(&self, address: u16, value: u16) -> Result<(), Error> {
+        self.request(|connection| connection.write_register(address, value))
+    }
+
+    fn write_registers(&self, address: u16, num: u16, src: &[u16]) -> Result<u16, Error> {
+        self.request(|connection| connection.write_registers(address, num, src))
+    }
+
+    fn write_and_read_registers(&self, write_address: u16, write_num: u16, src: &[u16], read_address: u16,
+                                read_num: u16, dest: &mut [u16])
+                                -> Result<u16, Error> {
+        self.request(|connection| {
+                connection.write_and_read_registers(write_address, write_num, src, read_address, read_num, dest)
+            })
+    }
+
+    fn mask_write_register(&self, address: u16, and_mask: u16, or_mask: u16) -> Result<(), Error> {
+        self.request(|connection| connection.mask_write_register(address, and_mask, or_mask))
+    }
+
+    fn send_raw_request(&self, raw_request: &mut [u8], lenght: usize) -> Result<u16, Error> {
+        self.attempt(&mut |connection: &ModbusConnection| connection.send_raw_request(raw_request, lenght))
+    }
+
+    fn receive_confirmation(&self, response: &mut [u8]) -> Result<u16, Error> {
+        self.attempt(&mut |connection: &ModbusConnection| connection.receive_confirmation(response))
+    }
+}
@@ -1,9 +1,12 @@
-use failure::Error;
+use crate::error::Error;
+use crate::modbus::Modbus;
 use libc::{c_char, c_int};
 use libmodbus_sys as ffi;
-use modbus::Modbus;
 use std::ffi::CString;
 use std::str;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{PoisonError, RwLock};
 
 
 #[derive(Debug, PartialEq)]
@@ -20,6 +23,40 @@ pub enum RequestToSendMode {
     RtuRtsDown = ffi::MODBUS_RTU_RTS_DOWN as isize,
 }
 
+/// Closures set by [`rtu_set_custom_rts()`](struct.Modbus.html#method.rtu_set_custom_rts), keyed by the address of
+/// the libmodbus context they belong to
+///
+/// libmodbus 3.1.4 passes nothing but the context to the RTS callback and has no user data pointer, so the callback
+/// looks the closure up here. The closure itself is owned by the [`Modbus`](struct.Modbus.html) context, an entry only
+/// points to it and is removed before the context is freed. The lock is held for the lookup only.
+static CUSTOM_RTS: RwLock<Vec<(usize, usize)>> = RwLock::new(Vec::new());
+
+pub(crate) type CustomRts = Box<dyn FnMut(bool) + Send>;
+
+/// Called by libmodbus instead of the ioctl on the RTS pin, forwards `on` to the closure registered for `ctx`
+unsafe extern "C" fn custom_rts_trampoline(ctx: *mut ffi::modbus_t, on: c_int) {
+    let set_rts = CUSTOM_RTS.read()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .iter()
+                            .find(|&&(key, _)| key == ctx as usize)
+                            .map(|&(_, set_rts)| set_rts as *mut CustomRts);
+
+    if let Some(set_rts) = set_rts {
+        // The context is busy with this call in the current thread, nobody else can call, replace or drop the closure.
+        // A panic must not unwind into libmodbus, it is reported by the panic hook and the transmission continues.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| (*set_rts)(on != 0)));
+    }
+}
+
+/// Point the callback of `ctx` to `set_rts`, or forget it if `set_rts` is null
+pub(crate) fn register_custom_rts(ctx: *mut ffi::modbus_t, set_rts: *mut CustomRts) {
+    let mut callbacks = CUSTOM_RTS.write().unwrap_or_else(PoisonError::into_inner);
+    callbacks.retain(|&(key, _)| key != ctx as usize);
+    if !set_rts.is_null() {
+        callbacks.push((ctx as usize, set_rts as usize));
+    }
+}
+
 /// The RTU backend (Remote Terminal Unit) is used in serial communication and makes use of a compact, binary
 /// representation of the data for protocol communication.
 /// The RTU format follows the commands/data with a cyclic redundancy check checksum as an error check mechanism to
@@ -54,12 +91,13 @@ pub enum RequestToSendMode {
 /// [`rtu_set_rts_delay()`](struct.Modbus.html#method.rtu_set_rts_delay)
 ///
 pub trait ModbusRTU {
-    fn new_rtu(device: &str, baud: i32, parity: char, data_bit: i32, stop_bit: i32) -> Result<Modbus, Error>;
+    fn new_rtu(device: &str, baud: i32, parity: char, data_bit: i32, stop_bit: i32) -> Result<Modbus, Error>
+        where Self: Sized;
     fn rtu_get_serial_mode(&self) -> Result<SerialMode, Error>;
     fn rtu_set_serial_mode(&mut self, mode: SerialMode) -> Result<(), Error>;
     fn rtu_get_rts(&self) -> Result<RequestToSendMode, Error>;
     fn rtu_set_rts(&mut self, mode: RequestToSendMode) -> Result<(), Error>;
-    fn rtu_set_custom_rts(&mut self, _mode: RequestToSendMode) -> Result<i32, Error>;
+    fn rtu_set_custom_rts(&mut self, set_rts: Box<dyn FnMut(bool) + Send>) -> Result<(), Error>;
     fn rtu_get_rts_delay(&self) -> Result<i32, Error>;
     fn rtu_set_rts_delay(&mut self, us: i32) -> Result<(), Error>;
 }
@@ -110,9 +148,9 @@ impl ModbusRTU for Modbus {
                                                     stop_bit as c_int);
 
             if ctx.is_null() {
-                bail!(::std::io::Error::last_os_error())
+                Err(Error::last_os_error())
             } else {
-                Ok(Modbus { ctx: ctx })
+                Ok(Modbus::from_raw(ctx))
             }
         }
     }
@@ -153,7 +191,7 @@ impl ModbusRTU for Modbus {
             match mode {
                 mode if mode == SerialMode::RtuRS232 as i32 => Ok(SerialMode::RtuRS232),
                 mode if mode == SerialMode::RtuRS485 as i32 => Ok(SerialMode::RtuRS485),
-                _ => bail!(::std::io::Error::last_os_error()),
+                _ => Err(Error::last_os_error()),
             }
         }
     }
@@ -194,7 +232,7 @@ impl ModbusRTU for Modbus {
         unsafe {
             let mode = ffi::modbus_rtu_set_serial_mode(self.ctx, mode as c_int) as i32;
             match mode {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -231,7 +269,7 @@ impl ModbusRTU for Modbus {
     fn rtu_set_rts(&mut self, mode: RequestToSendMode) -> Result<(), Error> {
         unsafe {
             match ffi::modbus_rtu_set_rts(self.ctx, mode as c_int) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
@@ -263,7 +301,7 @@ impl ModbusRTU for Modbus {
                 ffi::MODBUS_RTU_RTS_NONE => Ok(RequestToSendMode::RtuRtsNone),
                 ffi::MODBUS_RTU_RTS_UP => Ok(RequestToSendMode::RtuRtsUp),
                 ffi::MODBUS_RTU_RTS_DOWN => Ok(RequestToSendMode::RtuRtsDown),
-                _ => bail!(::std::io::Error::last_os_error()),
+                _ => Err(Error::last_os_error()),
             }
         }
     }
@@ -279,9 +317,43 @@ impl ModbusRTU for Modbus {
     ///
     /// This function can only be used with a context using a RTU backend.
     ///
-    /// TODO: implement rtu_set_custom_rts()!
-    fn rtu_set_custom_rts(&mut self, _mode: RequestToSendMode) -> Result<i32, Error> {
-        unimplemented!()
+    /// The closure is called with `true` if the line should be driven active and with `false` otherwise, it is owned
+    /// by the context from now on and dropped together with it. Setting a new closure replaces the previous one. A
+    /// panic in the closure is caught, it must not unwind through libmodbus.
+    ///
+    /// # Parameters
+    ///
+    /// * `set_rts` - closure toggling the transceiver direction, e.g. through a GPIO line
+    ///
+    /// # Return value
+    ///
+    /// The function returns an OK Result if successful. Otherwise it contains an Error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libmodbus_rs::{Modbus, ModbusRTU, RequestToSendMode};
+    /// let mut modbus = Modbus::new_rtu("/dev/ttyUSB0", 115200, 'N', 8, 1).unwrap();
+    ///
+    /// assert!(modbus.rtu_set_custom_rts(Box::new(|on| println!("set direction pin: {}", on))).is_ok());
+    /// assert!(modbus.rtu_set_rts(RequestToSendMode::RtuRtsUp).is_ok());
+    /// ```
+    fn rtu_set_custom_rts(&mut self, set_rts: Box<dyn FnMut(bool) + Send>) -> Result<(), Error> {
+        unsafe {
+            match ffi::modbus_rtu_set_custom_rts(self.ctx, Some(custom_rts_trampoline)) {
+                -1 => Err(Error::last_os_error()),
+                0 => {
+                    let set_rts = Box::into_raw(Box::new(set_rts));
+                    register_custom_rts(self.ctx, set_rts);
+                    let previous = mem::replace(&mut self.custom_rts, set_rts);
+                    if !previous.is_null() {
+                        drop(Box::from_raw(previous));
+                    }
+                    Ok(())
+                }
+                _ => panic!("libmodbus API incompatible response"),
+            }
+        }
     }
 
     /// `rtu_get_rts_delay` - get the current RTS delay in RTU
@@ -308,7 +380,7 @@ impl ModbusRTU for Modbus {
     fn rtu_get_rts_delay(&self) -> Result<i32, Error> {
         unsafe {
             match ffi::modbus_rtu_get_rts_delay(self.ctx) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 delay => Ok(delay),
             }
         }
@@ -337,7 +409,7 @@ impl ModbusRTU for Modbus {
     fn rtu_set_rts_delay(&mut self, us: i32) -> Result<(), Error> {
         unsafe {
             match ffi::modbus_rtu_set_rts_delay(self.ctx, us as c_int) {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 0 => Ok(()),
                 _ => panic!("libmodbus API incompatible response"),
             }
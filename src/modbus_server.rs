@@ -1,23 +1,30 @@
-use failure::Error;
+use crate::error::Error;
+use crate::modbus_handler::{self, RequestHandler};
+use crate::modbus_mapping::ModbusMapping;
+use crate::modbus::{Modbus, ModbusConnection};
 use libmodbus_sys as ffi;
-use modbus_mapping::ModbusMapping;
-use modbus::Modbus;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 
 /// The server is waiting for request from clients and must answer when it is concerned by the request. The libmodbus
 /// offers the following functions to handle requests:
 ///
 /// * Receive
-///     - [`receive()`](struct.Modbus.html#method.receive)
+///     - [`receive()`](struct.ModbusConnection.html#method.receive)
 /// * Reply
-///     - [`reply()`](struct.Modbus.html#method.reply), [`reply_exception()`](struct.Modbus.html#method.reply_exception)
+///     - [`reply()`](struct.ModbusConnection.html#method.reply), [`reply_exception()`](struct.ModbusConnection.html#method.reply_exception)
+///     - [`reply_with()`](struct.ModbusConnection.html#method.reply_with)
+/// * Stop a server loop
+///     - [`ShutdownHandle`](struct.ShutdownHandle.html)
 ///
 pub trait ModbusServer {
     fn receive(&self, request: &mut [u8]) -> Result<i32, Error>;
     fn reply(&self, request: &[u8], request_len: i32, modbus_mapping: &ModbusMapping) -> Result<i32, Error>;
+    fn reply_with(&self, request: &[u8], request_len: i32, handler: &mut dyn RequestHandler) -> Result<i32, Error>;
 }
 
-impl ModbusServer for Modbus {
+impl ModbusServer for ModbusConnection {
     /// `receive` - receive an indication request
     ///
     /// The [`receive()`](#method.receive) function shall receive an indication request from the socket of the context
@@ -33,6 +40,7 @@ impl ModbusServer for Modbus {
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusServer, ModbusTCP};
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = modbus.tcp_listen(1).unwrap().tcp_accept().unwrap();
     /// let mut query = vec![0; Modbus::MAX_ADU_LENGTH as usize];
     ///
     /// assert!(modbus.receive(&mut query).is_ok());
@@ -43,7 +51,7 @@ impl ModbusServer for Modbus {
         unsafe {
             let len = ffi::modbus_receive(self.ctx, request.as_mut_ptr());
             match len {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len),
             }
         }
@@ -57,6 +65,9 @@ impl ModbusServer for Modbus {
     /// according to the type of the manipulated data.
     /// If an error occurs, an exception response will be sent.
     ///
+    /// The `modbus_mapping` is locked (see [`ModbusMapping::lock()`](struct.ModbusMapping.html#method.lock)) for the
+    /// duration of the request.
+    ///
     /// This function is designed for Modbus server.
     ///
     /// # Examples
@@ -65,18 +76,127 @@ impl ModbusServer for Modbus {
     /// use libmodbus_rs::{Modbus, ModbusServer, ModbusTCP};
     ///
     /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = modbus.tcp_listen(1).unwrap().tcp_accept().unwrap();
     /// let mut query = vec![0; Modbus::MAX_ADU_LENGTH as usize];
     ///
     /// assert!(modbus.receive(&mut query).is_ok());
     /// ```
     fn reply(&self, request: &[u8], request_len: i32, modbus_mapping: &ModbusMapping) -> Result<i32, Error> {
+        // no other thread may touch the tables while libmodbus reads and writes them
+        let mapping = modbus_mapping.lock();
         unsafe {
-            let len =
-                ffi::modbus_reply(self.ctx, request.as_ptr(), request_len, modbus_mapping.modbus_mapping);
+            let len = ffi::modbus_reply(self.ctx, request.as_ptr(), request_len, mapping.as_raw());
             match len {
-                -1 => bail!(::std::io::Error::last_os_error()),
+                -1 => Err(Error::last_os_error()),
                 len => Ok(len),
             }
         }
     }
+
+    /// `reply_with` - answer the received request with the values of a handler
+    ///
+    /// The [`reply_with()`](#method.reply_with) function shall decode the request, call the matching method of the
+    /// [`RequestHandler`](trait.RequestHandler.html) and send the response, or the exception returned by the handler.
+    /// Requests with an unknown function code are answered with `Exception::IllegalFunction`, malformed requests
+    /// with `Exception::IllegalDataValue`. Broadcast requests (RTU) are passed to the handler, but not answered.
+    ///
+    /// This function is designed for Modbus server.
+    ///
+    /// # Return value
+    ///
+    /// The function returns a Result containing the number of bytes sent if successful. Otherwise it contains an
+    /// Error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Exception, Modbus, ModbusServer, ModbusTCP, RequestHandler};
+    ///
+    /// struct Counter(u16);
+    ///
+    /// impl RequestHandler for Counter {
+    ///     fn read_holding_registers(&mut self, _address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+    ///         self.0 += 1;
+    ///         Ok(vec![self.0; count as usize])
+    ///     }
+    /// }
+    ///
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = modbus.tcp_listen(1).unwrap().tcp_accept().unwrap();
+    /// let mut query = vec![0; Modbus::MAX_ADU_LENGTH];
+    /// let mut counter = Counter(0);
+    ///
+    /// while let Ok(len) = modbus.receive(&mut query) {
+    ///     if len > 0 {
+    ///         modbus.reply_with(&query, len, &mut counter).unwrap();
+    ///     }
+    /// }
+    /// ```
+    fn reply_with(&self, request: &[u8], request_len: i32, handler: &mut dyn RequestHandler) -> Result<i32, Error> {
+        modbus_handler::reply_with(self, request, request_len, handler)
+    }
+}
+
+/// Stops a server loop from another thread or from a signal handler
+///
+/// All clones of a handle share the same state, one of them is kept by the server loop while the others are handed
+/// to the code that decides to stop it. [`shutdown()`](#method.shutdown) only stores an atomic flag, so it is safe to
+/// call it from a signal handler.
+///
+/// A server loop checks the handle every time [`receive()`](struct.ModbusConnection.html#method.receive) returns,
+/// so the loop must not wait forever for a request. The indication timeout
+/// ([`set_indication_timeout()`](struct.Modbus.html#method.set_indication_timeout)) limits the time a loop needs to
+/// notice the shutdown. [`TcpServer`](struct.TcpServer.html) provides a handle with
+/// [`shutdown_handle()`](struct.TcpServer.html#method.shutdown_handle).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{Error, Modbus, ModbusMapping, ModbusServer, ModbusTCP, ShutdownHandle};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let handle = ShutdownHandle::new();
+/// let server_handle = handle.clone();
+///
+/// let server = thread::spawn(move || {
+///     let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+///     modbus.set_indication_timeout_duration(Duration::from_millis(100)).unwrap();
+///     let modbus = modbus.tcp_listen(1).unwrap().tcp_accept().unwrap();
+///     let mapping = ModbusMapping::new(500, 500, 500, 500).unwrap();
+///     let mut query = vec![0; Modbus::MAX_ADU_LENGTH];
+///
+///     while !server_handle.is_shutdown() {
+///         match modbus.receive(&mut query) {
+///             Ok(len) => { modbus.reply(&query, len, &mapping).unwrap(); },
+///             Err(Error::Timeout) => {},
+///             Err(_) => break,
+///         }
+///     }
+///     modbus.close()
+/// });
+///
+/// handle.shutdown();
+/// server.join().unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl ShutdownHandle {
+    /// Create a new handle, not yet triggered
+    pub fn new() -> ShutdownHandle {
+        ShutdownHandle::default()
+    }
+
+    /// Request the server loops using this handle to stop
+    pub fn shutdown(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// `true` once [`shutdown()`](#method.shutdown) was called on any clone of the handle
+    pub fn is_shutdown(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
 }
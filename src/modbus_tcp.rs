@@ -1,6 +1,6 @@
-use failure::Error;
+use crate::error::{Error, IntoInnerError};
+use crate::modbus::{Modbus, ModbusConnection, ModbusListener};
 use libmodbus_sys as ffi;
-use modbus::Modbus;
 use std::ffi::CString;
 
 
@@ -12,8 +12,7 @@ use std::ffi::CString;
 ///
 pub trait ModbusTCP {
     fn new_tcp(ip: &str, port: i32) -> Result<Modbus, Error>;
-    fn tcp_accept(&mut self, socket: &mut i32) -> Result<i32, Error>;
-    fn tcp_listen(&mut self, num_connection: i32) -> Result<i32, Error>;
+    fn tcp_listen(self, num_connection: i32) -> Result<ModbusListener, Error>;
 }
 
 impl ModbusTCP for Modbus {
@@ -36,7 +35,7 @@ impl ModbusTCP for Modbus {
     /// let modbus = Modbus::new_tcp("127.0.0.1", Modbus::TCP_DEFAULT_PORT as i32).unwrap();
     ///
     /// match modbus.connect() {
-    ///     Ok(_) => {  }
+    ///     Ok(_connection) => {  }
     ///     Err(e) => println!("Error: {}", e),
     /// }
     /// ```
@@ -46,67 +45,66 @@ impl ModbusTCP for Modbus {
             let ctx = ffi::modbus_new_tcp(ip.as_ptr(), port);
 
             if ctx.is_null() {
-                bail!(::std::io::Error::last_os_error())
+                Err(Error::last_os_error())
             } else {
-                Ok(Modbus { ctx: ctx })
+                Ok(Modbus::from_raw(ctx))
             }
         }
     }
 
-    /// `tcp_accept` - accept a new connection on a TCP Modbus socket (IPv4)
+    /// `tcp_listen` - create and listen a TCP Modbus socket (IPv4)
     ///
-    /// The [`tcp_accept()`](#method.tcp_accept) function shall extract the first connection on the
-    /// queue of pending connections and create a new socket given as argument.
+    /// The [`tcp_listen()`](#method.tcp_listen) function shall create a socket and listen to maximum
+    /// `num_connection` incoming connections on the specified IP address.
+    /// If IP address is set to NULL or '0.0.0.0', any addresses will be listen.
     ///
     /// # Parameters
     ///
-    /// * `socket`  - Socket
+    /// * `num_connection`  - maximum number of incoming connections on the specified IP address
+    ///
+    /// # Return value
+    ///
+    /// The function consumes the context and returns a Result containing the
+    /// [`ModbusListener`](struct.ModbusListener.html) if successful. Otherwise it contains an Error.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusTCP};
     ///
-    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
-    /// let mut socket = modbus.tcp_listen(1).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     ///
-    /// modbus.tcp_accept(&mut socket);
+    /// let listener = modbus.tcp_listen(1);
     /// ```
-    fn tcp_accept(&mut self, socket: &mut i32) -> Result<i32, Error> {
-        unsafe {
-            match ffi::modbus_tcp_accept(self.ctx, socket) {
-                -1 => bail!(::std::io::Error::last_os_error()),
-                socket => Ok(socket),
-            }
-        }
+    fn tcp_listen(self, num_connection: i32) -> Result<ModbusListener, Error> {
+        ModbusListener::listen(self, num_connection, ffi::modbus_tcp_listen)
     }
+}
 
-
-    /// `tcp_listen` - create and listen a TCP Modbus socket (IPv4)
+impl ModbusListener {
+    /// `tcp_accept` - accept a new connection on a TCP Modbus socket (IPv4)
     ///
-    /// The [`tcp_listen()`](#method.tcp_listen) function shall create a socket and listen to maximum
-    /// `num_connection` incoming connections on the specified IP address.
-    /// If IP address is set to NULL or '0.0.0.0', any addresses will be listen.
+    /// The [`tcp_accept()`](#method.tcp_accept) function shall extract the first connection on the
+    /// queue of pending connections of the listening socket.
     ///
-    /// # Parameters
+    /// # Return value
     ///
-    /// * `num_connection`  - maximum number of incoming connections on the specified IP address
+    /// The function consumes the listener and returns a Result containing the
+    /// [`ModbusConnection`](struct.ModbusConnection.html) to the client if successful. Otherwise it contains an
+    /// [`IntoInnerError`](struct.IntoInnerError.html) holding the Error and the listener, e.g. to accept again after
+    /// an interrupted call.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusTCP};
     ///
-    /// let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    /// let listener = modbus.tcp_listen(1).unwrap();
     ///
-    /// let socket = modbus.tcp_listen(1);
+    /// let connection = listener.tcp_accept().unwrap();
     /// ```
-    fn tcp_listen(&mut self, num_connection: i32) -> Result<i32, Error> {
-        unsafe {
-            match ffi::modbus_tcp_listen(self.ctx, num_connection) {
-                -1 => bail!(::std::io::Error::last_os_error()),
-                socket => Ok(socket),
-            }
-        }
+    pub fn tcp_accept(self) -> Result<ModbusConnection, IntoInnerError<ModbusListener>> {
+        self.accept(ffi::modbus_tcp_accept)
     }
 }
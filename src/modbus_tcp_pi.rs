@@ -1,6 +1,6 @@
-use failure::Error;
+use crate::error::{Error, IntoInnerError};
+use crate::modbus::{Modbus, ModbusConnection, ModbusListener};
 use libmodbus_sys as ffi;
-use modbus::Modbus;
 use std::ffi::CString;
 
 
@@ -16,8 +16,7 @@ use std::ffi::CString;
 ///
 pub trait ModbusTCPPI {
     fn new_tcp_pi(node: &str, service: &str) -> Result<Modbus, Error>;
-    fn tcp_pi_accept(&mut self, socket: &mut i32) -> Result<i32, Error>;
-    fn tcp_pi_listen(&mut self, num_connection: i32) -> Result<i32, Error>;
+    fn tcp_pi_listen(self, num_connection: i32) -> Result<ModbusListener, Error>;
 }
 
 impl ModbusTCPPI for Modbus {
@@ -43,7 +42,7 @@ impl ModbusTCPPI for Modbus {
     /// let modbus = Modbus::new_tcp_pi("::1", "1502").unwrap();
     ///
     /// match modbus.connect() {
-    ///     Ok(_) => {}
+    ///     Ok(_connection) => {}
     ///     Err(e) => println!("Error: {}", e),
     /// }
     /// ```
@@ -54,36 +53,9 @@ impl ModbusTCPPI for Modbus {
             let ctx = ffi::modbus_new_tcp_pi(node.as_ptr(), service.as_ptr());
 
             if ctx.is_null() {
-                bail!(::std::io::Error::last_os_error())
+                Err(Error::last_os_error())
             } else {
-                Ok(Modbus { ctx: ctx })
-            }
-        }
-    }
-
-    /// `tcp_pi_accept` - accept a new connection on a TCP PI Modbus socket (IPv6)
-    ///
-    /// The [`tcp_pi_accept()`](#method.tcp_pi_accept) function shall extract the first connection on the
-    /// queue of pending connections and create a new socket given as argument.
-    ///
-    /// # Parameters
-    ///
-    /// * `socket`  - Socket
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use libmodbus_rs::{Modbus, ModbusMapping, ModbusServer, ModbusTCPPI};
-    ///
-    /// let mut modbus = Modbus::new_tcp_pi("::0", "1502").unwrap();
-    /// let mut socket = modbus.tcp_pi_listen(1).unwrap();
-    /// modbus.tcp_pi_accept(&mut socket).unwrap();
-    /// ```
-    fn tcp_pi_accept(&mut self, socket: &mut i32) -> Result<i32, Error> {
-        unsafe {
-            match ffi::modbus_tcp_pi_accept(self.ctx, socket) {
-                -1 => bail!(::std::io::Error::last_os_error()),
-                socket => Ok(socket),
+                Ok(Modbus::from_raw(ctx))
             }
         }
     }
@@ -99,6 +71,11 @@ impl ModbusTCPPI for Modbus {
     ///
     /// If node is set to `""` or `0.0.0.0`, any addresses will be listen.
     ///
+    /// # Return value
+    ///
+    /// The function consumes the context and returns a Result containing the
+    /// [`ModbusListener`](struct.ModbusListener.html) if successful. Otherwise it contains an Error.
+    ///
     /// # Examples
     ///
     /// For detailed examples, look at the examples directory of this crate.
@@ -109,10 +86,10 @@ impl ModbusTCPPI for Modbus {
     /// ```rust,no_run
     /// use libmodbus_rs::{Modbus, ModbusMapping, ModbusServer, ModbusTCPPI};
     ///
-    /// let mut modbus = Modbus::new_tcp_pi("::0", "1502").unwrap();
-    /// let mut socket = modbus.tcp_pi_listen(1).unwrap();
+    /// let modbus = Modbus::new_tcp_pi("::0", "1502").unwrap();
+    /// let listener = modbus.tcp_pi_listen(1).unwrap();
     ///
-    /// modbus.tcp_pi_accept(&mut socket);
+    /// let modbus = listener.tcp_pi_accept().unwrap();
     ///
     /// let modbus_mapping = ModbusMapping::new(500, 500, 500, 500).unwrap();
     /// let mut query = vec![0u8; Modbus::MAX_ADU_LENGTH as usize];
@@ -122,12 +99,35 @@ impl ModbusTCPPI for Modbus {
     ///     modbus.reply(&query, request_len, &modbus_mapping);
     /// }
     /// ```
-    fn tcp_pi_listen(&mut self, num_connection: i32) -> Result<i32, Error> {
-        unsafe {
-            match ffi::modbus_tcp_pi_listen(self.ctx, num_connection) {
-                -1 => bail!(::std::io::Error::last_os_error()),
-                socket => Ok(socket),
-            }
-        }
+    fn tcp_pi_listen(self, num_connection: i32) -> Result<ModbusListener, Error> {
+        ModbusListener::listen(self, num_connection, ffi::modbus_tcp_pi_listen)
+    }
+}
+
+impl ModbusListener {
+    /// `tcp_pi_accept` - accept a new connection on a TCP PI Modbus socket (IPv6)
+    ///
+    /// The [`tcp_pi_accept()`](#method.tcp_pi_accept) function shall extract the first connection on the
+    /// queue of pending connections of the listening socket.
+    ///
+    /// # Return value
+    ///
+    /// The function consumes the listener and returns a Result containing the
+    /// [`ModbusConnection`](struct.ModbusConnection.html) to the client if successful. Otherwise it contains an
+    /// [`IntoInnerError`](struct.IntoInnerError.html) holding the Error and the listener, e.g. to accept again after
+    /// an interrupted call.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusTCPPI};
+    ///
+    /// let modbus = Modbus::new_tcp_pi("::0", "1502").unwrap();
+    /// let listener = modbus.tcp_pi_listen(1).unwrap();
+    ///
+    /// let connection = listener.tcp_pi_accept().unwrap();
+    /// ```
+    pub fn tcp_pi_accept(self) -> Result<ModbusConnection, IntoInnerError<ModbusListener>> {
+        self.accept(ffi::modbus_tcp_pi_accept)
     }
 }
@@ -0,0 +1,256 @@
+use crate::error::Error;
+use crate::modbus::{Modbus, ModbusConnection, ModbusListener};
+use crate::modbus_handler::RequestHandler;
+use crate::modbus_mapping::ModbusMapping;
+use crate::modbus_server::{ModbusServer, ShutdownHandle};
+use libc::{self, c_int};
+use std::io;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::time::Duration;
+
+
+/// Indication timeout set by the server if none is configured, the interval the shutdown handle is checked at
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+
+/// Serves many TCP clients at once
+///
+/// A libmodbus context only holds one socket, [`tcp_accept()`](struct.ModbusListener.html#method.tcp_accept) replaces
+/// it with the accepted connection. The `TcpServer` keeps the sockets of all connected clients, waits with `poll()`
+/// until one of them sent a request and switches the socket of the context to it before the request is received and
+/// answered. Like this all clients share one context and one mapping or handler.
+///
+/// The server is created from a [`ModbusListener`](struct.ModbusListener.html) of the TCP
+/// ([`tcp_listen()`](struct.Modbus.html#method.tcp_listen)) or the TCP PI
+/// ([`tcp_pi_listen()`](struct.Modbus.html#method.tcp_pi_listen)) backend. The `num_connection` of the listener
+/// limits the pending, not the served connections.
+///
+/// The server loop runs until it is stopped with the [`ShutdownHandle`](struct.ShutdownHandle.html) of
+/// [`shutdown_handle()`](#method.shutdown_handle), the handle is checked every time the indication timeout expires.
+/// If the context has no indication timeout, the server sets it to 100 ms.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use libmodbus_rs::{Modbus, ModbusMapping, ModbusTCP, TcpServer};
+///
+/// let listener = Modbus::new_tcp("0.0.0.0", 1502).unwrap().tcp_listen(5).unwrap();
+/// let mapping = ModbusMapping::new(500, 500, 500, 500).unwrap();
+///
+/// let mut server = TcpServer::new(listener);
+/// server.serve(&mapping).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TcpServer {
+    // the context, its socket is switched to the client being served, closes the listening socket on drop
+    connection: ModbusConnection,
+    listener: c_int,
+    clients: Vec<c_int>,
+    shutdown: ShutdownHandle,
+}
+
+impl TcpServer {
+    /// Create a server accepting connections from `listener`
+    pub fn new(listener: ModbusListener) -> TcpServer {
+        let socket = listener.socket();
+        let mut connection = listener.into_connection();
+        // no client yet, the listening socket must not be closed as client socket
+        let _ = connection.set_socket(-1);
+        if connection.get_indication_timeout_duration().ok() == Some(Duration::from_secs(0)) {
+            let _ = connection.set_indication_timeout_duration(SHUTDOWN_CHECK_INTERVAL);
+        }
+
+        TcpServer {
+            connection,
+            listener: socket,
+            clients: Vec::new(),
+            shutdown: ShutdownHandle::new(),
+        }
+    }
+
+    /// `shutdown_handle` - get a handle to stop the server loop
+    ///
+    /// Once the handle is triggered [`serve()`](#method.serve) or [`serve_with()`](#method.serve_with) disconnects
+    /// all clients, stops listening and returns `Ok(())`, at the latest after the indication timeout. New connections
+    /// are refused from then on, the context is taken back with [`close()`](#method.close).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Modbus, ModbusMapping, ModbusTCP, TcpServer};
+    /// use std::thread;
+    ///
+    /// let listener = Modbus::new_tcp("0.0.0.0", 1502).unwrap().tcp_listen(5).unwrap();
+    /// let mut server = TcpServer::new(listener);
+    /// let handle = server.shutdown_handle();
+    ///
+    /// let server_thread = thread::spawn(move || {
+    ///     let mapping = ModbusMapping::new(500, 500, 500, 500).unwrap();
+    ///     server.serve(&mapping).unwrap();
+    ///     server.close()
+    /// });
+    ///
+    /// handle.shutdown();
+    /// let modbus = server_thread.join().unwrap();
+    /// ```
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// `close` - disconnect all clients, stop listening and return the context
+    pub fn close(self) -> Modbus {
+        let mut server = ManuallyDrop::new(self);
+        server.disconnect_all();
+        unsafe {
+            // moved out of the server, the remaining fields are dropped here
+            let _clients = ptr::read(&server.clients);
+            let _shutdown = ptr::read(&server.shutdown);
+            // the connection closes the listening socket
+            ptr::read(&server.connection).close()
+        }
+    }
+
+    /// Number of connected clients
+    pub fn connections(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// `serve` - answer the requests of all clients with the data of a mapping
+    ///
+    /// New connections are accepted and requests answered with [`reply()`](struct.ModbusConnection.html#method.reply)
+    /// until the server is shut down or an error occurs on the listening socket. A client is disconnected if receiving
+    /// its request or sending the response fails.
+    ///
+    /// # Return value
+    ///
+    /// The function returns `Ok(())` after a shutdown, see [`shutdown_handle()`](#method.shutdown_handle). Otherwise
+    /// it returns the Error of the listening socket.
+    pub fn serve(&mut self, modbus_mapping: &ModbusMapping) -> Result<(), Error> {
+        self.run(|connection, request, request_len| connection.reply(request, request_len, modbus_mapping))
+    }
+
+    /// `serve_with` - answer the requests of all clients with the values of a handler
+    ///
+    /// Same as [`serve()`](#method.serve), but the requests are answered with
+    /// [`reply_with()`](struct.ModbusConnection.html#method.reply_with). The requests of all clients are passed to the
+    /// same handler, one after the other.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use libmodbus_rs::{Exception, Modbus, ModbusTCP, RequestHandler, TcpServer};
+    ///
+    /// struct Zeros;
+    ///
+    /// impl RequestHandler for Zeros {
+    ///     fn read_holding_registers(&mut self, _address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+    ///         Ok(vec![0; count as usize])
+    ///     }
+    /// }
+    ///
+    /// let listener = Modbus::new_tcp("0.0.0.0", 1502).unwrap().tcp_listen(5).unwrap();
+    /// TcpServer::new(listener).serve_with(&mut Zeros).unwrap();
+    /// ```
+    pub fn serve_with(&mut self, handler: &mut dyn RequestHandler) -> Result<(), Error> {
+        self.run(|connection, request, request_len| connection.reply_with(request, request_len, handler))
+    }
+
+    fn run<F>(&mut self, mut reply: F) -> Result<(), Error>
+        where F: FnMut(&ModbusConnection, &[u8], i32) -> Result<i32, Error>
+    {
+        let mut query = vec![0u8; Modbus::TCP_MAX_ADU_LENGTH];
+        let interval = self.connection
+            .get_indication_timeout_duration()
+            .ok()
+            .filter(|interval| *interval > Duration::from_secs(0))
+            .unwrap_or(SHUTDOWN_CHECK_INTERVAL);
+        let timeout = interval.as_millis().clamp(1, c_int::MAX as u128) as c_int;
+
+        loop {
+            if self.shutdown.is_shutdown() {
+                self.disconnect_all();
+                self.stop_listening();
+                return Ok(());
+            }
+
+            let mut fds: Vec<libc::pollfd> = Some(self.listener)
+                .into_iter()
+                .chain(self.clients.iter().cloned())
+                .map(|fd| libc::pollfd { fd, events: libc::POLLIN, revents: 0 })
+                .collect();
+
+            if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) } == -1 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err.into());
+            }
+
+            if fds[0].revents & (libc::POLLERR | libc::POLLNVAL) != 0 {
+                return Err(Error::IoError(io::Error::other("listening socket failed")));
+            }
+            for client in fds[1..].iter().filter(|fd| fd.revents != 0) {
+                self.serve_client(client.fd, &mut query, &mut reply);
+            }
+            if fds[0].revents & libc::POLLIN != 0 {
+                self.accept();
+            }
+        }
+    }
+
+    /// Accepts a pending connection, a failed accept is not fatal, the client can retry
+    fn accept(&mut self) {
+        let socket = unsafe { libc::accept(self.listener, ptr::null_mut(), ptr::null_mut()) };
+        if socket != -1 {
+            self.clients.push(socket);
+        }
+    }
+
+    /// Receives and answers one request of a client, the client is disconnected on errors
+    fn serve_client<F>(&mut self, socket: c_int, query: &mut [u8], reply: &mut F)
+        where F: FnMut(&ModbusConnection, &[u8], i32) -> Result<i32, Error>
+    {
+        let _ = self.connection.set_socket(socket);
+        let served = match self.connection.receive(query) {
+            // filtered request
+            Ok(0) => true,
+            Ok(len) => reply(&self.connection, query, len).is_ok(),
+            Err(_) => false,
+        };
+
+        if !served {
+            // depending on the error recovery mode libmodbus already closed the socket
+            if self.connection.get_socket().ok() == Some(socket) {
+                unsafe { libc::close(socket) };
+            }
+            self.clients.retain(|&client| client != socket);
+        }
+        let _ = self.connection.set_socket(-1);
+    }
+
+    /// Closes the listening socket, `poll()` ignores the negative descriptor left behind
+    fn stop_listening(&mut self) {
+        self.connection.close_listener();
+        self.listener = -1;
+    }
+
+    /// Closes the sockets of all clients
+    fn disconnect_all(&mut self) {
+        for client in self.clients.drain(..) {
+            unsafe {
+                libc::shutdown(client, libc::SHUT_RDWR);
+                libc::close(client);
+            }
+        }
+        let _ = self.connection.set_socket(-1);
+    }
+}
+
+impl Drop for TcpServer {
+    fn drop(&mut self) {
+        // the connection closes the listening socket
+        self.disconnect_all();
+    }
+}
@@ -0,0 +1,425 @@
+use crate::error::Error;
+
+
+/// Order of the two bytes within a register
+///
+/// Modbus sends registers big endian, `LittleEndian` is for devices swapping the bytes of every register. With the
+/// `serde` feature the order is deserialized from `"big_endian"` or `"little_endian"`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum ByteOrder {
+    /// Most significant byte first, the Modbus standard
+    #[default]
+    BigEndian,
+    /// Least significant byte first
+    LittleEndian,
+}
+
+/// Order of the registers of a value spanning several registers
+///
+/// The Modbus standard does not define how larger values are spread over registers, every vendor does it differently.
+/// With the `serde` feature the order is deserialized from `"big_endian"` or `"little_endian"`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum WordOrder {
+    /// Most significant register at the lowest address
+    #[default]
+    BigEndian,
+    /// Least significant register at the lowest address
+    LittleEndian,
+}
+
+/// A value stored in one or more consecutive registers
+///
+/// Implemented for `u16`, `i16`, `u32`, `i32`, `u64`, `i64`, `f32` and `f64`. The `get_float_*` functions of the
+/// [`prelude`](prelude/index.html) name the orders differently, with the bundled libmodbus 3.1.4 they correspond to:
+///
+/// | libmodbus | `ByteOrder`    | `WordOrder`    |
+/// |-----------|----------------|----------------|
+/// | `abcd`    | `LittleEndian` | `LittleEndian` |
+/// | `badc`    | `BigEndian`    | `LittleEndian` |
+/// | `cdab`    | `LittleEndian` | `BigEndian`    |
+/// | `dcba`    | `BigEndian`    | `BigEndian`    |
+///
+/// The letters name the bytes in the order they are stored in, `a` is the least significant byte. The plain big endian
+/// order of Modbus is `dcba`.
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{ByteOrder, RegisterValue, WordOrder};
+///
+/// let registers = [0x0001, 0x0002, 0x0003, 0x0004];
+/// let value = u64::from_registers(&registers, ByteOrder::BigEndian, WordOrder::LittleEndian).unwrap();
+/// assert_eq!(value, 0x0004_0003_0002_0001);
+///
+/// let mut dest = [0u16; 2];
+/// 123456.0f32.to_registers(&mut dest, ByteOrder::BigEndian, WordOrder::BigEndian).unwrap();
+/// assert_eq!(dest, [0x47F1, 0x2000]);
+/// ```
+pub trait RegisterValue: Sized {
+    /// Number of registers the value occupies
+    const REGISTERS: usize;
+
+    /// `from_registers` - decode the value from the first [`REGISTERS`](#associatedconstant.REGISTERS) registers of
+    /// `src`
+    ///
+    /// Fails with `Error::BufferTooSmall` if `src` is too short.
+    fn from_registers(src: &[u16], byte_order: ByteOrder, word_order: WordOrder) -> Result<Self, Error>;
+
+    /// `to_registers` - encode the value into the first [`REGISTERS`](#associatedconstant.REGISTERS) registers of
+    /// `dest`
+    ///
+    /// Fails with `Error::BufferTooSmall` if `dest` is too short, `dest` is not modified then.
+    fn to_registers(&self, dest: &mut [u16], byte_order: ByteOrder, word_order: WordOrder) -> Result<(), Error>;
+}
+
+macro_rules! register_value {
+    ($($t:ty => $registers:expr),*) => {$(
+        impl RegisterValue for $t {
+            const REGISTERS: usize = $registers;
+
+            fn from_registers(src: &[u16], byte_order: ByteOrder, word_order: WordOrder) -> Result<Self, Error> {
+                let src = registers(src, $registers)?;
+                let mut bytes = [0u8; $registers * 2];
+                for (index, &register) in src.iter().enumerate() {
+                    let word = word_index(index, $registers, word_order);
+                    bytes[word * 2..word * 2 + 2].copy_from_slice(&register_bytes(register, byte_order));
+                }
+                Ok(<$t>::from_be_bytes(bytes))
+            }
+
+            fn to_registers(&self, dest: &mut [u16], byte_order: ByteOrder, word_order: WordOrder)
+                            -> Result<(), Error> {
+                let dest = registers_mut(dest, $registers)?;
+                let bytes = self.to_be_bytes();
+                for (index, register) in dest.iter_mut().enumerate() {
+                    let word = word_index(index, $registers, word_order);
+                    *register = register_value([bytes[word * 2], bytes[word * 2 + 1]], byte_order);
+                }
+                Ok(())
+            }
+        }
+    )*}
+}
+
+register_value!(u16 => 1, i16 => 1, u32 => 2, i32 => 2, u64 => 4, i64 => 4, f32 => 2, f64 => 4);
+
+/// Index of the big endian word stored in register `index`
+fn word_index(index: usize, registers: usize, word_order: WordOrder) -> usize {
+    match word_order {
+        WordOrder::BigEndian => index,
+        WordOrder::LittleEndian => registers - 1 - index,
+    }
+}
+
+/// The bytes of a register, most significant first
+fn register_bytes(register: u16, byte_order: ByteOrder) -> [u8; 2] {
+    match byte_order {
+        ByteOrder::BigEndian => register.to_be_bytes(),
+        ByteOrder::LittleEndian => register.to_le_bytes(),
+    }
+}
+
+/// The register holding `bytes`, most significant first
+fn register_value(bytes: [u8; 2], byte_order: ByteOrder) -> u16 {
+    match byte_order {
+        ByteOrder::BigEndian => u16::from_be_bytes(bytes),
+        ByteOrder::LittleEndian => u16::from_le_bytes(bytes),
+    }
+}
+
+fn registers(src: &[u16], required: usize) -> Result<&[u16], Error> {
+    src.get(..required).ok_or(Error::BufferTooSmall { required, available: src.len() })
+}
+
+fn registers_mut(dest: &mut [u16], required: usize) -> Result<&mut [u16], Error> {
+    let available = dest.len();
+    dest.get_mut(..required).ok_or(Error::BufferTooSmall { required, available })
+}
+
+/// `get_value` - get a value from registers in the given byte and word order
+///
+/// Works on any register slice, the buffer of
+/// [`read_registers()`](../struct.ModbusConnection.html#method.read_registers) as well as the registers of a
+/// [`ModbusMapping`](../struct.ModbusMapping.html). See [`RegisterValue`](../trait.RegisterValue.html) for the
+/// supported types.
+///
+/// # Return value
+///
+/// The value decoded from the first registers of `src`, or `Error::BufferTooSmall` if `src` is too short.
+///
+/// # Parameters
+///
+/// * `src`         - slice of at least `T::REGISTERS` values
+/// * `byte_order`  - order of the bytes within each register
+/// * `word_order`  - order of the registers
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{ByteOrder, WordOrder};
+/// use libmodbus_rs::prelude::*;
+///
+/// assert_eq!(get_value::<f32>(&[0x47F1, 0x2000], ByteOrder::BigEndian, WordOrder::BigEndian).unwrap(), 123456.0);
+/// assert_eq!(get_value::<f32>(&[0x0020, 0xF147], ByteOrder::LittleEndian, WordOrder::LittleEndian).unwrap(),
+///            get_float_abcd(&[0x0020, 0xF147]));
+/// assert_eq!(get_value::<i32>(&[0xFFFE, 0xFFFF], ByteOrder::BigEndian, WordOrder::LittleEndian).unwrap(), -2);
+/// ```
+pub fn get_value<T: RegisterValue>(src: &[u16], byte_order: ByteOrder, word_order: WordOrder) -> Result<T, Error> {
+    T::from_registers(src, byte_order, word_order)
+}
+
+/// `set_value` - set a value in registers using the given byte and word order
+///
+/// The counterpart of [`get_value()`](fn.get_value.html).
+///
+/// # Parameters
+///
+/// * `src`         - the value
+/// * `dest`        - slice of at least `T::REGISTERS` values, the value is written to its first registers
+/// * `byte_order`  - order of the bytes within each register
+/// * `word_order`  - order of the registers
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::{ByteOrder, ModbusMapping, WordOrder};
+/// use libmodbus_rs::prelude::*;
+///
+/// let modbus_mapping = ModbusMapping::new(0, 0, 6, 0).unwrap();
+/// let mut mapping = modbus_mapping.lock();
+///
+/// set_value(1_234_567_890_123u64, &mut mapping.get_registers_mut()[2..], ByteOrder::BigEndian,
+///           WordOrder::BigEndian).unwrap();
+/// assert_eq!(mapping.get_registers(), [0, 0, 0x0000, 0x011F, 0x71FB, 0x04CB]);
+/// ```
+pub fn set_value<T: RegisterValue>(src: T, dest: &mut [u16], byte_order: ByteOrder, word_order: WordOrder)
+                                   -> Result<(), Error> {
+    src.to_registers(dest, byte_order, word_order)
+}
+
+/// `get_string` - get a string packed into registers, two bytes per register
+///
+/// Trailing NUL bytes and spaces, the padding of fixed length strings, are removed.
+///
+/// # Return value
+///
+/// The string, or `Error::BadData` if the bytes are not valid UTF-8. ASCII is a subset of UTF-8.
+///
+/// # Parameters
+///
+/// * `src`         - the registers holding the string
+/// * `byte_order`  - order of the two characters within each register, `BigEndian` for the first one in the high byte
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::ByteOrder;
+/// use libmodbus_rs::prelude::*;
+///
+/// let registers = [0x5341, 0x3132, 0x3300, 0x0000];
+/// assert_eq!(get_string(&registers, ByteOrder::BigEndian).unwrap(), "SA123");
+/// assert_eq!(get_string(&registers[..2], ByteOrder::LittleEndian).unwrap(), "AS21");
+/// ```
+pub fn get_string(src: &[u16], byte_order: ByteOrder) -> Result<String, Error> {
+    let mut bytes: Vec<u8> = src.iter().flat_map(|&register| register_bytes(register, byte_order)).collect();
+    while let Some(&b'\0') | Some(&b' ') = bytes.last() {
+        bytes.pop();
+    }
+
+    String::from_utf8(bytes).map_err(|_| Error::BadData)
+}
+
+/// `set_string` - set a string in registers, two bytes per register
+///
+/// All registers of `dest` are written, the bytes following the string are set to `padding`, usually `b'\0'` or
+/// `b' '`.
+///
+/// # Return value
+///
+/// `Error::TooManyData` if the string is longer than the `2 * dest.len()` bytes of `dest`, `dest` is not modified
+/// then.
+///
+/// # Parameters
+///
+/// * `src`         - the string
+/// * `dest`        - the registers to hold the string
+/// * `byte_order`  - order of the two characters within each register
+/// * `padding`     - byte filling the registers after the string
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::ByteOrder;
+/// use libmodbus_rs::prelude::*;
+///
+/// let mut dest = [0u16; 3];
+/// set_string("SA1", &mut dest, ByteOrder::BigEndian, b' ').unwrap();
+/// assert_eq!(dest, [0x5341, 0x3120, 0x2020]);
+/// ```
+pub fn set_string(src: &str, dest: &mut [u16], byte_order: ByteOrder, padding: u8) -> Result<(), Error> {
+    let bytes = src.as_bytes();
+    if bytes.len() > dest.len() * 2 {
+        return Err(Error::TooManyData);
+    }
+
+    let mut bytes = bytes.iter().cloned().chain(std::iter::repeat(padding));
+    for register in dest.iter_mut() {
+        let high = bytes.next().unwrap_or(padding);
+        let low = bytes.next().unwrap_or(padding);
+        *register = register_value([high, low], byte_order);
+    }
+    Ok(())
+}
+
+/// `get_bcd` - get a number stored as packed BCD, four decimal digits per register
+///
+/// All registers of `src` are decoded, at most 4 registers or 16 digits.
+///
+/// # Return value
+///
+/// The number, `Error::BadData` if a nibble is not a decimal digit or `Error::TooManyData` if `src` holds more than
+/// 4 registers.
+///
+/// # Parameters
+///
+/// * `src`         - the registers holding the number
+/// * `word_order`  - order of the registers, `BigEndian` for the most significant digits first
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::WordOrder;
+/// use libmodbus_rs::prelude::*;
+///
+/// assert_eq!(get_bcd(&[0x0102], WordOrder::BigEndian).unwrap(), 102);
+/// assert_eq!(get_bcd(&[0x5678, 0x1234], WordOrder::LittleEndian).unwrap(), 1234_5678);
+/// ```
+pub fn get_bcd(src: &[u16], word_order: WordOrder) -> Result<u64, Error> {
+    if src.len() > 4 {
+        return Err(Error::TooManyData);
+    }
+
+    let mut value = 0u64;
+    for index in 0..src.len() {
+        let register = src[word_index(index, src.len(), word_order)];
+        for shift in [12, 8, 4, 0].iter() {
+            let digit = (register >> shift) & 0xF;
+            if digit > 9 {
+                return Err(Error::BadData);
+            }
+            value = value * 10 + u64::from(digit);
+        }
+    }
+    Ok(value)
+}
+
+/// `set_bcd` - set a number as packed BCD, four decimal digits per register
+///
+/// All registers of `dest` are written, leading digits are zero.
+///
+/// # Return value
+///
+/// `Error::TooManyData` if `value` has more digits than `dest` can hold, `dest` is not modified then.
+///
+/// # Parameters
+///
+/// * `value`       - the number
+/// * `dest`        - the registers to hold the number
+/// * `word_order`  - order of the registers, `BigEndian` for the most significant digits first
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::WordOrder;
+/// use libmodbus_rs::prelude::*;
+///
+/// let mut dest = [0u16; 2];
+/// set_bcd(20_231_231, &mut dest, WordOrder::BigEndian).unwrap();
+/// assert_eq!(dest, [0x2023, 0x1231]);
+/// ```
+pub fn set_bcd(value: u64, dest: &mut [u16], word_order: WordOrder) -> Result<(), Error> {
+    let digits = dest.len() as u32 * 4;
+    if digits < 20 && value >= 10u64.pow(digits) {
+        return Err(Error::TooManyData);
+    }
+
+    let mut rest = value;
+    let registers = dest.len();
+    for index in (0..registers).rev() {
+        let mut register = 0u16;
+        for shift in [0, 4, 8, 12].iter() {
+            register |= ((rest % 10) as u16) << shift;
+            rest /= 10;
+        }
+        dest[word_index(index, registers, word_order)] = register;
+    }
+    Ok(())
+}
+
+/// A named bit of a bitfield register, like a status or alarm word
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Flag<'a> {
+    /// Name of the flag
+    pub name: &'a str,
+    /// Position of the bit, `0` for the least significant bit
+    pub bit: u8,
+}
+
+impl<'a> Flag<'a> {
+    /// Create a new flag for `bit` of a register
+    pub fn new(name: &'a str, bit: u8) -> Flag<'a> {
+        Flag { name, bit }
+    }
+
+    fn mask(&self) -> u16 {
+        1u16.checked_shl(u32::from(self.bit)).unwrap_or(0)
+    }
+}
+
+/// `get_flags` - get the names of the flags set in a register
+///
+/// Bits without a `Flag` are ignored.
+///
+/// # Parameters
+///
+/// * `register`    - the bitfield
+/// * `flags`       - the names of the bits
+///
+/// # Examples
+///
+/// ```rust
+/// use libmodbus_rs::prelude::*;
+///
+/// const STATUS: [Flag<'static>; 3] = [Flag { name: "running", bit: 0 },
+///                                     Flag { name: "fault", bit: 1 },
+///                                     Flag { name: "remote", bit: 15 }];
+///
+/// assert_eq!(get_flags(0x8001, &STATUS), vec!["running", "remote"]);
+/// assert_eq!(set_flags(&["fault", "running"], &STATUS).unwrap(), 0x0003);
+/// ```
+pub fn get_flags<'a>(register: u16, flags: &[Flag<'a>]) -> Vec<&'a str> {
+    flags.iter().filter(|flag| register & flag.mask() != 0).map(|flag| flag.name).collect()
+}
+
+/// `set_flags` - get the register with the named flags set and all other bits cleared
+///
+/// The counterpart of [`get_flags()`](fn.get_flags.html).
+///
+/// # Return value
+///
+/// The register, or `Error::BadData` if a name is not one of `flags` or its bit is beyond bit 15.
+///
+/// # Parameters
+///
+/// * `names`       - the flags to set
+/// * `flags`       - the names of the bits
+pub fn set_flags(names: &[&str], flags: &[Flag]) -> Result<u16, Error> {
+    names.iter().try_fold(0u16, |register, name| {
+        match flags.iter().find(|flag| flag.name == *name).map(Flag::mask) {
+            Some(mask) if mask != 0 => Ok(register | mask),
+            _ => Err(Error::BadData),
+        }
+    })
+}
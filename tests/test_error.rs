@@ -0,0 +1,76 @@
+extern crate libc;
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Exception, Modbus};
+use std::io;
+
+
+#[test]
+fn from_errno_exception() {
+    match Error::from_errno(Modbus::ENOBASE as i32 + 6) {
+        Error::Exception(Exception::SlaveDeviceBusy) => {},
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn from_errno_bad_crc() {
+    match Error::from_errno(Modbus::ENOBASE as i32 + 12) {
+        Error::BadCrc => {},
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn from_errno_too_many_data() {
+    match Error::from_errno(Modbus::ENOBASE as i32 + 16) {
+        Error::TooManyData => {},
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn from_errno_timeout() {
+    match Error::from_errno(libc::ETIMEDOUT) {
+        Error::Timeout => {},
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn from_errno_os_error() {
+    match Error::from_errno(libc::ECONNREFUSED) {
+        Error::IoError(err) => assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused),
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn display_matches_strerror() {
+    for errnum in (Modbus::ENOBASE as i32 + 1)..(Modbus::ENOBASE as i32 + 18) {
+        if errnum == Modbus::ENOBASE as i32 + 9 || errnum == Modbus::ENOBASE as i32 + 15 {
+            // libmodbus has no message for these codes
+            continue;
+        }
+        assert_eq!(Error::from_errno(errnum).to_string(), Modbus::strerror(errnum));
+    }
+}
+
+#[test]
+fn display_chunk_failed() {
+    let err = Error::ChunkFailed { address: 250, count: 125, error: Box::new(Error::Timeout) };
+    assert_eq!(err.to_string(), format!("Chunk of 125 values at address 250 failed: {}", Error::Timeout));
+}
+
+#[test]
+fn is_transient() {
+    assert!(Error::Timeout.is_transient());
+    assert!(Error::BadCrc.is_transient());
+    assert!(Error::from_errno(libc::ECONNRESET).is_transient());
+    assert!(Error::Exception(Exception::GatewayTarget).is_transient());
+    assert!(Error::ChunkFailed { address: 0, count: 1, error: Box::new(Error::Timeout) }.is_transient());
+
+    assert!(!Error::Exception(Exception::IllegalDataAddress).is_transient());
+    assert!(!Error::TooManyData.is_transient());
+    assert!(!Error::ChunkFailed { address: 0, count: 1, error: Box::new(Error::BadData) }.is_transient());
+}
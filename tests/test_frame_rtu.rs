@@ -0,0 +1,128 @@
+extern crate libc;
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Modbus, ModbusClient, ModbusRTU, Timeout};
+use libmodbus_rs::frame::rtu::{self, Adu};
+use std::ffi::CStr;
+use std::thread;
+use std::time::Duration;
+
+
+#[test]
+fn crc16() {
+    assert_eq!(rtu::crc16(&[]), 0xFFFF);
+    assert_eq!(rtu::crc16(b"123456789"), 0x4B37);
+    // write single register 1 = 3 on slave 17
+    assert_eq!(rtu::crc16(&[0x11, 0x06, 0x00, 0x01, 0x00, 0x03]), 0x9B9A);
+}
+
+#[test]
+fn encode() {
+    let frame = Adu::new(0x11, &[0x06, 0x00, 0x01, 0x00, 0x03]).encode().unwrap();
+    assert_eq!(frame, vec![0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B]);
+}
+
+#[test]
+fn encode_invalid_pdu() {
+    match Adu::new(1, &[]).encode() {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    let pdu = vec![0x10; Modbus::MAX_PDU_LENGTH + 1];
+    match Adu::new(1, &pdu).encode() {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+    let pdu = vec![0x10; Modbus::MAX_PDU_LENGTH];
+    assert_eq!(Adu::new(1, &pdu).encode().unwrap().len(), Modbus::RTU_MAX_ADU_LENGTH);
+}
+
+#[test]
+fn decode() {
+    let frame = [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B];
+    let adu = Adu::decode(&frame).unwrap();
+    assert_eq!(adu, Adu::new(0x11, &[0x06, 0x00, 0x01, 0x00, 0x03]));
+    assert_eq!(adu.function(), Some(0x06));
+}
+
+#[test]
+fn decode_roundtrip() {
+    let pdu: Vec<u8> = (0..Modbus::MAX_PDU_LENGTH).map(|i| i as u8).collect();
+    let frame = Adu::new(Modbus::BROADCAST_ADDRESS, &pdu).encode().unwrap();
+    let adu = Adu::decode(&frame).unwrap();
+    assert_eq!(adu.slave, Modbus::BROADCAST_ADDRESS);
+    assert_eq!(adu.pdu, &pdu[..]);
+}
+
+#[test]
+fn decode_truncated() {
+    let frame = [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B];
+    match Adu::decode(&frame[..3]) {
+        Err(Error::IncompleteFrame) => {},
+        other => panic!("expected incomplete frame, got {:?}", other),
+    }
+    match Adu::decode(&frame[..7]) {
+        Err(Error::BadCrc) => {},
+        other => panic!("expected bad crc, got {:?}", other),
+    }
+}
+
+#[test]
+fn decode_corrupted() {
+    let mut frame = Adu::new(0x11, &[0x06, 0x00, 0x01, 0x00, 0x03]).encode().unwrap();
+    for i in 0..frame.len() {
+        frame[i] ^= 0x01;
+        match Adu::decode(&frame) {
+            Err(Error::BadCrc) => {},
+            other => panic!("flipped bit in byte {} not detected: {:?}", i, other),
+        }
+        frame[i] ^= 0x01;
+    }
+    assert!(Adu::decode(&frame).is_ok());
+}
+
+#[test]
+fn decode_too_long() {
+    let frame = vec![0; Modbus::RTU_MAX_ADU_LENGTH + 1];
+    match Adu::decode(&frame) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+/// Opens a pseudo-terminal, returns the master file descriptor and the path of the slave device
+fn open_pty() -> (i32, String) {
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(master >= 0, "could not open pseudo-terminal");
+        assert_eq!(libc::grantpt(master), 0);
+        assert_eq!(libc::unlockpt(master), 0);
+        let path = CStr::from_ptr(libc::ptsname(master)).to_string_lossy().into_owned();
+        (master, path)
+    }
+}
+
+#[test]
+fn send_raw_request_on_the_wire() {
+    let (master, path) = open_pty();
+    let mut raw_request = vec![0x11, 0x03, 0x00, 0x6B, 0x00, 0x03];
+    let expected = Adu::new(raw_request[0], &raw_request[1..]).encode().unwrap();
+
+    let mut modbus = Modbus::new_rtu(&path, 115200, 'N', 8, 1).unwrap();
+    modbus.set_response_timeout(Timeout { sec: 0, usec: 100_000 }).unwrap();
+    let modbus = modbus.connect().expect("could not connect to pseudo-terminal");
+    let request_len = raw_request.len();
+    assert_eq!(modbus.send_raw_request(&mut raw_request, request_len).unwrap(), expected.len() as u16);
+
+    // the pseudo-terminal delivers the frame to the master side as written
+    thread::sleep(Duration::from_millis(50));
+    let mut wire = vec![0u8; Modbus::RTU_MAX_ADU_LENGTH];
+    let len = unsafe { libc::read(master, wire.as_mut_ptr() as *mut libc::c_void, wire.len()) };
+    assert!(len > 0);
+    wire.truncate(len as usize);
+    assert_eq!(wire, expected);
+    assert_eq!(Adu::decode(&wire).unwrap().pdu, &raw_request[1..]);
+
+    drop(modbus);
+    unsafe { libc::close(master) };
+}
@@ -0,0 +1,172 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Modbus, ModbusClient, ModbusTCP};
+use libmodbus_rs::frame::tcp::{self, Adu, Decoder, Header};
+use std::io::{self, Read};
+use std::net::TcpListener;
+
+
+#[test]
+fn header_roundtrip() {
+    let header = Header { transaction_id: 0x1234, protocol_id: 0, length: 6, unit_id: 0x11 };
+    let bytes = header.encode();
+    assert_eq!(bytes, [0x12, 0x34, 0x00, 0x00, 0x00, 0x06, 0x11]);
+    assert_eq!(Header::decode(&bytes).unwrap(), header);
+    assert_eq!(header.frame_length(), 12);
+}
+
+#[test]
+fn header_validate() {
+    let header = Header { transaction_id: 1, protocol_id: 0, length: 6, unit_id: 0xFF };
+    assert!(header.validate().is_ok());
+
+    match (Header { protocol_id: 1, ..header }).validate() {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    match (Header { length: 1, ..header }).validate() {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    let max_length = (Modbus::TCP_MAX_ADU_LENGTH - tcp::HEADER_LENGTH + 1) as u16;
+    assert!(Header { length: max_length, ..header }.validate().is_ok());
+    match (Header { length: max_length + 1, ..header }).validate() {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+#[test]
+fn encode() {
+    let frame = Adu::new(0x0102, 0x11, &[0x03, 0x00, 0x6B, 0x00, 0x03]).encode().unwrap();
+    assert_eq!(frame, vec![0x01, 0x02, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]);
+}
+
+#[test]
+fn encode_invalid_pdu() {
+    match Adu::new(1, 0xFF, &[]).encode() {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    let pdu = vec![0x10; Modbus::MAX_PDU_LENGTH];
+    assert_eq!(Adu::new(1, 0xFF, &pdu).encode().unwrap().len(), Modbus::TCP_MAX_ADU_LENGTH);
+    let pdu = vec![0x10; Modbus::MAX_PDU_LENGTH + 1];
+    match Adu::new(1, 0xFF, &pdu).encode() {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+#[test]
+fn decode() {
+    let frame = [0x01, 0x02, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03, 0x00, 0x6B, 0x00, 0x03];
+    let adu = Adu::decode(&frame).unwrap();
+    assert_eq!(adu, Adu::new(0x0102, 0x11, &[0x03, 0x00, 0x6B, 0x00, 0x03]));
+    assert_eq!(adu.function(), Some(0x03));
+    assert_eq!(adu.header(), Header::decode(&frame).unwrap());
+}
+
+#[test]
+fn decode_length_mismatch() {
+    let frame = [0x01, 0x02, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x00];
+    match Adu::decode(&frame[..5]) {
+        Err(Error::IncompleteFrame) => {},
+        other => panic!("expected incomplete frame, got {:?}", other),
+    }
+    match Adu::decode(&frame[..11]) {
+        Err(Error::IncompleteFrame) => {},
+        other => panic!("expected incomplete frame, got {:?}", other),
+    }
+    match Adu::decode(&frame) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+#[test]
+fn decoder_partial_and_multiple_frames() {
+    let first = Adu::new(1, 0xFF, &[0x03, 0x00, 0x00, 0x00, 0x01]).encode().unwrap();
+    let second = Adu::new(2, 0xFF, &[0x06, 0x00, 0x01, 0x00, 0x03]).encode().unwrap();
+    let stream: Vec<u8> = first.iter().chain(second.iter()).cloned().collect();
+
+    let mut decoder = Decoder::new();
+    let mut frames = Vec::new();
+    // byte by byte, the worst case of a fragmented stream
+    for byte in &stream {
+        decoder.push(&[*byte]);
+        while let Some(frame) = decoder.next_frame().unwrap() {
+            frames.push(frame);
+        }
+    }
+    assert_eq!(frames, vec![first.clone(), second.clone()]);
+    assert_eq!(decoder.buffered(), 0);
+
+    // both frames in one chunk
+    decoder.push(&stream);
+    assert_eq!(decoder.next_frame().unwrap(), Some(first));
+    assert_eq!(decoder.next_frame().unwrap(), Some(second));
+    assert_eq!(decoder.next_frame().unwrap(), None);
+}
+
+#[test]
+fn decoder_invalid_header() {
+    let mut decoder = Decoder::new();
+    decoder.push(&[0x00, 0x01, 0x00, 0x07, 0x00, 0x06, 0xFF, 0x03]);
+    match decoder.next_frame() {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    assert_eq!(decoder.buffered(), 0);
+}
+
+/// Returns at most `chunk` bytes per read, like a socket under load
+struct Fragmented<'a> {
+    data: &'a [u8],
+    chunk: usize,
+}
+
+impl<'a> Read for Fragmented<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.chunk.min(buf.len()).min(self.data.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn decoder_read_frame() {
+    let first = Adu::new(1, 0xFF, &[0x03, 0x00, 0x00, 0x00, 0x01]).encode().unwrap();
+    let second = Adu::new(2, 0xFF, &[0x06, 0x00, 0x01, 0x00, 0x03]).encode().unwrap();
+    let stream: Vec<u8> = first.iter().chain(second.iter()).cloned().collect();
+
+    let mut reader = Fragmented { data: &stream, chunk: 5 };
+    let mut decoder = Decoder::new();
+    assert_eq!(decoder.read_frame(&mut reader).unwrap(), Some(first));
+    assert_eq!(decoder.read_frame(&mut reader).unwrap(), Some(second));
+    assert_eq!(decoder.read_frame(&mut reader).unwrap(), None);
+
+    let mut reader = Fragmented { data: &stream[..15], chunk: 4 };
+    let mut decoder = Decoder::new();
+    assert!(decoder.read_frame(&mut reader).unwrap().is_some());
+    match decoder.read_frame(&mut reader) {
+        Err(Error::IncompleteFrame) => {},
+        other => panic!("expected incomplete frame, got {:?}", other),
+    }
+}
+
+#[test]
+fn send_raw_request_on_the_wire() {
+    let listener = TcpListener::bind("127.0.0.1:1522").unwrap();
+    let modbus = Modbus::new_tcp("127.0.0.1", 1522).unwrap().connect().expect("could not connect");
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let mut raw_request = vec![0xFF, 0x03, 0x00, 0x6B, 0x00, 0x03];
+    let request_len = raw_request.len();
+    modbus.send_raw_request(&mut raw_request, request_len).unwrap();
+
+    let frame = Decoder::new().read_frame(&mut stream).unwrap().expect("no frame received");
+    let adu = Adu::decode(&frame).unwrap();
+    assert_eq!(adu.unit_id, 0xFF);
+    assert_eq!(adu.pdu, &raw_request[1..]);
+}
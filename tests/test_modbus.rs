@@ -1,13 +1,15 @@
 extern crate libmodbus_rs;
 
-use libmodbus_rs::{Modbus, Timeout, ModbusTCP};
+use libmodbus_rs::{Error, Modbus, Timeout, ModbusTCP};
+use std::convert::TryFrom;
+use std::time::Duration;
 
 
 #[test]
 fn connect() {
-    // create server
-    match Modbus::new_tcp("127.0.0.1", 1502) {
-            Ok(mut server) => server.tcp_listen(1),
+    // create server, it listens as long as the listener is alive
+    let _listener = match Modbus::new_tcp("127.0.0.1", 1502) {
+            Ok(server) => server.tcp_listen(1),
             Err(err) => panic!("Could not create server: {}", err),
         }
         .unwrap();
@@ -21,7 +23,8 @@ fn connect() {
 
 #[test]
 fn flush() {
-    let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    let _listener = Modbus::new_tcp("127.0.0.1", 1531).unwrap().tcp_listen(1).unwrap();
+    let modbus = Modbus::new_tcp("127.0.0.1", 1531).unwrap().connect().unwrap();
     assert!(modbus.flush().is_ok());
 }
 
@@ -136,9 +139,9 @@ fn get_header_length() {
 #[test]
 #[ignore]
 fn reply_exception() {
-    // create server
-    match Modbus::new_tcp("127.0.0.1", 1502) {
-            Ok(mut server) => server.tcp_listen(1),
+    // create server, it listens as long as the listener is alive
+    let _listener = match Modbus::new_tcp("127.0.0.1", 1502) {
+            Ok(server) => server.tcp_listen(1),
             Err(err) => panic!("Could not create server: {}", err),
         }
         .unwrap();
@@ -152,16 +155,18 @@ fn reply_exception() {
 
 
 #[test]
-#[ignore]
 fn close() {
-    let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
-    modbus.close();
+    let _listener = Modbus::new_tcp("127.0.0.1", 1532).unwrap().tcp_listen(1).unwrap();
+    let modbus = Modbus::new_tcp("127.0.0.1", 1532).unwrap().connect().unwrap();
+    let modbus = modbus.close();
+    // the context can be connected again after it was closed
+    let modbus = modbus.connect().unwrap();
+    modbus.close().free();
 }
 
 #[test]
-#[ignore]
 fn free() {
-    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     modbus.free();
 }
 
@@ -189,3 +194,118 @@ fn timeout_new_usec() {
     let timeout = Timeout::new_usec(2);
     assert_eq!(timeout, Timeout { sec: 0, usec: 2});
 }
+
+// Exception tests
+#[test]
+fn exception_try_from() {
+    use libmodbus_rs::Exception;
+    use std::convert::TryFrom;
+
+    assert_eq!(Exception::try_from(1).unwrap(), Exception::IllegalFunction);
+    assert_eq!(Exception::try_from(2).unwrap(), Exception::IllegalDataAddress);
+    assert_eq!(Exception::try_from(6).unwrap(), Exception::SlaveDeviceBusy);
+    assert_eq!(Exception::try_from(11).unwrap(), Exception::GatewayTarget);
+}
+
+#[test]
+fn exception_try_from_invalid() {
+    use libmodbus_rs::{Error, Exception};
+    use std::convert::TryFrom;
+
+    match Exception::try_from(0) {
+        Err(Error::BadException) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match Exception::try_from(12) {
+        Err(Error::BadException) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn exception_display() {
+    use libmodbus_rs::Exception;
+
+    assert_eq!(Exception::SlaveDeviceBusy.to_string(), "Slave device or server is busy");
+    assert_eq!(Exception::IllegalDataAddress.to_string(), "Illegal data address");
+}
+
+// FunctionCode tests
+#[test]
+fn function_code_values() {
+    use libmodbus_rs::FunctionCode;
+
+    assert_eq!(FunctionCode::WriteMultipleCoils as u8, 0x0F);
+    assert_eq!(FunctionCode::WriteMultipleRegisters as u8, 0x10);
+    assert_eq!(FunctionCode::ReportSlaveId as u8, 0x11);
+    assert_eq!(FunctionCode::MaskWriteRegister as u8, 0x16);
+    assert_eq!(FunctionCode::WriteAndReadRegisters as u8, 0x17);
+}
+
+#[test]
+fn function_code_try_from() {
+    use libmodbus_rs::{Exception, FunctionCode};
+
+    for code in 0..=255u8 {
+        if let Ok(function) = FunctionCode::try_from(code) {
+            assert_eq!(function as u8, code);
+        }
+    }
+    match FunctionCode::try_from(0x42) {
+        Err(Error::Exception(Exception::IllegalFunction)) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn set_indication_timeout() {
+    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    assert_eq!(modbus.get_indication_timeout().unwrap(), Timeout { sec: 0, usec: 0 });
+
+    assert!(modbus.set_indication_timeout(Timeout { sec: 2, usec: 250000 }).is_ok());
+    assert_eq!(modbus.get_indication_timeout().unwrap(), Timeout { sec: 2, usec: 250000 });
+}
+
+#[test]
+fn set_timeout_invalid_usec() {
+    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    let timeout = Timeout { sec: 0, usec: 1_000_000 };
+
+    match modbus.set_byte_timeout(timeout) {
+        Err(Error::InvalidTimeout) => {},
+        other => panic!("expected invalid timeout, got {:?}", other),
+    }
+    match modbus.set_response_timeout(timeout) {
+        Err(Error::InvalidTimeout) => {},
+        other => panic!("expected invalid timeout, got {:?}", other),
+    }
+    match modbus.set_indication_timeout(timeout) {
+        Err(Error::InvalidTimeout) => {},
+        other => panic!("expected invalid timeout, got {:?}", other),
+    }
+    // unchanged
+    assert_eq!(modbus.get_byte_timeout().unwrap(), Timeout { sec: 0, usec: 500000 });
+}
+
+#[test]
+fn timeout_duration_conversion() {
+    assert_eq!(Timeout::try_from(Duration::new(3, 123_456_789)).unwrap(), Timeout { sec: 3, usec: 123_456 });
+    assert_eq!(Duration::from(Timeout { sec: 3, usec: 123_456 }), Duration::new(3, 123_456_000));
+    match Timeout::try_from(Duration::from_secs(u64::from(u32::MAX) + 1)) {
+        Err(Error::InvalidTimeout) => {},
+        other => panic!("expected invalid timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn set_timeouts_duration() {
+    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+
+    assert!(modbus.set_byte_timeout_duration(Duration::from_millis(100)).is_ok());
+    assert!(modbus.set_response_timeout_duration(Duration::from_millis(1500)).is_ok());
+    assert!(modbus.set_indication_timeout_duration(Duration::from_secs(5)).is_ok());
+
+    assert_eq!(modbus.get_byte_timeout().unwrap(), Timeout { sec: 0, usec: 100000 });
+    assert_eq!(modbus.get_response_timeout_duration().unwrap(), Duration::from_millis(1500));
+    assert_eq!(modbus.get_indication_timeout_duration().unwrap(), Duration::from_secs(5));
+}
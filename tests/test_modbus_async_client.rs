@@ -0,0 +1,141 @@
+#![cfg(feature = "tokio")]
+extern crate libc;
+extern crate libmodbus_rs;
+
+use libmodbus_rs::frame::tcp::{Adu, Decoder};
+use libmodbus_rs::{AsyncModbusClient, Error, Exception, Modbus, ModbusMapping, ModbusTCP, TcpServer};
+use std::io::{self, Write};
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+
+fn start_server(port: u16, mapping: Arc<ModbusMapping>) {
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", i32::from(port)).unwrap().tcp_listen(5).unwrap();
+        let _ = TcpServer::new(listener).serve(&mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn read_and_write() {
+    let port = 1529;
+    let mapping = Arc::new(ModbusMapping::new(16, 16, 16, 16).unwrap());
+    mapping.lock().get_input_registers_mut()[..2].copy_from_slice(&[0x1234, 0x5678]);
+    mapping.lock().get_input_bits_mut()[..3].copy_from_slice(&[1, 0, 1]);
+    start_server(port, mapping.clone());
+
+    let mut client = AsyncModbusClient::connect(("127.0.0.1", port)).await.expect("could not connect");
+
+    client.write_bit(1, true).await.unwrap();
+    assert_eq!(client.write_bits(4, &[true, false, true]).await.unwrap(), 3);
+    assert_eq!(client.read_bits(0, 7).await.unwrap(), vec![false, true, false, false, true, false, true]);
+    assert_eq!(client.read_input_bits(0, 3).await.unwrap(), vec![true, false, true]);
+
+    client.write_register(0, 0x00F0).await.unwrap();
+    assert_eq!(client.write_registers(1, &[1, 2, 3]).await.unwrap(), 3);
+    client.mask_write_register(0, 0x000F, 0x0005).await.unwrap();
+    assert_eq!(client.read_registers(0, 4).await.unwrap(), vec![0x0000, 1, 2, 3]);
+    assert_eq!(client.write_and_read_registers(4, &[4, 5], 3, 3).await.unwrap(), vec![3, 4, 5]);
+    assert_eq!(client.read_input_registers(0, 2).await.unwrap(), vec![0x1234, 0x5678]);
+    assert_eq!(mapping.lock().get_registers()[..6], [0x0000, 1, 2, 3, 4, 5]);
+
+    // libmodbus answers with its slave id 180, the run indicator and its version
+    let id = client.report_slave_id().await.unwrap();
+    assert_eq!(id[..2], [180, 0xFF]);
+    assert!(id[2..].starts_with(b"LMB"));
+}
+
+#[tokio::test]
+async fn errors() {
+    let port = 1536;
+    start_server(port, Arc::new(ModbusMapping::new(0, 0, 10, 0).unwrap()));
+
+    let mut client = AsyncModbusClient::connect(("127.0.0.1", port)).await.expect("could not connect");
+
+    match client.read_registers(5, 10).await {
+        Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+        other => panic!("expected illegal data address, got {:?}", other),
+    }
+    match client.read_registers(0, Modbus::MAX_READ_REGISTERS as u16 + 1).await {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+    // the connection is still usable
+    assert_eq!(client.read_registers(0, 10).await.unwrap(), vec![0; 10]);
+}
+
+#[tokio::test]
+async fn late_response_is_discarded() {
+    let port = 1537;
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut decoder = Decoder::new();
+        for value in [1u16, 2].iter() {
+            let request = decoder.read_frame(&mut stream).unwrap().unwrap();
+            let transaction_id = Adu::decode(&request).unwrap().transaction_id;
+            if *value == 1 {
+                // answer the first request after the client gave up
+                thread::sleep(Duration::from_millis(300));
+            }
+            let pdu = [0x03, 0x02, (value >> 8) as u8, *value as u8];
+            stream.write_all(&Adu::new(transaction_id, 0xFF, &pdu).encode().unwrap()).unwrap();
+        }
+    });
+
+    let mut client = AsyncModbusClient::connect(("127.0.0.1", port)).await.expect("could not connect");
+    client.set_response_timeout(Duration::from_millis(100));
+    assert_eq!(client.get_response_timeout(), Duration::from_millis(100));
+
+    match client.read_registers(0, 1).await {
+        Err(Error::Timeout) => {},
+        other => panic!("expected timeout, got {:?}", other),
+    }
+    client.set_response_timeout(Duration::from_secs(1));
+    assert_eq!(client.read_registers(0, 1).await.unwrap(), vec![2]);
+}
+
+fn set_buffer_size<S: AsRawFd>(socket: &S, option: libc::c_int) {
+    let size: libc::c_int = 4096;
+    unsafe {
+        libc::setsockopt(socket.as_raw_fd(), libc::SOL_SOCKET, option, &size as *const _ as *const libc::c_void,
+                         mem::size_of::<libc::c_int>() as libc::socklen_t);
+    }
+}
+
+#[tokio::test]
+async fn interrupted_write_breaks_connection() {
+    let port = 1553;
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    set_buffer_size(&listener, libc::SO_RCVBUF);
+    // the server never reads, the small buffers fill up until a request can not be written completely
+    let server = thread::spawn(move || listener.accept().unwrap());
+
+    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    set_buffer_size(&stream, libc::SO_SNDBUF);
+    stream.set_nonblocking(true).unwrap();
+    let mut client = AsyncModbusClient::from_stream(tokio::net::TcpStream::from_std(stream).unwrap()).unwrap();
+    client.set_response_timeout(Duration::from_millis(10));
+
+    let values = vec![0; Modbus::MAX_WRITE_REGISTERS as usize];
+    let mut requests = 0;
+    while !client.is_broken() {
+        match client.write_registers(0, &values).await {
+            Err(Error::Timeout) => requests += 1,
+            other => panic!("expected timeout, got {:?}", other),
+        }
+        assert!(requests < 1000, "buffers did not fill up");
+    }
+
+    // nothing is sent after the partial frame
+    match client.read_registers(0, 1).await {
+        Err(Error::IoError(ref err)) if err.kind() == io::ErrorKind::BrokenPipe => {},
+        other => panic!("expected broken connection, got {:?}", other),
+    }
+    drop(server.join().unwrap());
+}
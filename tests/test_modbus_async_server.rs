@@ -0,0 +1,139 @@
+#![cfg(feature = "tokio")]
+extern crate libmodbus_rs;
+
+use libmodbus_rs::frame::tcp::{Adu, Decoder};
+use libmodbus_rs::{AsyncModbusClient, AsyncRequestHandler, AsyncTcpServer, Error, Exception, Modbus, ModbusClient,
+                   ModbusMapping, ModbusTCP};
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::task;
+
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serve_mapping() {
+    let port = 1538;
+    let mapping = Arc::new(ModbusMapping::new_start_address(0, 8, 0, 0, 100, 10, 0, 0).unwrap());
+    let server = AsyncTcpServer::bind(("127.0.0.1", port)).await.unwrap();
+    assert_eq!(server.local_addr().unwrap().port(), port);
+    tokio::spawn(async move { server.serve(mapping).await });
+
+    // the blocking libmodbus client talks to the async server
+    task::spawn_blocking(move || {
+        let client = Modbus::new_tcp("127.0.0.1", i32::from(port)).unwrap().connect().expect("could not connect");
+        assert_eq!(client.write_registers(100, 3, &[1, 2, 3]).unwrap(), 3);
+        client.write_register(109, 9).unwrap();
+        assert_eq!(client.read_registers_vec(100, 10).unwrap(), vec![1, 2, 3, 0, 0, 0, 0, 0, 0, 9]);
+        client.mask_write_register(100, 0x00F0, 0x0004).unwrap();
+        let mut read = vec![0u16; 2];
+        client.write_and_read_registers(102, 1, &[7], 100, 2, &mut read).unwrap();
+        assert_eq!(read, vec![0x0004, 2]);
+
+        client.write_bit(3, true).unwrap();
+        assert_eq!(client.read_bits_vec(0, 8).unwrap(), vec![false, false, false, true, false, false, false, false]);
+
+        // outside of the tables
+        match client.read_registers_vec(0, 1) {
+            Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+            other => panic!("expected illegal data address, got {:?}", other),
+        }
+        match client.read_registers_vec(105, 6) {
+            Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+            other => panic!("expected illegal data address, got {:?}", other),
+        }
+
+        // answered like libmodbus does
+        let mut id = vec![0u8; 32];
+        let len = client.report_slave_id(32, &mut id).unwrap() as usize;
+        assert_eq!(id[..5], [180, 0xFF, b'L', b'M', b'B']);
+        assert!(len > 5);
+    })
+    .await
+    .unwrap();
+}
+
+#[derive(Default)]
+struct Store {
+    registers: Mutex<Vec<u16>>,
+}
+
+impl AsyncRequestHandler for Store {
+    async fn read_holding_registers(&self, address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+        let registers = self.registers.lock().unwrap();
+        registers.get(address as usize..(address + count) as usize)
+            .map(|values| values.to_vec())
+            .ok_or(Exception::IllegalDataAddress)
+    }
+
+    async fn write_multiple_registers(&self, address: u16, values: &[u16]) -> Result<(), Exception> {
+        let mut registers = self.registers.lock().unwrap();
+        let end = address as usize + values.len();
+        if registers.len() < end {
+            registers.resize(end, 0);
+        }
+        registers[address as usize..end].copy_from_slice(values);
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serve_with_handler() {
+    let port = 1539;
+    let server = AsyncTcpServer::bind(("127.0.0.1", port)).await.unwrap();
+    tokio::spawn(async move { server.serve_with(Arc::new(Store::default())).await });
+
+    let mut clients = Vec::new();
+    for _ in 0..2 {
+        clients.push(AsyncModbusClient::connect(("127.0.0.1", port)).await.expect("could not connect"));
+    }
+    assert_eq!(clients[0].write_registers(2, &[20, 30]).await.unwrap(), 2);
+    assert_eq!(clients[1].read_registers(0, 4).await.unwrap(), vec![0, 0, 20, 30]);
+
+    match clients[1].read_registers(3, 2).await {
+        Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+        other => panic!("expected illegal data address, got {:?}", other),
+    }
+    // not implemented by the handler
+    match clients[0].write_register(0, 1).await {
+        Err(Error::Exception(Exception::IllegalFunction)) => {},
+        other => panic!("expected illegal function, got {:?}", other),
+    }
+    // more registers than allowed in one response
+    match clients[0].read_registers(0, Modbus::MAX_READ_REGISTERS as u16 + 1).await {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requests() {
+    let port = 1540;
+    let server = AsyncTcpServer::bind(("127.0.0.1", port)).await.unwrap();
+    tokio::spawn(async move { server.serve(Arc::new(ModbusMapping::new(0, 0, 10, 0).unwrap())).await });
+
+    task::spawn_blocking(move || {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut decoder = Decoder::new();
+        let requests: [(&[u8], &[u8]); 4] = [
+            // unknown function code
+            (&[0x42, 0x00], &[0xC2, Exception::IllegalFunction as u8]),
+            // byte count does not match the number of registers
+            (&[0x10, 0x00, 0x00, 0x00, 0x02, 0x02, 0x00, 0x01], &[0x90, Exception::IllegalDataValue as u8]),
+            // no registers
+            (&[0x03, 0x00, 0x00, 0x00, 0x00], &[0x83, Exception::IllegalDataValue as u8]),
+            (&[0x03, 0x00, 0x09, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x00]),
+        ];
+
+        for (transaction_id, &(request, response)) in requests.iter().enumerate() {
+            stream.write_all(&Adu::new(transaction_id as u16, 0x11, request).encode().unwrap()).unwrap();
+            let frame = decoder.read_frame(&mut stream).unwrap().unwrap();
+            let adu = Adu::decode(&frame).unwrap();
+            assert_eq!(adu.transaction_id, transaction_id as u16);
+            assert_eq!(adu.unit_id, 0x11);
+            assert_eq!(adu.pdu, response);
+        }
+    })
+    .await
+    .unwrap();
+}
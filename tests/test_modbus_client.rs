@@ -1,14 +1,15 @@
 extern crate libmodbus_rs;
 
-use libmodbus_rs::{Modbus, ModbusClient, ModbusServer, ModbusMapping, FunctionCode, ModbusTCP};
+use libmodbus_rs::{Error, Exception, Modbus, ModbusClient, ModbusServer, ModbusMapping, FunctionCode, ModbusTCP,
+                   TcpServer};
 use std::thread;
 use std::time::Duration;
 
 fn start_server(port: i32) -> thread::JoinHandle<()> {
     thread::spawn(move || {
-        let mut modbus = Modbus::new_tcp("127.0.0.1", port).expect("Could not create TCP Server context");
-        let mut socket = modbus.tcp_listen(1).expect("Could not listen to TCP socket");
-        modbus.tcp_accept(&mut socket).expect("Could not accept connection");
+        let modbus = Modbus::new_tcp("127.0.0.1", port).expect("Could not create TCP Server context");
+        let listener = modbus.tcp_listen(1).expect("Could not listen to TCP socket");
+        let modbus = listener.tcp_accept().expect("Could not accept connection");
 
         let mb_mapping = ModbusMapping::new(Modbus::MAX_READ_BITS, 
                                             Modbus::MAX_READ_BITS,
@@ -38,7 +39,7 @@ fn read_bits() {
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
             let mut dest = vec![0u8; 100];
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             assert!(client.read_bits(0, 1, &mut dest).is_ok());
         },
         _ => panic!("could not connect"),
@@ -58,7 +59,7 @@ fn read_input_bits() {
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
             let mut dest = vec![0u8; 100];
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             assert!(client.read_input_bits(0, 1, &mut dest).is_ok());
         },
         _ => panic!("could not connect"),
@@ -78,7 +79,7 @@ fn read_registers() {
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
             let mut dest = vec![0u16; 100];
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             assert!(client.read_registers(0, 1, &mut dest).is_ok());
         },
         _ => panic!("could not connect"),
@@ -98,7 +99,7 @@ fn read_input_registers() {
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
             let mut dest = vec![0u16; 100];
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             assert!(client.read_input_registers(0, 1, &mut dest).is_ok());
         },
         _ => panic!("could not connect"),
@@ -118,7 +119,7 @@ fn report_slave_id() {
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
             let mut bytes = vec![0u8; Modbus::MAX_PDU_LENGTH];
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             // println!("{:?}", str::from_utf8(&client.report_slave_id[2..])) # => Ok("LMB3.1.4")
             assert!(client.report_slave_id(Modbus::MAX_PDU_LENGTH, &mut bytes).is_ok());
             // assert_eq!(client.report_slave_id().unwrap(), vec![180, 255, 76, 77, 66, 51, 46, 49, 46, 52]);
@@ -139,7 +140,7 @@ fn write_bit() {
     // connect client
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             assert!(client.write_bit(0, true).is_ok());
         },
         _ => panic!("could not connect"),
@@ -158,7 +159,7 @@ fn write_bits() {
     // connect client
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             let src = vec![1u8];
             assert_eq!(client.write_bits(0, 1, &src).unwrap(), 1);
         },
@@ -178,7 +179,7 @@ fn write_register() {
     // connect client
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             let address: u16 = 1;
             let value = u16::max_value();
             assert!(client.write_register(address, value).is_ok());
@@ -199,7 +200,7 @@ fn write_registers() {
     // connect client
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             let address = 1;
             let tab_bytes = vec![0u16];
             assert_eq!(client.write_registers(address, 1, &tab_bytes).unwrap(), 1);
@@ -220,7 +221,7 @@ fn write_and_read_registers() {
     // connect client
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
-            client.connect().expect("could not connect");
+            let _client = client.connect().expect("could not connect");
         },
         _ => panic!("could not connect"),
     }
@@ -238,10 +239,11 @@ fn send_raw_request() {
     // connect client
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             let mut raw_request: Vec<u8> = vec![0xFF, FunctionCode::ReadHoldingRegisters as u8, 0x00, 0x01, 0x0, 0x05];
             let mut response = vec![0u8; Modbus::MAX_ADU_LENGTH];
-            assert_eq!(client.send_raw_request(&mut raw_request, 12).unwrap(), 18);
+            let request_len = raw_request.len();
+            assert_eq!(client.send_raw_request(&mut raw_request, request_len).unwrap(), 12);
             assert!(client.receive_confirmation(&mut response).is_ok());
         },
         _ => panic!("could not connect"),
@@ -260,10 +262,11 @@ fn receive_confirmation() {
     // connect client
     match Modbus::new_tcp("127.0.0.1", port) {
         Ok(client) => {
-            client.connect().expect("could not connect");
+            let client = client.connect().expect("could not connect");
             let mut raw_request: Vec<u8> = vec![0xFF, FunctionCode::ReadHoldingRegisters as u8, 0x00, 0x01, 0x0, 0x05];
             let mut response = vec![0u8; Modbus::MAX_ADU_LENGTH];
-            assert_eq!(client.send_raw_request(&mut raw_request, 12).unwrap(), 18);
+            let request_len = raw_request.len();
+            assert_eq!(client.send_raw_request(&mut raw_request, request_len).unwrap(), 12);
             assert!(client.receive_confirmation(&mut response).is_ok());
         },
         _ => panic!("could not connect"),
@@ -271,3 +274,285 @@ fn receive_confirmation() {
 
     let _ = server_thread.join();
 }
+
+#[test]
+fn read_registers_illegal_data_address() {
+    let port = 1514;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    // connect client
+    match Modbus::new_tcp("127.0.0.1", port) {
+        Ok(client) => {
+            let mut dest = vec![0u16; 100];
+            let client = client.connect().expect("could not connect");
+            match client.read_registers(Modbus::MAX_READ_REGISTERS as u16, 1, &mut dest) {
+                Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+                other => panic!("expected illegal data address exception, got {:?}", other),
+            }
+        },
+        _ => panic!("could not connect"),
+    }
+
+    let _ = server_thread.join();
+}
+
+#[test]
+fn receive_confirmation_exception() {
+    let port = 1515;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    // connect client
+    match Modbus::new_tcp("127.0.0.1", port) {
+        Ok(client) => {
+            let client = client.connect().expect("could not connect");
+            // read one register behind the end of the mapping
+            let mut raw_request: Vec<u8> = vec![0xFF, FunctionCode::ReadHoldingRegisters as u8, 0x00, 0x7D, 0x00, 0x01];
+            let mut response = vec![0u8; Modbus::MAX_ADU_LENGTH];
+            let request_len = raw_request.len();
+            client.send_raw_request(&mut raw_request, request_len).expect("could not send raw request");
+            match client.receive_confirmation(&mut response) {
+                Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+                other => panic!("expected illegal data address exception, got {:?}", other),
+            }
+        },
+        _ => panic!("could not connect"),
+    }
+
+    let _ = server_thread.join();
+}
+
+#[test]
+fn read_registers_buffer_too_small() {
+    let port = 1518;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let mut dest = vec![0u16; 4];
+    match client.read_registers(0, 5, &mut dest) {
+        Err(Error::BufferTooSmall { required: 5, available: 4 }) => {},
+        other => panic!("expected buffer too small, got {:?}", other),
+    }
+    drop(client);
+
+    let _ = server_thread.join();
+}
+
+#[test]
+fn read_registers_too_many_data() {
+    let port = 1519;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let num = Modbus::MAX_READ_REGISTERS as u16 + 1;
+    let mut dest = vec![0u16; num as usize];
+    match client.read_registers(0, num, &mut dest) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+    drop(client);
+
+    let _ = server_thread.join();
+}
+
+#[test]
+fn write_and_read_registers_buffer_too_small() {
+    let port = 1520;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let src = vec![0u16; 2];
+    let mut dest = vec![0u16; 2];
+    match client.write_and_read_registers(0, 3, &src, 0, 2, &mut dest) {
+        Err(Error::BufferTooSmall { required: 3, available: 2 }) => {},
+        other => panic!("expected buffer too small, got {:?}", other),
+    }
+    drop(client);
+
+    let _ = server_thread.join();
+}
+
+#[test]
+fn send_raw_request_buffer_too_small() {
+    let port = 1521;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let mut raw_request: Vec<u8> = vec![0xFF, FunctionCode::ReadHoldingRegisters as u8, 0x00, 0x01, 0x0, 0x05];
+    match client.send_raw_request(&mut raw_request, 12) {
+        Err(Error::BufferTooSmall { required: 12, available: 6 }) => {},
+        other => panic!("expected buffer too small, got {:?}", other),
+    }
+    drop(client);
+
+    let _ = server_thread.join();
+}
+
+#[test]
+fn read_registers_vec() {
+    let port = 1516;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    // connect client
+    match Modbus::new_tcp("127.0.0.1", port) {
+        Ok(client) => {
+            let client = client.connect().expect("could not connect");
+            client.write_registers(0, 3, &[1, 2, 3]).expect("could not write registers");
+            assert_eq!(client.read_registers_vec(0, 3).unwrap(), vec![1, 2, 3]);
+        },
+        _ => panic!("could not connect"),
+    }
+
+    let _ = server_thread.join();
+}
+
+#[test]
+fn read_bits_vec() {
+    let port = 1517;
+    // Start modbus server
+    let server_thread = start_server(port);
+    thread::sleep(Duration::from_millis(200));
+
+    // connect client
+    match Modbus::new_tcp("127.0.0.1", port) {
+        Ok(client) => {
+            let client = client.connect().expect("could not connect");
+            client.write_bits(0, 3, &[1, 0, 1]).expect("could not write bits");
+            assert_eq!(client.read_bits_vec(0, 3).unwrap(), vec![true, false, true]);
+        },
+        _ => panic!("could not connect"),
+    }
+
+    let _ = server_thread.join();
+}
+
+fn start_mapping_server(port: i32, mapping: ModbusMapping) {
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(1).unwrap();
+        let _ = TcpServer::new(listener).serve(&mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+}
+
+#[test]
+fn registers_range() {
+    let port = 1543;
+    start_mapping_server(port, ModbusMapping::new(0, 0, 2000, 2000).unwrap());
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let values: Vec<u16> = (0..2000).collect();
+    client.write_registers_range(0, &values).unwrap();
+    assert_eq!(client.read_registers_range(0, 2000).unwrap(), values);
+    assert_eq!(client.read_registers_range(1990, 10).unwrap(), values[1990..].to_vec());
+    assert_eq!(client.read_input_registers_range(0, 2000).unwrap(), vec![0; 2000]);
+    assert!(client.read_registers_range(0, 0).unwrap().is_empty());
+
+    // the 17th request of 125 registers starts at 2000, behind the end of the mapping
+    match client.read_registers_range(0, 2001) {
+        Err(Error::ChunkFailed { address: 2000, count: 1, error }) => {
+            match *error {
+                Error::Exception(Exception::IllegalDataAddress) => {},
+                other => panic!("expected illegal data address exception, got {:?}", other),
+            }
+        },
+        other => panic!("expected failed chunk, got {:?}", other),
+    }
+    match client.write_registers_range(1950, &values[..100]) {
+        Err(Error::ChunkFailed { address: 1950, count: 100, .. }) => {},
+        other => panic!("expected failed chunk, got {:?}", other),
+    }
+    match client.read_registers_range(0xFFFF, 2) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+#[test]
+fn bits_range() {
+    let port = 1544;
+    start_mapping_server(port, ModbusMapping::new(5000, 5000, 0, 0).unwrap());
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let values: Vec<u8> = (0..5000).map(|i| (i % 3 == 0) as u8).collect();
+    client.write_bits_range(0, &values).unwrap();
+    let bits = client.read_bits_range(0, 5000).unwrap();
+    assert_eq!(bits, values.iter().map(|&value| value == 1).collect::<Vec<_>>());
+    assert_eq!(client.read_input_bits_range(0, 5000).unwrap(), vec![false; 5000]);
+}
+
+/// A client implementing the single requests only, on top of a register table
+struct Registers(std::cell::RefCell<Vec<u16>>);
+
+impl ModbusClient for Registers {
+    fn read_bits(&self, _: u16, _: u16, _: &mut [u8]) -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+    fn read_input_bits(&self, _: u16, _: u16, _: &mut [u8]) -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+    fn read_registers(&self, address: u16, num: u16, dest: &mut [u16]) -> Result<u16, Error> {
+        let registers = self.0.borrow();
+        let values = registers.get(address as usize..(address + num) as usize).ok_or(Error::TooManyData)?;
+        dest[..values.len()].copy_from_slice(values);
+        Ok(num)
+    }
+    fn read_input_registers(&self, _: u16, _: u16, _: &mut [u16]) -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+    fn report_slave_id(&self, _: usize, _: &mut [u8]) -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+    fn write_bit(&self, _: u16, _: bool) -> Result<(), Error> {
+        Err(Error::BadData)
+    }
+    fn write_bits(&self, _: u16, _: u16, _: &[u8]) -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+    fn write_register(&self, _: u16, _: u16) -> Result<(), Error> {
+        Err(Error::BadData)
+    }
+    fn write_registers(&self, address: u16, num: u16, src: &[u16]) -> Result<u16, Error> {
+        self.0.borrow_mut()[address as usize..(address + num) as usize].copy_from_slice(&src[..num as usize]);
+        Ok(num)
+    }
+    fn write_and_read_registers(&self, _: u16, _: u16, _: &[u16], _: u16, _: u16, _: &mut [u16])
+                                -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+    fn mask_write_register(&self, _: u16, _: u16, _: u16) -> Result<(), Error> {
+        Err(Error::BadData)
+    }
+    fn send_raw_request(&self, _: &mut [u8], _: usize) -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+    fn receive_confirmation(&self, _: &mut [u8]) -> Result<u16, Error> {
+        Err(Error::BadData)
+    }
+}
+
+#[test]
+fn provided_functions() {
+    let client = Registers(std::cell::RefCell::new(vec![0; 300]));
+    let values: Vec<u16> = (0..300).collect();
+
+    client.write_registers_range(0, &values).unwrap();
+    assert_eq!(client.read_registers_range(0, 300).unwrap(), values);
+    assert_eq!(client.read_registers_vec(10, 2).unwrap(), vec![10, 11]);
+    match client.read_registers_range(200, 200) {
+        Err(Error::ChunkFailed { address: 200, count: 125, .. }) => {},
+        other => panic!("expected failed chunk, got {:?}", other),
+    }
+}
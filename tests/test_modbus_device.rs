@@ -0,0 +1,57 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::frame::tcp::{Adu, Decoder};
+use libmodbus_rs::{Bus, Modbus, ModbusClient, ModbusTCP};
+use std::io::Write;
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+
+/// Answers every read of holding registers with the unit id of the request
+fn start_unit_id_server(port: u16) {
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut decoder = Decoder::new();
+        while let Ok(Some(frame)) = decoder.read_frame(&mut stream) {
+            let request = Adu::decode(&frame).unwrap();
+            let count = request.pdu[4] as usize;
+            let mut pdu = vec![0x03, (count * 2) as u8];
+            for _ in 0..count {
+                pdu.extend_from_slice(&[0, request.unit_id]);
+            }
+            let response = Adu::new(request.transaction_id, request.unit_id, &pdu).encode().unwrap();
+            stream.write_all(&response).unwrap();
+        }
+    });
+    thread::sleep(Duration::from_millis(200));
+}
+
+#[test]
+fn devices_share_bus() {
+    let port = 1551;
+    start_unit_id_server(port);
+    let bus = Bus::new(Modbus::new_tcp("127.0.0.1", port as i32).unwrap().connect().unwrap());
+
+    let threads: Vec<_> = (1..5).map(|slave| {
+                                    let device = bus.device(slave);
+                                    thread::spawn(move || {
+                                        for _ in 0..50 {
+                                            assert_eq!(device.read_registers_vec(0, 2).unwrap(),
+                                                       vec![u16::from(slave); 2]);
+                                        }
+                                    })
+                                })
+                                .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let device = bus.device(9);
+    assert_eq!(device.get_slave(), 9);
+    assert_eq!(device.read_registers_range(0, 130).unwrap(), vec![9; 130]);
+    bus.with_connection(|connection| connection.set_slave(3)).unwrap();
+    assert_eq!(device.read_registers_vec(0, 1).unwrap(), vec![9]);
+    assert_eq!(device.bus().with_connection(|connection| connection.get_slave()).unwrap(), 9);
+}
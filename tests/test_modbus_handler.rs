@@ -0,0 +1,208 @@
+extern crate libc;
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Exception, Modbus, ModbusClient, ModbusRTU, ModbusServer, ModbusTCP, RequestHandler};
+use libmodbus_rs::frame::rtu;
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+
+/// 16 registers and 16 coils, the register 0 counts the reads
+#[derive(Default)]
+struct Device {
+    registers: [u16; 16],
+    coils: [bool; 16],
+    reads: u16,
+}
+
+impl Device {
+    fn range(address: u16, count: usize) -> Result<std::ops::Range<usize>, Exception> {
+        let start = address as usize;
+        if start + count > 16 {
+            return Err(Exception::IllegalDataAddress);
+        }
+        Ok(start..start + count)
+    }
+}
+
+impl RequestHandler for Device {
+    fn read_coils(&mut self, address: u16, count: u16) -> Result<Vec<bool>, Exception> {
+        Ok(self.coils[Device::range(address, count as usize)?].to_vec())
+    }
+
+    fn read_holding_registers(&mut self, address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+        self.reads += 1;
+        self.registers[0] = self.reads;
+        Ok(self.registers[Device::range(address, count as usize)?].to_vec())
+    }
+
+    fn read_input_registers(&mut self, _address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+        // a broken handler, one value too much
+        Ok(vec![0; count as usize + 1])
+    }
+
+    fn write_single_coil(&mut self, address: u16, value: bool) -> Result<(), Exception> {
+        self.coils[Device::range(address, 1)?.start] = value;
+        Ok(())
+    }
+
+    fn write_single_register(&mut self, address: u16, value: u16) -> Result<(), Exception> {
+        self.registers[Device::range(address, 1)?.start] = value;
+        Ok(())
+    }
+
+    fn write_multiple_coils(&mut self, address: u16, values: &[bool]) -> Result<(), Exception> {
+        self.coils[Device::range(address, values.len())?].copy_from_slice(values);
+        Ok(())
+    }
+
+    fn write_multiple_registers(&mut self, address: u16, values: &[u16]) -> Result<(), Exception> {
+        self.registers[Device::range(address, values.len())?].copy_from_slice(values);
+        Ok(())
+    }
+
+    fn report_slave_id(&mut self) -> Result<Vec<u8>, Exception> {
+        Ok(vec![0x2A, 0xFF, b'R', b's'])
+    }
+
+    fn mask_write_register(&mut self, address: u16, and_mask: u16, or_mask: u16) -> Result<(), Exception> {
+        let register = &mut self.registers[Device::range(address, 1)?.start];
+        *register = (*register & and_mask) | (or_mask & !and_mask);
+        Ok(())
+    }
+
+    fn write_and_read_registers(&mut self, read_address: u16, read_count: u16, write_address: u16, values: &[u16])
+                                -> Result<Vec<u16>, Exception> {
+        let read = Device::range(read_address, read_count as usize)?;
+        self.write_multiple_registers(write_address, values)?;
+        Ok(self.registers[read].to_vec())
+    }
+}
+
+fn start_server(port: i32, device: Arc<Mutex<Device>>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let modbus = Modbus::new_tcp("127.0.0.1", port).expect("Could not create TCP Server context");
+        let listener = modbus.tcp_listen(1).expect("Could not listen to TCP socket");
+        let modbus = listener.tcp_accept().expect("Could not accept connection");
+        let mut query = vec![0u8; Modbus::TCP_MAX_ADU_LENGTH];
+
+        while let Ok(rc) = modbus.receive(&mut query) {
+            let mut device = device.lock().unwrap();
+            modbus.reply_with(&query, rc, &mut *device).expect("Could not reply");
+        }
+    })
+}
+
+fn expect_exception<T: std::fmt::Debug>(result: Result<T, Error>, exception: Exception) {
+    match result {
+        Err(Error::Exception(ref received)) if *received == exception => {},
+        other => panic!("expected {:?}, got {:?}", exception, other),
+    }
+}
+
+#[test]
+fn reply_with_handler() {
+    let port = 1524;
+    let device = Arc::new(Mutex::new(Device::default()));
+    let server_thread = start_server(port, device.clone());
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+
+    // values are computed on each request
+    assert_eq!(client.read_registers_vec(0, 2).unwrap(), vec![1, 0]);
+    assert_eq!(client.read_registers_vec(0, 1).unwrap(), vec![2]);
+
+    client.write_register(3, 0x1234).unwrap();
+    client.write_registers(4, 3, &[4, 5, 6]).unwrap();
+    client.mask_write_register(3, 0x00FF, 0x0F00).unwrap();
+    assert_eq!(device.lock().unwrap().registers[3..7], [0x0F34, 4, 5, 6]);
+
+    let mut dest = vec![0u16; 3];
+    client.write_and_read_registers(5, 2, &[50, 60], 4, 3, &mut dest).unwrap();
+    assert_eq!(dest, vec![4, 50, 60]);
+
+    client.write_bit(1, true).unwrap();
+    client.write_bits(8, 3, &[1, 0, 1]).unwrap();
+    assert_eq!(client.read_bits_vec(0, 11).unwrap(),
+               vec![false, true, false, false, false, false, false, false, true, false, true]);
+
+    let mut slave_id = vec![0u8; Modbus::MAX_PDU_LENGTH];
+    let len = client.report_slave_id(Modbus::MAX_PDU_LENGTH, &mut slave_id).unwrap();
+    assert_eq!(&slave_id[..len as usize], &[0x2A, 0xFF, b'R', b's']);
+
+    drop(client);
+    let _ = server_thread.join();
+}
+
+#[test]
+fn reply_with_exceptions() {
+    let port = 1525;
+    let server_thread = start_server(port, Arc::new(Mutex::new(Device::default())));
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+
+    // returned by the handler
+    expect_exception(client.read_registers_vec(10, 10), Exception::IllegalDataAddress);
+    expect_exception(client.write_register(16, 1), Exception::IllegalDataAddress);
+    // not implemented by the handler
+    expect_exception(client.read_input_bits_vec(0, 1), Exception::IllegalFunction);
+    // wrong number of values returned by the handler
+    expect_exception(client.read_input_registers_vec(0, 2), Exception::SlaveOrServerFailure);
+    // the connection is still usable after an exception
+    assert_eq!(client.read_registers_vec(1, 1).unwrap(), vec![0]);
+
+    // unknown function code
+    let mut raw_request = vec![0xFF, 0x42, 0x00];
+    let request_len = raw_request.len();
+    client.send_raw_request(&mut raw_request, request_len).unwrap();
+    let mut response = vec![0u8; Modbus::MAX_ADU_LENGTH];
+    expect_exception(client.receive_confirmation(&mut response), Exception::IllegalFunction);
+
+    drop(client);
+    let _ = server_thread.join();
+}
+
+/// Opens a pseudo-terminal, returns the master file descriptor and the path of the slave device
+fn open_pty() -> (i32, String) {
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(master >= 0, "could not open pseudo-terminal");
+        assert_eq!(libc::grantpt(master), 0);
+        assert_eq!(libc::unlockpt(master), 0);
+        let path = CStr::from_ptr(libc::ptsname(master)).to_string_lossy().into_owned();
+        (master, path)
+    }
+}
+
+#[test]
+fn reply_with_rtu() {
+    let (master, path) = open_pty();
+    let mut server = Modbus::new_rtu(&path, 115200, 'N', 8, 1).unwrap();
+    server.set_slave(17).unwrap();
+    let server = server.connect().expect("could not connect to pseudo-terminal");
+
+    // the client side of the serial line is the master of the pseudo-terminal
+    let request = rtu::Adu::new(17, &[0x06, 0x00, 0x02, 0xAB, 0xCD]).encode().unwrap();
+    assert_eq!(unsafe { libc::write(master, request.as_ptr() as *const libc::c_void, request.len()) },
+               request.len() as isize);
+
+    let mut query = vec![0u8; Modbus::RTU_MAX_ADU_LENGTH];
+    let rc = server.receive(&mut query).unwrap();
+    let mut device = Device::default();
+    server.reply_with(&query, rc, &mut device).unwrap();
+    assert_eq!(device.registers[2], 0xABCD);
+
+    thread::sleep(Duration::from_millis(50));
+    let mut response = vec![0u8; Modbus::RTU_MAX_ADU_LENGTH];
+    let len = unsafe { libc::read(master, response.as_mut_ptr() as *mut libc::c_void, response.len()) };
+    assert!(len > 0);
+    // a write single register is answered with an echo of the request
+    assert_eq!(&response[..len as usize], &request[..]);
+
+    drop(server);
+    unsafe { libc::close(master) };
+}
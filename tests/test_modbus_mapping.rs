@@ -1,7 +1,9 @@
 #![allow(unused_imports)]
 extern crate libmodbus_rs;
 
-use libmodbus_rs::{Modbus, ModbusMapping, ModbusTCP};
+use libmodbus_rs::{Exception, Modbus, ModbusMapping, ModbusTCP, RequestHandler};
+use std::sync::Arc;
+use std::thread;
 
 
 #[test]
@@ -15,9 +17,8 @@ fn new_start_address() {
 }
 
 #[test]
-#[ignore]
 fn free() {
-    let mut modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    let modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     modbus.free();
 }
 
@@ -25,54 +26,121 @@ fn free() {
 fn get_bits() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_bits(), [0u8, 0, 0, 0, 0]);
+    assert_eq!(modbus_mapping.lock().get_bits(), [0u8, 0, 0, 0, 0]);
 }
 
 #[test]
 fn get_bits_mut() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_bits_mut(), [0u8, 0, 0, 0, 0]);
+    assert_eq!(modbus_mapping.lock().get_bits_mut(), [0u8, 0, 0, 0, 0]);
 }
 
 #[test]
 fn get_input_bit() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_input_bits(), [0u8, 0, 0, 0, 0])
+    assert_eq!(modbus_mapping.lock().get_input_bits(), [0u8, 0, 0, 0, 0]);
 }
 
 #[test]
 fn get_input_bits_mut() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_input_bits_mut(), [0u8, 0, 0, 0, 0])
+    assert_eq!(modbus_mapping.lock().get_input_bits_mut(), [0u8, 0, 0, 0, 0]);
 }
 
 #[test]
 fn get_input_registers() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_input_registers(), [0u16, 0, 0, 0, 0])
+    assert_eq!(modbus_mapping.lock().get_input_registers(), [0u16, 0, 0, 0, 0]);
 }
 
 #[test]
 fn get_input_registers_mut() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_input_registers_mut(), [0u16, 0, 0, 0, 0])
+    assert_eq!(modbus_mapping.lock().get_input_registers_mut(), [0u16, 0, 0, 0, 0]);
 }
 
 #[test]
 fn get_registers() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_input_registers(), [0u16, 0, 0, 0, 0])
+    assert_eq!(modbus_mapping.lock().get_input_registers(), [0u16, 0, 0, 0, 0]);
 }
 
 #[test]
 fn get_registers_mut() {
     let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
 
-    assert_eq!(modbus_mapping.get_input_registers_mut(), [0u16, 0, 0, 0, 0])
+    assert_eq!(modbus_mapping.lock().get_input_registers_mut(), [0u16, 0, 0, 0, 0]);
+}
+
+#[test]
+fn empty_tables() {
+    let modbus_mapping = ModbusMapping::new_start_address(0, 0, 0, 0, 10000, 10, 0, 0).unwrap();
+    let mut mapping = modbus_mapping.lock();
+
+    assert!(mapping.get_bits().is_empty());
+    assert!(mapping.get_input_bits_mut().is_empty());
+    assert!(mapping.get_input_registers().is_empty());
+    assert_eq!(mapping.get_registers().len(), 10);
+}
+
+#[test]
+fn lock_write() {
+    let modbus_mapping = ModbusMapping::new(5, 5, 5, 5).unwrap();
+    {
+        let mut mapping = modbus_mapping.lock();
+        mapping.get_bits_mut()[1] = 1;
+        mapping.get_registers_mut()[4] = 0xBEEF;
+    }
+
+    let mapping = modbus_mapping.lock();
+    assert_eq!(mapping.get_bits(), [0u8, 1, 0, 0, 0]);
+    assert_eq!(mapping.get_registers(), [0u16, 0, 0, 0, 0xBEEF]);
+}
+
+#[test]
+fn lock_from_threads() {
+    let modbus_mapping = Arc::new(ModbusMapping::new(0, 0, 1, 0).unwrap());
+
+    let threads: Vec<_> = (0..4).map(|_| {
+        let modbus_mapping = modbus_mapping.clone();
+        thread::spawn(move || {
+            for _ in 0..1000 {
+                modbus_mapping.lock().get_registers_mut()[0] += 1;
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(modbus_mapping.lock().get_registers(), [4000u16]);
+}
+
+#[test]
+fn request_handler() {
+    let mapping = ModbusMapping::new_start_address(10, 4, 0, 2, 100, 4, 0, 0).unwrap();
+    let mut guard = mapping.lock();
+    guard.get_input_bits_mut()[1] = 1;
+
+    guard.write_multiple_coils(11, &[true, true]).unwrap();
+    guard.write_single_coil(12, false).unwrap();
+    assert_eq!(guard.read_coils(10, 4).unwrap(), vec![false, true, false, false]);
+    assert_eq!(guard.read_discrete_inputs(0, 2).unwrap(), vec![false, true]);
+
+    guard.write_multiple_registers(100, &[0x00FF, 2]).unwrap();
+    guard.mask_write_register(100, 0x0F0F, 0xF0F0).unwrap();
+    assert_eq!(guard.write_and_read_registers(100, 4, 103, &[4]).unwrap(), vec![0xF0FF, 2, 0, 4]);
+    assert_eq!(guard.get_registers(), [0xF0FF, 2, 0, 4]);
+
+    // addresses are checked against start address and size of the tables
+    assert_eq!(guard.read_coils(9, 1), Err(Exception::IllegalDataAddress));
+    assert_eq!(guard.read_holding_registers(101, 4), Err(Exception::IllegalDataAddress));
+    assert_eq!(guard.write_single_register(104, 1), Err(Exception::IllegalDataAddress));
+    assert_eq!(guard.read_input_registers(0, 1), Err(Exception::IllegalDataAddress));
 }
@@ -0,0 +1,159 @@
+extern crate libc;
+extern crate libmodbus_rs;
+
+use libmodbus_rs::frame::tcp::{Adu, Decoder};
+use libmodbus_rs::{Error, Exception, Modbus, ModbusMapping, ModbusTCP, PipelinedTcpClient, Request, Response,
+                   TcpServer};
+use std::io::{self, Write};
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+
+#[test]
+fn pipelined_requests() {
+    let port = 1541;
+    let mapping = Arc::new(ModbusMapping::new(0, 0, 100, 0).unwrap());
+    for (i, register) in mapping.lock().get_registers_mut().iter_mut().enumerate() {
+        *register = i as u16;
+    }
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(1).unwrap();
+        let _ = TcpServer::new(listener).serve(&mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let mut client = PipelinedTcpClient::connect(("127.0.0.1", port as u16)).expect("could not connect");
+    assert!(client.receive().unwrap().is_none());
+
+    let mut sent = Vec::new();
+    for address in (0..100).step_by(10) {
+        sent.push(client.send(Request::ReadHoldingRegisters { address, count: 10 }).unwrap());
+    }
+    sent.push(client.send(Request::WriteSingleRegister { address: 0, value: 42 }).unwrap());
+    sent.push(client.send(Request::ReadHoldingRegisters { address: 95, count: 10 }).unwrap());
+    assert_eq!(client.in_flight(), 12);
+
+    let mut received = Vec::new();
+    while let Some(confirmation) = client.receive().unwrap() {
+        match (&confirmation.request, confirmation.result) {
+            (&Request::ReadHoldingRegisters { address: 95, .. }, result) => {
+                match result {
+                    Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+                    other => panic!("expected illegal data address, got {:?}", other),
+                }
+            },
+            (&Request::ReadHoldingRegisters { address, count }, result) => {
+                assert_eq!(result.unwrap(), Response::Registers((address..address + count).collect()));
+            },
+            (_, result) => assert_eq!(result.unwrap(), Response::Written),
+        }
+        received.push(confirmation.transaction_id);
+    }
+    // the server answers in order
+    assert_eq!(received, sent);
+    assert_eq!(client.in_flight(), 0);
+}
+
+#[test]
+fn out_of_order_and_timeout() {
+    let port = 1542;
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut decoder = Decoder::new();
+        let mut transaction_ids = Vec::new();
+        for _ in 0..3 {
+            let request = decoder.read_frame(&mut stream).unwrap().unwrap();
+            transaction_ids.push(Adu::decode(&request).unwrap().transaction_id);
+        }
+        // answer the last request first and the second one too late
+        for &(index, value) in [(2usize, 3u8), (0, 1), (1, 2)].iter() {
+            if index == 1 {
+                thread::sleep(Duration::from_millis(300));
+            }
+            let response = Adu::new(transaction_ids[index], 0xFF, &[0x03, 0x02, 0x00, value]).encode().unwrap();
+            stream.write_all(&response).unwrap();
+        }
+        thread::sleep(Duration::from_millis(500));
+    });
+
+    let mut client = PipelinedTcpClient::connect(("127.0.0.1", port)).expect("could not connect");
+    client.set_response_timeout(Duration::from_millis(100));
+    assert_eq!(client.get_response_timeout(), Duration::from_millis(100));
+
+    let first = client.send(Request::ReadHoldingRegisters { address: 1, count: 1 }).unwrap();
+    let second = client.send(Request::ReadHoldingRegisters { address: 2, count: 1 }).unwrap();
+    let third = client.send_with_timeout(Request::ReadHoldingRegisters { address: 3, count: 1 },
+                                         Duration::from_secs(1))
+        .unwrap();
+
+    let confirmation = client.receive().unwrap().unwrap();
+    assert_eq!(confirmation.transaction_id, third);
+    assert_eq!(confirmation.result.unwrap(), Response::Registers(vec![3]));
+
+    let confirmation = client.receive().unwrap().unwrap();
+    assert_eq!(confirmation.transaction_id, first);
+    assert_eq!(confirmation.result.unwrap(), Response::Registers(vec![1]));
+
+    let confirmation = client.receive().unwrap().unwrap();
+    assert_eq!(confirmation.transaction_id, second);
+    assert_eq!(confirmation.request, Request::ReadHoldingRegisters { address: 2, count: 1 });
+    match confirmation.result {
+        Err(Error::Timeout) => {},
+        other => panic!("expected timeout, got {:?}", other),
+    }
+
+    // the late response is discarded
+    assert!(client.receive().unwrap().is_none());
+}
+
+fn set_buffer_size<S: AsRawFd>(socket: &S, option: libc::c_int) {
+    let size: libc::c_int = 4096;
+    unsafe {
+        libc::setsockopt(socket.as_raw_fd(), libc::SOL_SOCKET, option, &size as *const _ as *const libc::c_void,
+                         mem::size_of::<libc::c_int>() as libc::socklen_t);
+    }
+}
+
+#[test]
+fn stalled_server_breaks_connection() {
+    let port = 1554;
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    set_buffer_size(&listener, libc::SO_RCVBUF);
+    // the server never reads, the small buffers fill up until a request can not be written completely
+    let server = thread::spawn(move || listener.accept().unwrap());
+
+    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    set_buffer_size(&stream, libc::SO_SNDBUF);
+    let mut client = PipelinedTcpClient::from_stream(stream).unwrap();
+
+    let values = vec![0; Modbus::MAX_WRITE_REGISTERS as usize];
+    let mut requests = 0;
+    loop {
+        match client.send_with_timeout(Request::WriteMultipleRegisters { address: 0, values: values.clone() },
+                                       Duration::from_millis(50))
+        {
+            Ok(_) => requests += 1,
+            Err(Error::IoError(ref err)) if err.kind() == io::ErrorKind::WouldBlock ||
+                                            err.kind() == io::ErrorKind::TimedOut => break,
+            Err(err) => panic!("expected write timeout, got {:?}", err),
+        }
+        assert!(requests < 1000, "buffers did not fill up");
+    }
+    assert!(client.is_broken());
+
+    // nothing is sent or received after the partial frame
+    match client.send(Request::ReadHoldingRegisters { address: 0, count: 1 }) {
+        Err(Error::IoError(ref err)) if err.kind() == io::ErrorKind::BrokenPipe => {},
+        other => panic!("expected broken connection, got {:?}", other),
+    }
+    match client.receive() {
+        Err(Error::IoError(ref err)) if err.kind() == io::ErrorKind::BrokenPipe => {},
+        other => panic!("expected broken connection, got {:?}", other),
+    }
+    drop(server.join().unwrap());
+}
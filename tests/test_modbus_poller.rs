@@ -0,0 +1,137 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Change, DataType, Error, Modbus, ModbusMapping, ModbusTCP, PollGroup, Poller, Quality, RegisterMap,
+                   RegisterPoint, ShutdownHandle, Table, TcpServer, Value};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+
+fn start_server(port: i32, mapping: Arc<ModbusMapping>) -> (ShutdownHandle, thread::JoinHandle<()>) {
+    let mut server = TcpServer::new(Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(1).unwrap());
+    let handle = server.shutdown_handle();
+    let server = thread::spawn(move || {
+        let _ = server.serve(&mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+    (handle, server)
+}
+
+fn group(interval: Duration) -> PollGroup {
+    let map = RegisterMap::new(vec![RegisterPoint::new("level", Table::InputRegisters, 0, DataType::U16),
+                                    RegisterPoint::new("alarm", Table::DiscreteInputs, 0, DataType::Bool)]);
+    let mut group = PollGroup::new(map.unwrap(), interval);
+    group.set_point_deadband("level", 10.0).unwrap();
+    group
+}
+
+#[test]
+fn point_deadband() {
+    let mut group = group(Duration::from_secs(1));
+    match group.set_point_deadband("flow", 1.0) {
+        Err(Error::InvalidPoint { ref name, reason: "no such point" }) if name == "flow" => {},
+        other => panic!("expected unknown point, got {:?}", other),
+    }
+    assert_eq!(group.get_interval(), Duration::from_secs(1));
+    assert_eq!(group.map().points().len(), 2);
+}
+
+#[test]
+fn report_changes() {
+    let port = 1548;
+    let mapping = Arc::new(ModbusMapping::new(0, 1, 0, 1).unwrap());
+    mapping.lock().get_input_registers_mut()[0] = 100;
+    let _server = start_server(port, mapping.clone());
+
+    let mut poller = Poller::new();
+    let device = poller.add_device(Modbus::new_tcp("127.0.0.1", port).unwrap().connect().unwrap());
+    let levels = poller.add_group(device, group(Duration::from_secs(0)));
+    let slow = poller.add_group(device, group(Duration::from_secs(60)));
+
+    let called = Arc::new(Mutex::new(Vec::new()));
+    let callback = called.clone();
+    poller.on_change(move |change| callback.lock().unwrap().push(change.clone()));
+    let changes = poller.subscribe();
+    let values = || changes.try_iter().map(|change: Change| (change.group, change.name, change.sample.value)).collect();
+
+    poller.poll();
+    let reported: Vec<_> = values();
+    assert_eq!(reported,
+               vec![(levels, "level".to_owned(), Some(Value::Unsigned(100))),
+                    (levels, "alarm".to_owned(), Some(Value::Bool(false))),
+                    (slow, "level".to_owned(), Some(Value::Unsigned(100))),
+                    (slow, "alarm".to_owned(), Some(Value::Bool(false)))]);
+    assert_eq!(called.lock().unwrap().len(), 4);
+
+    // within the deadband of the reported value
+    mapping.lock().get_input_registers_mut()[0] = 106;
+    poller.poll();
+    mapping.lock().get_input_registers_mut()[0] = 110;
+    poller.poll();
+    assert_eq!(values(), vec![]);
+    assert_eq!(poller.sample(levels, "level").unwrap().value, Some(Value::Unsigned(100)));
+
+    mapping.lock().get_input_registers_mut()[0] = 111;
+    mapping.lock().get_input_bits_mut()[0] = 1;
+    poller.poll();
+    assert_eq!(values(),
+               vec![(levels, "level".to_owned(), Some(Value::Unsigned(111))),
+                    (levels, "alarm".to_owned(), Some(Value::Bool(true)))]);
+    assert_eq!(called.lock().unwrap().len(), 6);
+
+    let sample = poller.sample(levels, "alarm").unwrap();
+    assert_eq!((sample.value, sample.quality), (Some(Value::Bool(true)), Quality::Good));
+    assert_eq!(poller.sample(slow, "level").unwrap().value, Some(Value::Unsigned(100)));
+    assert!(poller.sample(levels, "flow").is_none());
+    assert!(poller.last_error(levels).is_none());
+}
+
+#[test]
+fn mark_stale() {
+    let port = 1549;
+    let mapping = Arc::new(ModbusMapping::new(0, 1, 0, 1).unwrap());
+    mapping.lock().get_input_registers_mut()[0] = 7;
+    let (shutdown, server) = start_server(port, mapping);
+
+    let mut poller = Poller::new();
+    let device = poller.add_device(Modbus::new_tcp("127.0.0.1", port).unwrap().connect().unwrap());
+    poller.device_mut(device).unwrap().set_response_timeout_duration(Duration::from_millis(100)).unwrap();
+    let group = poller.add_group(device, group(Duration::from_secs(0)));
+    let missing = RegisterMap::new(vec![RegisterPoint::new("flow", Table::InputRegisters, 5, DataType::U16)]).unwrap();
+    let missing = poller.add_group(device, PollGroup::new(missing, Duration::from_secs(0)));
+    let changes = poller.subscribe();
+
+    poller.poll();
+    assert_eq!(changes.try_iter().count(), 3);
+    let flow = poller.sample(missing, "flow").unwrap();
+    assert_eq!((flow.value, flow.quality), (None, Quality::Stale));
+    match poller.last_error(missing) {
+        Some(&Error::ChunkFailed { address: 5, count: 1, .. }) => {},
+        other => panic!("expected failed chunk, got {:?}", other),
+    }
+
+    shutdown.shutdown();
+    server.join().unwrap();
+    poller.poll();
+    let stale: Vec<Change> = changes.try_iter().collect();
+    assert_eq!(stale.iter().map(|change| change.name.as_str()).collect::<Vec<_>>(), vec!["level", "alarm"]);
+    assert!(stale.iter().all(|change| change.sample.quality == Quality::Stale));
+    assert_eq!(poller.sample(group, "level").unwrap().value, Some(Value::Unsigned(7)));
+    assert!(poller.last_error(group).is_some());
+
+    // stale points are reported once
+    poller.poll();
+    assert_eq!(changes.try_iter().count(), 0);
+}
+
+#[test]
+fn run_until_shutdown() {
+    let mut poller: Poller = Poller::new();
+    assert_eq!(poller.poll(), Duration::MAX);
+    let shutdown = ShutdownHandle::new();
+    let handle = shutdown.clone();
+    let runner = thread::spawn(move || poller.run(&handle));
+    thread::sleep(Duration::from_millis(50));
+    shutdown.shutdown();
+    runner.join().unwrap();
+}
@@ -0,0 +1,111 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Modbus, ModbusClient, ModbusMapping, ModbusTCP, Point, ReadPlanner, Request, Response,
+                   Table, TcpServer};
+use std::thread;
+use std::time::Duration;
+
+
+#[test]
+fn merge_adjacent_and_overlapping_points() {
+    let points = [Point::new(Table::InputRegisters, 10, 2),
+                  Point::new(Table::InputRegisters, 0, 4),
+                  Point::new(Table::InputRegisters, 2, 4),
+                  Point::new(Table::InputRegisters, 6, 1),
+                  Point::new(Table::DiscreteInputs, 0, 1)];
+    let plan = ReadPlanner::new().plan(&points).unwrap();
+    assert_eq!(plan.requests(),
+               &[Request::ReadDiscreteInputs { address: 0, count: 1 },
+                 Request::ReadInputRegisters { address: 0, count: 7 },
+                 Request::ReadInputRegisters { address: 10, count: 2 }]);
+
+    let values = plan.scatter(&[Response::Bits(vec![true]),
+                                Response::Registers(vec![0, 1, 2, 3, 4, 5, 6]),
+                                Response::Registers(vec![10, 11])])
+                     .unwrap();
+    assert_eq!(values,
+               vec![Response::Registers(vec![10, 11]),
+                    Response::Registers(vec![0, 1, 2, 3]),
+                    Response::Registers(vec![2, 3, 4, 5]),
+                    Response::Registers(vec![6]),
+                    Response::Bits(vec![true])]);
+
+    match plan.scatter(&[Response::Bits(vec![true])]) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+}
+
+#[test]
+fn respect_limits() {
+    let mut planner = ReadPlanner::new();
+    planner.set_max_gap(10);
+    planner.add_forbidden_range(Table::HoldingRegisters, 205, 1);
+
+    // one register per 10 addresses, a request ends before it would exceed 125 registers or cover 205
+    let points: Vec<Point> = (0..30).map(|i| Point::new(Table::HoldingRegisters, i * 10, 1)).collect();
+    let plan = planner.plan(&points).unwrap();
+    assert_eq!(plan.requests(),
+               &[Request::ReadHoldingRegisters { address: 0, count: 121 },
+                 Request::ReadHoldingRegisters { address: 130, count: 71 },
+                 Request::ReadHoldingRegisters { address: 210, count: 81 }]);
+
+    match planner.plan(&[Point::new(Table::HoldingRegisters, 204, 2)]) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    match planner.plan(&[Point::new(Table::HoldingRegisters, 0, Modbus::MAX_READ_REGISTERS as u16 + 1)]) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+    match planner.plan(&[Point::new(Table::Coils, 0xFFFF, 2)]) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+#[test]
+fn reject_empty_points() {
+    let points = [Point::new(Table::InputRegisters, 0, 2), Point::new(Table::InputRegisters, 10, 0)];
+    match ReadPlanner::new().plan(&points) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+}
+
+#[test]
+fn read() {
+    let port = 1545;
+    let mapping = ModbusMapping::new(100, 0, 300, 0).unwrap();
+    for (address, register) in mapping.lock().get_registers_mut().iter_mut().enumerate() {
+        *register = address as u16;
+    }
+    mapping.lock().get_bits_mut()[42] = 1;
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(1).unwrap();
+        let _ = TcpServer::new(listener).serve(&mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let mut points: Vec<Point> = (0..40).map(|i| Point::new(Table::HoldingRegisters, i * 7, 2)).collect();
+    points.push(Point::new(Table::Coils, 40, 3));
+    let mut planner = ReadPlanner::new();
+    planner.set_max_gap(5);
+    let plan = planner.plan(&points).unwrap();
+    assert_eq!(plan.requests().len(), 4);
+
+    let values = plan.read(&client).unwrap();
+    for (point, value) in points[..40].iter().zip(&values) {
+        assert_eq!(*value, Response::Registers(vec![point.address, point.address + 1]));
+    }
+    assert_eq!(values[40], Response::Bits(vec![false, false, true]));
+
+    // the mapping ends at register 300
+    let plan = planner.plan(&[Point::new(Table::HoldingRegisters, 299, 2)]).unwrap();
+    match plan.read(&client) {
+        Err(Error::ChunkFailed { address: 299, count: 2, .. }) => {},
+        other => panic!("expected failed chunk, got {:?}", other),
+    }
+    assert_eq!(client.read_registers_vec(0, 1).unwrap(), vec![0]);
+}
@@ -0,0 +1,146 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Access, DataType, Error, Modbus, ModbusMapping, ModbusTCP, RegisterMap, RegisterPoint, Table,
+                   TcpServer, Value, WordOrder};
+use std::thread;
+use std::time::Duration;
+
+
+fn device_map() -> RegisterMap {
+    let mut voltage = RegisterPoint::new("voltage", Table::InputRegisters, 0, DataType::U16);
+    voltage.scale = 0.1;
+    let mut energy = RegisterPoint::new("energy", Table::InputRegisters, 2, DataType::U64);
+    energy.word_order = WordOrder::LittleEndian;
+    let temperature = RegisterPoint::new("temperature", Table::InputRegisters, 6, DataType::I16);
+    let mut setpoint = RegisterPoint::new("setpoint", Table::HoldingRegisters, 0, DataType::F32);
+    setpoint.access = Access::ReadWrite;
+    let mut limit = RegisterPoint::new("limit", Table::HoldingRegisters, 2, DataType::U16);
+    limit.access = Access::Write;
+    let mut pump = RegisterPoint::new("pump", Table::Coils, 3, DataType::Bool);
+    pump.access = Access::ReadWrite;
+
+    RegisterMap::new(vec![voltage, energy, temperature, setpoint, limit, pump]).unwrap()
+}
+
+#[test]
+fn read_and_write_points() {
+    let port = 1546;
+    let mapping = ModbusMapping::new(10, 0, 10, 10).unwrap();
+    mapping.lock().get_input_registers_mut()[..7].copy_from_slice(&[2304, 0, 0x5678, 0x1234, 0, 0, 0xFFF6]);
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(1).unwrap();
+        let _ = TcpServer::new(listener).serve(&mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let map = device_map();
+    match map.read(&client, "voltage").unwrap() {
+        Value::Float(voltage) => assert!((voltage - 230.4).abs() < 1e-9),
+        other => panic!("expected a float, got {:?}", other),
+    }
+    assert_eq!(map.read(&client, "energy").unwrap(), Value::Unsigned(0x1234_5678));
+    assert_eq!(map.read(&client, "temperature").unwrap(), Value::Signed(-10));
+
+    map.write(&client, "setpoint", Value::Float(21.5)).unwrap();
+    map.write(&client, "limit", Value::Unsigned(500)).unwrap();
+    map.write(&client, "pump", Value::Bool(true)).unwrap();
+    assert_eq!(map.read(&client, "setpoint").unwrap(), Value::Float(21.5));
+    assert_eq!(map.read(&client, "pump").unwrap(), Value::Bool(true));
+
+    let values = map.read_all(&client).unwrap();
+    let names: Vec<&str> = values.iter().map(|&(name, _)| name).collect();
+    assert_eq!(names, vec!["voltage", "energy", "temperature", "setpoint", "pump"]);
+    assert_eq!(values[3].1, Value::Float(21.5));
+
+    match map.read(&client, "limit") {
+        Err(Error::InvalidPoint { ref name, reason: "write only" }) if name == "limit" => {},
+        other => panic!("expected write only point, got {:?}", other),
+    }
+    match map.write(&client, "voltage", Value::Float(1.0)) {
+        Err(Error::InvalidPoint { reason: "read only", .. }) => {},
+        other => panic!("expected read only point, got {:?}", other),
+    }
+    match map.write(&client, "limit", Value::Signed(-1)) {
+        Err(Error::InvalidPoint { reason: "value out of range", .. }) => {},
+        other => panic!("expected value out of range, got {:?}", other),
+    }
+    match map.read(&client, "current") {
+        Err(Error::InvalidPoint { reason: "no such point", .. }) => {},
+        other => panic!("expected unknown point, got {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_points() {
+    let point = RegisterPoint::new("a", Table::HoldingRegisters, 0, DataType::U16);
+    match RegisterMap::new(vec![point.clone(), point]) {
+        Err(Error::InvalidRegisterMap(_)) => {},
+        other => panic!("expected duplicate point, got {:?}", other),
+    }
+
+    let mut input = RegisterPoint::new("b", Table::InputRegisters, 0, DataType::U16);
+    input.access = Access::ReadWrite;
+    let invalid = vec![RegisterPoint::new("a", Table::Coils, 0, DataType::U16),
+                       RegisterPoint::new("a", Table::HoldingRegisters, 0, DataType::Bool),
+                       RegisterPoint::new("a", Table::HoldingRegisters, 0xFFFE, DataType::U64),
+                       input];
+    for point in invalid {
+        match RegisterMap::new(vec![point]) {
+            Err(Error::InvalidRegisterMap(_)) => {},
+            other => panic!("expected invalid point, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn from_toml() {
+    let document = r#"
+        [[points]]
+        name = "energy"
+        table = "input"
+        address = 2
+        type = "u64"
+        word_order = "little_endian"
+
+        [[points]]
+        name = "setpoint"
+        table = "holding"
+        address = 0
+        type = "f32"
+        scale = 0.5
+        unit = "°C"
+        access = "read_write"
+    "#;
+    let map = RegisterMap::from_toml(document).unwrap();
+    assert_eq!(map.points().len(), 2);
+    let setpoint = map.point("setpoint").unwrap();
+    assert_eq!(setpoint.scale, 0.5);
+    assert_eq!(setpoint.unit, Some("°C".to_owned()));
+    assert_eq!(setpoint.access, Access::ReadWrite);
+    assert_eq!(map.point("energy").unwrap().access, Access::Read);
+
+    match RegisterMap::from_toml("[[points]]\nname = \"x\"\ntable = \"holding\"\naddress = 0\ntype = \"u128\"") {
+        Err(Error::InvalidRegisterMap(_)) => {},
+        other => panic!("expected invalid register map, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn from_json() {
+    let document = r#"{ "points": [
+        { "name": "pump", "table": "coil", "address": 3, "type": "bool", "access": "write" },
+        { "name": "level", "table": "discrete", "address": 0, "type": "bool" }
+    ] }"#;
+    let map = RegisterMap::from_json(document).unwrap();
+    assert_eq!(map.point("pump").unwrap().access, Access::Write);
+    assert_eq!(map.point("level").unwrap().table, Table::DiscreteInputs);
+
+    let document = r#"{ "points": [ { "name": "pump", "table": "input", "address": 0, "type": "bool" } ] }"#;
+    match RegisterMap::from_json(document) {
+        Err(Error::InvalidRegisterMap(_)) => {},
+        other => panic!("expected invalid register map, got {:?}", other),
+    }
+}
@@ -0,0 +1,139 @@
+#![cfg(feature = "derive")]
+
+extern crate libmodbus_rs;
+
+use libmodbus_rs::prelude::{get_float_abcd, get_float_badc, get_float_cdab, get_float_dcba};
+use libmodbus_rs::{Error, Exception, Modbus, ModbusMapping, ModbusRegisters, ModbusTCP, Point, Table, TcpServer};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+
+#[derive(Clone, Debug, PartialEq, ModbusRegisters)]
+struct Drive {
+    #[modbus(coil = 0)]
+    enabled: bool,
+    #[modbus(discrete = 1)]
+    fault: bool,
+    #[modbus(holding = 0, ty = "f32_badc")]
+    speed: f32,
+    #[modbus(holding = 2)]
+    mode: u16,
+    #[modbus(input = 0)]
+    temperature: i16,
+    #[modbus(input = 1, ty = "u64_badcfehg")]
+    hours: u64,
+    #[modbus(input = 5, byte_order = "little_endian")]
+    position: i32,
+}
+
+#[derive(Debug, ModbusRegisters)]
+struct Floats {
+    #[modbus(holding = 0, ty = "f32_abcd")]
+    abcd: f32,
+    #[modbus(holding = 0, ty = "f32_badc")]
+    badc: f32,
+    #[modbus(holding = 0, ty = "f32_cdab")]
+    cdab: f32,
+    #[modbus(holding = 0, ty = "f32_dcba")]
+    dcba: f32,
+    #[modbus(holding = 0, byte_order = "little_endian")]
+    byte_order: f32,
+}
+
+fn drive() -> Drive {
+    Drive {
+        enabled: true,
+        fault: true,
+        speed: 1450.5,
+        mode: 3,
+        temperature: -12,
+        hours: 0x0102_0304_0506_0708,
+        position: -100_000,
+    }
+}
+
+#[test]
+fn points() {
+    assert_eq!(Drive::POINTS,
+               &[Point::new(Table::Coils, 0, 1),
+                 Point::new(Table::DiscreteInputs, 1, 1),
+                 Point::new(Table::HoldingRegisters, 0, 2),
+                 Point::new(Table::HoldingRegisters, 2, 1),
+                 Point::new(Table::InputRegisters, 0, 1),
+                 Point::new(Table::InputRegisters, 1, 4),
+                 Point::new(Table::InputRegisters, 5, 2)]);
+}
+
+#[test]
+fn orders_match_prelude() {
+    let mapping = ModbusMapping::new(0, 0, 2, 0).unwrap();
+    let registers = [0x2000, 0x47F1];
+    mapping.lock().get_registers_mut().copy_from_slice(&registers);
+
+    let floats = Floats::from_mapping(&mapping).unwrap();
+    assert_eq!(floats.abcd, get_float_abcd(&registers));
+    assert_eq!(floats.badc, get_float_badc(&registers));
+    assert_eq!(floats.cdab, get_float_cdab(&registers));
+    assert_eq!(floats.dcba, get_float_dcba(&registers));
+    assert_eq!(floats.byte_order, floats.cdab);
+    assert_eq!(floats.badc, 123456.0);
+}
+
+#[test]
+fn mapping_round_trip() {
+    let mapping = ModbusMapping::new(1, 2, 3, 7).unwrap();
+    drive().to_mapping(&mapping).unwrap();
+    {
+        let guard = mapping.lock();
+        assert_eq!(guard.get_bits(), [1]);
+        assert_eq!(guard.get_input_bits(), [0, 1]);
+        let speed = 1450.5f32.to_bits();
+        assert_eq!(guard.get_registers(), [speed as u16, (speed >> 16) as u16, 3]);
+        let position = (-100_000i32) as u32;
+        assert_eq!(guard.get_input_registers(),
+                   [(-12i16) as u16,
+                    0x0708,
+                    0x0506,
+                    0x0304,
+                    0x0102,
+                    ((position >> 16) as u16).swap_bytes(),
+                    (position as u16).swap_bytes()]);
+    }
+    assert_eq!(Drive::from_mapping(&mapping).unwrap(), drive());
+
+    let small = ModbusMapping::new(1, 2, 3, 6).unwrap();
+    match drive().to_mapping(&small) {
+        Err(Error::Exception(Exception::IllegalDataAddress)) => {},
+        other => panic!("expected illegal data address, got {:?}", other),
+    }
+    assert_eq!(small.lock().get_bits(), [0]);
+}
+
+#[test]
+fn read_and_write_device() {
+    let port = 1547;
+    let mapping = Arc::new(ModbusMapping::new(1, 2, 3, 7).unwrap());
+    drive().to_mapping(&mapping).unwrap();
+    let server_mapping = mapping.clone();
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(1).unwrap();
+        let _ = TcpServer::new(listener).serve(&server_mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let client = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let mut device = Drive::read_from(&client).unwrap();
+    assert_eq!(device, drive());
+
+    device.enabled = false;
+    device.speed = -1.25;
+    device.mode = 7;
+    // fields of read only tables are not written
+    device.temperature = 40;
+    device.write_to(&client).unwrap();
+
+    let expected = Drive { enabled: false, speed: -1.25, mode: 7, ..drive() };
+    assert_eq!(Drive::from_mapping(&mapping).unwrap(), expected);
+    assert_eq!(Drive::read_from(&client).unwrap(), expected);
+}
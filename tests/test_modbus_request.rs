@@ -0,0 +1,150 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Indication, Modbus, ModbusClient, ModbusServer, ModbusTCP, Request};
+use std::thread;
+use std::time::Duration;
+
+
+#[test]
+fn from_pdu_reads() {
+    assert_eq!(Request::from_pdu(&[0x01, 0x00, 0x13, 0x00, 0x25]).unwrap(),
+               Request::ReadCoils { address: 0x13, count: 0x25 });
+    assert_eq!(Request::from_pdu(&[0x02, 0x00, 0xC4, 0x00, 0x16]).unwrap(),
+               Request::ReadDiscreteInputs { address: 0xC4, count: 0x16 });
+    assert_eq!(Request::from_pdu(&[0x03, 0x00, 0x6B, 0x00, 0x03]).unwrap(),
+               Request::ReadHoldingRegisters { address: 0x6B, count: 3 });
+    assert_eq!(Request::from_pdu(&[0x04, 0x00, 0x08, 0x00, 0x01]).unwrap(),
+               Request::ReadInputRegisters { address: 8, count: 1 });
+    assert_eq!(Request::from_pdu(&[0x11]).unwrap(), Request::ReportSlaveId);
+}
+
+#[test]
+fn from_pdu_writes() {
+    assert_eq!(Request::from_pdu(&[0x05, 0x00, 0xAC, 0xFF, 0x00]).unwrap(),
+               Request::WriteSingleCoil { address: 0xAC, value: true });
+    assert_eq!(Request::from_pdu(&[0x06, 0x00, 0x01, 0x00, 0x03]).unwrap(),
+               Request::WriteSingleRegister { address: 1, value: 3 });
+    assert_eq!(Request::from_pdu(&[0x0F, 0x00, 0x13, 0x00, 0x0A, 0x02, 0xCD, 0x01]).unwrap(),
+               Request::WriteMultipleCoils {
+                   address: 0x13,
+                   values: vec![true, false, true, true, false, false, true, true, true, false],
+               });
+    assert_eq!(Request::from_pdu(&[0x16, 0x00, 0x04, 0x00, 0xF2, 0x00, 0x25]).unwrap(),
+               Request::MaskWriteRegister { address: 4, and_mask: 0xF2, or_mask: 0x25 });
+    assert_eq!(Request::from_pdu(&[0x17, 0x00, 0x03, 0x00, 0x06, 0x00, 0x0E, 0x00, 0x03, 0x06, 0x00, 0xFF, 0x00,
+                                   0xFF, 0x00, 0xFF])
+                       .unwrap(),
+               Request::WriteAndReadRegisters {
+                   read_address: 3,
+                   read_count: 6,
+                   write_address: 0x0E,
+                   values: vec![0xFF, 0xFF, 0xFF],
+               });
+}
+
+#[test]
+fn from_pdu_other() {
+    let request = Request::from_pdu(&[0x08, 0x00, 0x00, 0xA5, 0x37]).unwrap();
+    assert_eq!(request, Request::Other { function: 0x08, data: vec![0x00, 0x00, 0xA5, 0x37] });
+    assert_eq!(request.function(), 0x08);
+    assert_eq!(Request::from_pdu(&[0x42]).unwrap(), Request::Other { function: 0x42, data: vec![] });
+}
+
+#[test]
+fn from_pdu_invalid() {
+    match Request::from_pdu(&[]) {
+        Err(Error::IncompleteFrame) => {},
+        other => panic!("expected incomplete frame, got {:?}", other),
+    }
+    match Request::from_pdu(&[0x03, 0x00, 0x6B, 0x00]) {
+        Err(Error::IncompleteFrame) => {},
+        other => panic!("expected incomplete frame, got {:?}", other),
+    }
+    // byte count does not match the number of registers
+    match Request::from_pdu(&[0x10, 0x00, 0x01, 0x00, 0x02, 0x02, 0x00, 0x0A]) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    // byte count announces more data than received
+    match Request::from_pdu(&[0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A]) {
+        Err(Error::IncompleteFrame) => {},
+        other => panic!("expected incomplete frame, got {:?}", other),
+    }
+    match Request::from_pdu(&[0x05, 0x00, 0xAC, 0x12, 0x34]) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+}
+
+#[test]
+fn to_pdu() {
+    assert_eq!(Request::ReadCoils { address: 0x13, count: 0x25 }.to_pdu(), vec![0x01, 0x00, 0x13, 0x00, 0x25]);
+    assert_eq!(Request::WriteSingleCoil { address: 0xAC, value: true }.to_pdu(), vec![0x05, 0x00, 0xAC, 0xFF, 0x00]);
+    assert_eq!(Request::ReportSlaveId.to_pdu(), vec![0x11]);
+    assert_eq!(Request::Other { function: 0x08, data: vec![0x00, 0x00] }.to_pdu(), vec![0x08, 0x00, 0x00]);
+
+    let requests = vec![Request::ReadInputRegisters { address: 8, count: 1 },
+                        Request::WriteSingleRegister { address: 1, value: 3 },
+                        Request::WriteMultipleCoils {
+                            address: 0x13,
+                            values: vec![true, false, true, true, false, false, true, true, true, false],
+                        },
+                        Request::WriteMultipleRegisters { address: 1, values: vec![0x000A, 0x0102] },
+                        Request::MaskWriteRegister { address: 4, and_mask: 0xF2, or_mask: 0x25 },
+                        Request::WriteAndReadRegisters {
+                            read_address: 3,
+                            read_count: 6,
+                            write_address: 0x0E,
+                            values: vec![0xFF, 0xFF, 0xFF],
+                        }];
+    for request in requests {
+        assert_eq!(Request::from_pdu(&request.to_pdu()).unwrap(), request);
+    }
+}
+
+#[test]
+fn from_bytes_rtu() {
+    let query = [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B];
+    let indication = Indication::from_bytes(&query, 1).unwrap();
+    assert_eq!(indication,
+               Indication {
+                   slave: 0x11,
+                   transaction_id: None,
+                   request: Request::WriteSingleRegister { address: 1, value: 3 },
+               });
+}
+
+#[test]
+fn from_bytes_tcp_ignores_trailing_data() {
+    // the whole receive buffer instead of the received length
+    let mut query = vec![0; Modbus::TCP_MAX_ADU_LENGTH];
+    query[..12].copy_from_slice(&[0x12, 0x34, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x00, 0xA5, 0x37]);
+    let indication = Indication::from_bytes(&query, 7).unwrap();
+    assert_eq!(indication.slave, 1);
+    assert_eq!(indication.transaction_id, Some(0x1234));
+    assert_eq!(indication.request, Request::Other { function: 0x08, data: vec![0x00, 0x00, 0xA5, 0x37] });
+}
+
+#[test]
+fn parse_received_request() {
+    let server_thread = thread::spawn(|| {
+        let server = Modbus::new_tcp("127.0.0.1", 1523).unwrap();
+        let server = server.tcp_listen(1).unwrap().tcp_accept().expect("unable to accept TCP socket");
+        let mut query = vec![0; Modbus::MAX_ADU_LENGTH];
+        let len = server.receive(&mut query).unwrap();
+        Indication::parse(&server, &query[..len as usize]).unwrap()
+    });
+    thread::sleep(Duration::from_millis(100));
+
+    let mut client = Modbus::new_tcp("127.0.0.1", 1523).unwrap();
+    client.set_slave(7).unwrap();
+    client.set_response_timeout_duration(Duration::from_millis(100)).unwrap();
+    let client = client.connect().expect("could not connect");
+    // the server does not reply
+    assert!(client.write_registers(0x20, 3, &[1, 2, 3]).is_err());
+
+    let indication = server_thread.join().unwrap();
+    assert_eq!(indication.slave, 7);
+    assert!(indication.transaction_id.is_some());
+    assert_eq!(indication.request, Request::WriteMultipleRegisters { address: 0x20, values: vec![1, 2, 3] });
+}
@@ -0,0 +1,55 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Exception, Request, Response};
+
+
+#[test]
+fn decode_reads() {
+    let request = Request::ReadCoils { address: 0x13, count: 10 };
+    assert_eq!(Response::decode(&request, &[0x01, 0x02, 0xCD, 0x01]).unwrap(),
+               Response::Bits(vec![true, false, true, true, false, false, true, true, true, false]));
+
+    let request = Request::WriteAndReadRegisters { read_address: 3, read_count: 1, write_address: 0, values: vec![1] };
+    assert_eq!(Response::decode(&request, &[0x17, 0x02, 0x00, 0xFE]).unwrap(), Response::Registers(vec![0xFE]));
+
+    assert_eq!(Response::decode(&Request::ReportSlaveId, &[0x11, 0x02, 0x01, 0xFF]).unwrap(),
+               Response::Data(vec![0x01, 0xFF]));
+}
+
+#[test]
+fn decode_writes() {
+    let request = Request::WriteSingleCoil { address: 0xAC, value: true };
+    assert_eq!(Response::decode(&request, &[0x05, 0x00, 0xAC, 0xFF, 0x00]).unwrap(), Response::Written);
+
+    let request = Request::WriteMultipleRegisters { address: 1, values: vec![0x000A, 0x0102] };
+    assert_eq!(Response::decode(&request, &[0x10, 0x00, 0x01, 0x00, 0x02]).unwrap(), Response::Written);
+
+    // wrong number of written registers
+    match Response::decode(&request, &[0x10, 0x00, 0x01, 0x00, 0x01]) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+}
+
+#[test]
+fn decode_invalid() {
+    let request = Request::ReadHoldingRegisters { address: 0, count: 2 };
+    match Response::decode(&request, &[0x83, 0x06]) {
+        Err(Error::Exception(Exception::SlaveDeviceBusy)) => {},
+        other => panic!("expected slave device busy, got {:?}", other),
+    }
+    match Response::decode(&request, &[0x83, 0x42]) {
+        Err(Error::BadException) => {},
+        other => panic!("expected bad exception, got {:?}", other),
+    }
+    // response of another function
+    match Response::decode(&request, &[0x04, 0x04, 0x00, 0x00, 0x00, 0x00]) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    // one register missing
+    match Response::decode(&request, &[0x03, 0x02, 0x00, 0x00]) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+}
@@ -0,0 +1,123 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Error, Exception, Modbus, ModbusClient, ModbusMapping, ModbusTCP, ReconnectingClient, RetryPolicy,
+                   ShutdownHandle, TcpServer};
+use std::cell::Cell;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    let mut policy = RetryPolicy::new();
+    policy.set_max_attempts(max_attempts);
+    policy.set_backoff(Duration::from_millis(10), Duration::from_millis(50));
+    policy
+}
+
+fn start_server(port: i32, mapping: Arc<ModbusMapping>) -> (ShutdownHandle, thread::JoinHandle<()>) {
+    let mut server = TcpServer::new(Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(1).unwrap());
+    let handle = server.shutdown_handle();
+    let server = thread::spawn(move || {
+        let _ = server.serve(&mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+    (handle, server)
+}
+
+#[test]
+fn backoff() {
+    let mut policy = RetryPolicy::new();
+    policy.set_backoff(Duration::from_millis(50), Duration::from_secs(1));
+    policy.set_multiplier(3.0);
+    assert_eq!(policy.backoff(1), Duration::from_millis(50));
+    assert_eq!(policy.backoff(2), Duration::from_millis(150));
+    assert_eq!(policy.backoff(4), Duration::from_secs(1));
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+
+    policy.set_multiplier(0.5);
+    assert_eq!(policy.backoff(3), Duration::from_millis(50));
+    policy.set_max_attempts(0);
+    assert_eq!(policy.get_max_attempts(), 1);
+    assert_eq!(RetryPolicy::never().get_max_attempts(), 1);
+}
+
+#[test]
+fn retry_transient_errors() {
+    let policy = policy(3);
+    let attempts = Cell::new(0);
+    let result = policy.retry(|| {
+                           attempts.set(attempts.get() + 1);
+                           if attempts.get() < 3 { Err(Error::Timeout) } else { Ok(attempts.get()) }
+                       });
+    assert_eq!(result.unwrap(), 3);
+
+    attempts.set(0);
+    let result: Result<(), Error> = policy.retry(|| {
+                                              attempts.set(attempts.get() + 1);
+                                              Err(Error::BadCrc)
+                                          });
+    assert!(matches!(result, Err(Error::BadCrc)));
+    assert_eq!(attempts.get(), 3);
+
+    attempts.set(0);
+    let result: Result<(), Error> = policy.retry(|| {
+                                              attempts.set(attempts.get() + 1);
+                                              Err(Error::Exception(Exception::IllegalDataAddress))
+                                          });
+    assert!(matches!(result, Err(Error::Exception(Exception::IllegalDataAddress))));
+    assert_eq!(attempts.get(), 1);
+}
+
+#[test]
+fn retry_on() {
+    let mut policy = policy(4);
+    policy.set_retry_on(|error| matches!(*error, Error::BadData));
+    let attempts = Cell::new(0);
+    let result: Result<(), Error> = policy.retry(|| {
+                                              attempts.set(attempts.get() + 1);
+                                              if attempts.get() < 2 { Err(Error::BadData) } else { Err(Error::Timeout) }
+                                          });
+    assert!(matches!(result, Err(Error::Timeout)));
+    assert_eq!(attempts.get(), 2);
+}
+
+#[test]
+fn reconnect() {
+    let port = 1550;
+    let mapping = Arc::new(ModbusMapping::new(0, 0, 10, 0).unwrap());
+    mapping.lock().get_registers_mut()[0] = 42;
+
+    let mut modbus = Modbus::new_tcp("127.0.0.1", port).unwrap();
+    modbus.set_response_timeout_duration(Duration::from_millis(200)).unwrap();
+    let client = ReconnectingClient::new(modbus, policy(2));
+    match client.read_registers_vec(0, 1) {
+        Err(Error::IoError(_)) => {},
+        other => panic!("expected connection refused, got {:?}", other),
+    }
+    assert!(!client.is_connected());
+
+    let (shutdown, server) = start_server(port, mapping.clone());
+    assert!(!client.is_connected());
+    assert_eq!(client.read_registers_vec(0, 1).unwrap(), vec![42]);
+    assert!(client.is_connected());
+
+    // the server drops the connection, the client connects to the restarted server
+    shutdown.shutdown();
+    server.join().unwrap();
+    let _server = start_server(port, mapping.clone());
+    client.write_register(1, 7).unwrap();
+    assert_eq!(mapping.lock().get_registers()[1], 7);
+    assert_eq!(client.read_registers_range(0, 2).unwrap(), vec![42, 7]);
+
+    // exceptions are answered over a working link, it is kept
+    assert!(client.read_registers_vec(20, 1).is_err());
+    assert!(client.is_connected());
+
+    client.disconnect();
+    assert!(!client.is_connected());
+    client.connect().unwrap();
+    assert!(client.is_connected());
+    let modbus = client.into_inner();
+    assert_eq!(modbus.get_response_timeout_duration().unwrap(), Duration::from_millis(200));
+}
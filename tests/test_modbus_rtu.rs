@@ -1,6 +1,9 @@
+extern crate libc;
 extern crate libmodbus_rs;
 
-use libmodbus_rs::{Modbus, ModbusRTU, SerialMode};
+use libmodbus_rs::{Modbus, ModbusClient, ModbusRTU, SerialMode, Timeout};
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
 
 
 #[test]
@@ -41,11 +44,56 @@ fn rtu_set_rts() {
     assert_eq!(modbus.rtu_get_rts().unwrap(), libmodbus_rs::RequestToSendMode::RtuRtsUp);
 }
 
+/// Opens a pseudo-terminal, returns the master file descriptor and the path of the slave device
+fn open_pty() -> (i32, String) {
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(master >= 0, "could not open pseudo-terminal");
+        assert_eq!(libc::grantpt(master), 0);
+        assert_eq!(libc::unlockpt(master), 0);
+        let path = CStr::from_ptr(libc::ptsname(master)).to_string_lossy().into_owned();
+        (master, path)
+    }
+}
+
 #[test]
-#[ignore]
 fn rtu_set_custom_rts() {
-    let _modbus = Modbus::new_rtu("/dev/ttyS0", 115200, 'N', 8, 1).unwrap();
-    // function pointer via ffi ???
+    let (master, path) = open_pty();
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    {
+        let mut modbus = Modbus::new_rtu(&path, 115200, 'N', 8, 1).unwrap();
+        let recorder = calls.clone();
+        modbus.rtu_set_custom_rts(Box::new(move |on| recorder.lock().unwrap().push(on))).unwrap();
+        modbus.set_slave(1).unwrap();
+        modbus.set_response_timeout(Timeout { sec: 0, usec: 100_000 }).unwrap();
+        let mut modbus = modbus.connect().expect("could not connect to pseudo-terminal");
+
+        // releases the bus
+        modbus.rtu_set_rts(libmodbus_rs::RequestToSendMode::RtuRtsUp).unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec![false]);
+
+        // nobody answers on the other side, only the direction toggling is of interest
+        assert!(modbus.write_register(0, 1).is_err());
+        assert_eq!(*calls.lock().unwrap(), vec![false, true, false]);
+    }
+
+    // the closure is dropped together with the context
+    assert_eq!(Arc::strong_count(&calls), 1);
+    unsafe { libc::close(master); }
+}
+
+#[test]
+fn rtu_set_custom_rts_panic() {
+    let (master, path) = open_pty();
+    let mut modbus = Modbus::new_rtu(&path, 115200, 'N', 8, 1).unwrap();
+    modbus.rtu_set_custom_rts(Box::new(|_| panic!("GPIO not available"))).unwrap();
+    let mut modbus = modbus.connect().expect("could not connect to pseudo-terminal");
+
+    // the panic does not unwind through libmodbus
+    assert!(modbus.rtu_set_rts(libmodbus_rs::RequestToSendMode::RtuRtsUp).is_ok());
+    drop(modbus);
+    unsafe { libc::close(master); }
 }
 
 #[test]
@@ -1,29 +1,27 @@
 extern crate libmodbus_rs;
 
-use libmodbus_rs::{Modbus, ModbusServer, ModbusTCP};
+use libmodbus_rs::{Error, FunctionCode, Modbus, ModbusClient, ModbusServer, ModbusTCP};
+use std::thread;
+use std::time::{Duration, Instant};
 
 
 #[test]
-#[ignore]
 fn receive() {
     let mut query = vec![0; Modbus::MAX_ADU_LENGTH as usize];
     // create server
-    match Modbus::new_tcp("127.0.0.1", 1502) {
-        Ok(mut server) => {
-            let mut socket = server.tcp_listen(1).expect("could not listen");
-            server.tcp_accept(&mut socket).expect("unable to accept TCP socket");
-        },
+    let listener = match Modbus::new_tcp("127.0.0.1", 1535) {
+        Ok(server) => server.tcp_listen(1).expect("could not listen"),
         Err(err) => panic!("Could not create server: {}", err),
-    }
-
-    // connect client
-    match Modbus::new_tcp("127.0.0.1", 1502) {
-        Ok(client) => {
-            client.connect().expect("could not connect");
-            assert!(client.receive(&mut query).is_ok());
-        },
-        _ => panic!("could not connect"),
-    }
+    };
+
+    // connect client and send a request, there is no need to wait for the reply
+    let client = Modbus::new_tcp("127.0.0.1", 1535).unwrap().connect().expect("could not connect");
+    let mut raw_request = vec![0xFF, FunctionCode::ReadHoldingRegisters as u8, 0x00, 0x01, 0x00, 0x05];
+    let request_len = raw_request.len();
+    client.send_raw_request(&mut raw_request, request_len).expect("could not send request");
+
+    let server = listener.tcp_accept().expect("unable to accept TCP socket");
+    assert_eq!(server.receive(&mut query).unwrap(), 12);
 }
 
 #[test]
@@ -31,3 +29,29 @@ fn receive() {
 fn reply() {
     let _modbus = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
 }
+
+#[test]
+fn receive_indication_timeout() {
+    let port = 1530;
+    let server_thread = thread::spawn(move || {
+        let server = Modbus::new_tcp("127.0.0.1", port).expect("could not create server");
+        let listener = server.tcp_listen(1).expect("could not listen");
+        let mut server = listener.tcp_accept().expect("unable to accept TCP socket");
+        server.set_indication_timeout_duration(Duration::from_millis(100)).expect("could not set indication timeout");
+
+        let mut query = vec![0; Modbus::MAX_ADU_LENGTH];
+        let start = Instant::now();
+        match server.receive(&mut query) {
+            Err(Error::Timeout) => {},
+            other => panic!("expected timeout, got {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    // connect a client that never sends anything
+    let client = Modbus::new_tcp("127.0.0.1", port).expect("could not create client");
+    let _client = client.connect().expect("could not connect");
+
+    server_thread.join().expect("server thread failed");
+}
@@ -1,6 +1,7 @@
+extern crate libc;
 extern crate libmodbus_rs;
 
-use libmodbus_rs::{Modbus, ModbusTCP};
+use libmodbus_rs::{Error, Modbus, ModbusTCP};
 
 
 #[test]
@@ -9,21 +10,60 @@ fn new_tcp() {
 }
 
 #[test]
-#[ignore]
 fn tcp_accept() {
-    let mut server = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
-    match server.tcp_listen(1) {
-        Ok(mut socket) => assert_eq!(server.tcp_accept(&mut socket).unwrap(), 1),
-        _ => panic!("could not listen to socket"),
+    let server = Modbus::new_tcp("127.0.0.1", 1533).unwrap();
+    let listener = server.tcp_listen(1).expect("could not listen to socket");
+    let client = Modbus::new_tcp("127.0.0.1", 1533).unwrap().connect().expect("could not connect");
+
+    let connection = listener.tcp_accept().expect("could not accept connection");
+    assert!(connection.get_socket().unwrap() >= 0);
+    drop(client);
+}
+
+#[test]
+fn tcp_accept_failed() {
+    let server = Modbus::new_tcp("127.0.0.1", 1552).unwrap();
+    let listener = server.tcp_listen(1).expect("could not listen to socket");
+    unsafe { libc::fcntl(listener.socket(), libc::F_SETFL, libc::O_NONBLOCK) };
+
+    // nobody connected yet, the listener is handed back
+    let err = listener.tcp_accept().expect_err("accepted without a client");
+    match *err.error() {
+        Error::IoError(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => {},
+        ref other => panic!("expected EAGAIN, got {:?}", other),
     }
+    let listener = err.into_inner();
+
+    let client = Modbus::new_tcp("127.0.0.1", 1552).unwrap().connect().expect("could not connect");
+    assert!(listener.tcp_accept().is_ok());
+    drop(client);
 }
 
 #[test]
 fn tcp_listen() {
-    let mut server = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
+    let server = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     let client = Modbus::new_tcp("127.0.0.1", 1502).unwrap();
     match server.tcp_listen(1) {
-        Ok(mut _socket) => assert!(client.connect().is_ok()),
+        Ok(listener) => {
+            assert!(listener.socket() >= 0);
+            assert!(client.connect().is_ok())
+        },
         _ => panic!("could not listen to socket"),
     }
 }
+
+#[test]
+fn tcp_listen_close() {
+    let server = Modbus::new_tcp("127.0.0.1", 1534).unwrap();
+    let listener = server.tcp_listen(1).expect("could not listen to socket");
+    let _server = listener.close();
+
+    // nobody listens any more
+    let client = Modbus::new_tcp("127.0.0.1", 1534).unwrap();
+    let err = client.connect().expect_err("connected without a server");
+    assert!(err.error().is_transient());
+
+    // the context is handed back and connects once the server listens again
+    let _listener = Modbus::new_tcp("127.0.0.1", 1534).unwrap().tcp_listen(1).unwrap();
+    assert!(err.into_inner().connect().is_ok());
+}
@@ -11,9 +11,13 @@ fn new_tcp_pi() {
 #[test]
 #[ignore]
 fn tcp_pi_accept() {
-    let mut server = Modbus::new_tcp_pi("::1", "1502").unwrap();
+    let server = Modbus::new_tcp_pi("::1", "1502").unwrap();
+    let client = Modbus::new_tcp_pi("::1", "1502").unwrap();
     match server.tcp_pi_listen(1) {
-        Ok(mut socket) => assert_eq!(server.tcp_pi_accept(&mut socket).unwrap(), 1),
+        Ok(listener) => {
+            let _client = client.connect().expect("could not connect");
+            assert!(listener.tcp_pi_accept().is_ok())
+        },
         _ => panic!("could not listen to socket"),
     }
 }
@@ -21,10 +25,10 @@ fn tcp_pi_accept() {
 #[test]
 #[ignore]
 fn tcp_pi_listen() {
-    let mut server = Modbus::new_tcp_pi("::1", "1502").unwrap();
+    let server = Modbus::new_tcp_pi("::1", "1502").unwrap();
     let client = Modbus::new_tcp_pi("::1", "1502").unwrap();
     match server.tcp_pi_listen(1) {
-        Ok(mut _socket) => assert!(client.connect().is_ok()),
+        Ok(_listener) => assert!(client.connect().is_ok()),
         _ => panic!("could not listen to socket"),
     }
 }
@@ -0,0 +1,111 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::{Exception, Modbus, ModbusClient, ModbusMapping, ModbusTCP, RequestHandler, ShutdownHandle,
+                   TcpServer};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+#[test]
+fn serve_many_clients() {
+    let port = 1526;
+    let mapping = Arc::new(ModbusMapping::new(0, 0, 10, 0).unwrap());
+    let server_mapping = mapping.clone();
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(5).unwrap();
+        let _ = TcpServer::new(listener).serve(&server_mapping);
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    // all clients are connected at the same time
+    let clients: Vec<_> = (0..3)
+        .map(|_| Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect"))
+        .collect();
+    for (i, client) in clients.iter().enumerate() {
+        client.write_register(i as u16, 10 + i as u16).unwrap();
+    }
+    for client in clients.iter().rev() {
+        assert_eq!(client.read_registers_vec(0, 3).unwrap(), vec![10, 11, 12]);
+    }
+    assert_eq!(mapping.lock().get_registers()[..3], [10, 11, 12]);
+
+    // a disconnected client does not disturb the others
+    let mut clients = clients.into_iter();
+    drop(clients.next());
+    let client = clients.next().unwrap();
+    client.write_register(5, 55).unwrap();
+    assert_eq!(client.read_registers_vec(5, 1).unwrap(), vec![55]);
+
+    // new clients are accepted while others are connected
+    let late = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    assert_eq!(late.read_registers_vec(5, 1).unwrap(), vec![55]);
+}
+
+struct Echo;
+
+impl RequestHandler for Echo {
+    fn read_holding_registers(&mut self, address: u16, count: u16) -> Result<Vec<u16>, Exception> {
+        Ok((address..address + count).collect())
+    }
+}
+
+#[test]
+fn serve_with_handler() {
+    let port = 1527;
+    thread::spawn(move || {
+        let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(5).unwrap();
+        let _ = TcpServer::new(listener).serve_with(&mut Echo);
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let first = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    let second = Modbus::new_tcp("127.0.0.1", port).unwrap().connect().expect("could not connect");
+    assert_eq!(first.read_registers_vec(3, 2).unwrap(), vec![3, 4]);
+    assert_eq!(second.read_registers_vec(7, 1).unwrap(), vec![7]);
+    assert_eq!(first.read_registers_vec(1, 1).unwrap(), vec![1]);
+}
+
+#[test]
+fn shutdown() {
+    let port = 1528;
+    let listener = Modbus::new_tcp("127.0.0.1", port).unwrap().tcp_listen(5).unwrap();
+    let mut server = TcpServer::new(listener);
+    let handle = server.shutdown_handle();
+    let server_thread = thread::spawn(move || {
+        let mapping = ModbusMapping::new(0, 0, 10, 0).unwrap();
+        let result = server.serve(&mapping);
+        let connections = server.connections();
+        (result, connections, server)
+    });
+
+    let mut client = Modbus::new_tcp("127.0.0.1", port).unwrap();
+    client.set_response_timeout_duration(Duration::from_millis(200)).unwrap();
+    let client = client.connect().expect("could not connect");
+    assert_eq!(client.read_registers_vec(0, 1).unwrap(), vec![0]);
+
+    let start = Instant::now();
+    handle.clone().shutdown();
+    let (result, connections, server) = server_thread.join().unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(result.is_ok());
+    assert_eq!(connections, 0);
+    // the server stopped listening before it is closed
+    assert!(Modbus::new_tcp("127.0.0.1", port).unwrap().connect().is_err());
+    let modbus = server.close();
+    // the server set the indication timeout to check the handle
+    assert_eq!(modbus.get_indication_timeout_duration().unwrap(), Duration::from_millis(100));
+
+    // client and listening sockets are closed
+    assert!(client.read_registers_vec(0, 1).is_err());
+    assert!(Modbus::new_tcp("127.0.0.1", port).unwrap().connect().is_err());
+}
+
+#[test]
+fn shutdown_handle() {
+    let handle = ShutdownHandle::new();
+    let clone = handle.clone();
+    assert!(!clone.is_shutdown());
+    handle.shutdown();
+    assert!(clone.is_shutdown());
+}
@@ -0,0 +1,146 @@
+extern crate libmodbus_rs;
+
+use libmodbus_rs::prelude::*;
+use libmodbus_rs::ModbusMapping;
+
+
+const ORDERS: [(ByteOrder, WordOrder); 4] = [(ByteOrder::BigEndian, WordOrder::BigEndian),
+                                             (ByteOrder::LittleEndian, WordOrder::BigEndian),
+                                             (ByteOrder::BigEndian, WordOrder::LittleEndian),
+                                             (ByteOrder::LittleEndian, WordOrder::LittleEndian)];
+
+fn round_trip<T: RegisterValue + Copy + PartialEq + std::fmt::Debug>(value: T) {
+    for &(byte_order, word_order) in &ORDERS {
+        let mut dest = vec![0u16; T::REGISTERS];
+        set_value(value, &mut dest, byte_order, word_order).unwrap();
+        assert_eq!(get_value::<T>(&dest, byte_order, word_order).unwrap(), value);
+    }
+}
+
+#[test]
+fn round_trip_all_types() {
+    round_trip(0xABCDu16);
+    round_trip(-2i16);
+    round_trip(0x0102_0304u32);
+    round_trip(-123_456i32);
+    round_trip(0x0102_0304_0506_0708u64);
+    round_trip(i64::MIN);
+    round_trip(-1.5f32);
+    round_trip(std::f64::consts::PI);
+}
+
+#[test]
+fn u64_orders() {
+    let value = 0x0102_0304_0506_0708u64;
+    let expected = [[0x0102, 0x0304, 0x0506, 0x0708],
+                    [0x0201, 0x0403, 0x0605, 0x0807],
+                    [0x0708, 0x0506, 0x0304, 0x0102],
+                    [0x0807, 0x0605, 0x0403, 0x0201]];
+    for (&(byte_order, word_order), expected) in ORDERS.iter().zip(&expected) {
+        let mut dest = [0u16; 4];
+        set_value(value, &mut dest, byte_order, word_order).unwrap();
+        assert_eq!(&dest, expected);
+    }
+}
+
+#[test]
+fn f64_big_endian() {
+    let registers = [0x4009, 0x21FB, 0x5444, 0x2D18];
+    assert_eq!(get_value::<f64>(&registers, ByteOrder::BigEndian, WordOrder::BigEndian).unwrap(),
+               std::f64::consts::PI);
+}
+
+#[test]
+fn f32_matches_libmodbus() {
+    let mut dest = [0u16; 2];
+    set_float_cdab(123456.0, &mut dest);
+    assert_eq!(get_value::<f32>(&dest, ByteOrder::LittleEndian, WordOrder::BigEndian).unwrap(), 123456.0);
+    set_float_badc(123456.0, &mut dest);
+    assert_eq!(get_value::<f32>(&dest, ByteOrder::BigEndian, WordOrder::LittleEndian).unwrap(), 123456.0);
+}
+
+#[test]
+fn mapping_registers() {
+    let modbus_mapping = ModbusMapping::new(0, 0, 0, 4).unwrap();
+    let mut mapping = modbus_mapping.lock();
+    set_value(-7i32, &mut mapping.get_input_registers_mut()[1..], ByteOrder::BigEndian, WordOrder::BigEndian).unwrap();
+    assert_eq!(mapping.get_input_registers(), [0, 0xFFFF, 0xFFF9, 0]);
+}
+
+#[test]
+fn buffer_too_small() {
+    let mut dest = [0xAAAAu16; 3];
+    match set_value(1u64, &mut dest, ByteOrder::BigEndian, WordOrder::BigEndian) {
+        Err(Error::BufferTooSmall { required: 4, available: 3 }) => {},
+        other => panic!("expected buffer too small, got {:?}", other),
+    }
+    assert_eq!(dest, [0xAAAA; 3]);
+    match get_value::<f32>(&[1], ByteOrder::BigEndian, WordOrder::BigEndian) {
+        Err(Error::BufferTooSmall { required: 2, available: 1 }) => {},
+        other => panic!("expected buffer too small, got {:?}", other),
+    }
+}
+
+#[test]
+fn string_round_trip() {
+    for &byte_order in &[ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+        for &padding in b"\0 " {
+            let mut dest = [0xFFFFu16; 5];
+            set_string("Zähler 7", &mut dest, byte_order, padding).unwrap();
+            assert_eq!(get_string(&dest, byte_order).unwrap(), "Zähler 7");
+        }
+    }
+}
+
+#[test]
+fn string_errors() {
+    let mut dest = [0u16; 2];
+    match set_string("SA123", &mut dest, ByteOrder::BigEndian, b'\0') {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+    assert_eq!(dest, [0, 0]);
+    match get_string(&[0xC328], ByteOrder::BigEndian) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+}
+
+#[test]
+fn bcd() {
+    let mut dest = [0u16; 4];
+    set_bcd(1_234_567_890_123_456, &mut dest, WordOrder::LittleEndian).unwrap();
+    assert_eq!(dest, [0x3456, 0x9012, 0x5678, 0x1234]);
+    assert_eq!(get_bcd(&dest, WordOrder::LittleEndian).unwrap(), 1_234_567_890_123_456);
+    assert_eq!(get_bcd(&[], WordOrder::BigEndian).unwrap(), 0);
+
+    match set_bcd(10_000, &mut dest[..1], WordOrder::BigEndian) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+    match get_bcd(&[0x12A4], WordOrder::BigEndian) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    match get_bcd(&[0; 5], WordOrder::BigEndian) {
+        Err(Error::TooManyData) => {},
+        other => panic!("expected too many data, got {:?}", other),
+    }
+}
+
+#[test]
+fn flags() {
+    let flags = [Flag::new("running", 0), Flag::new("fault", 3), Flag::new("invalid", 16)];
+    assert_eq!(get_flags(0xFFFF, &flags), vec!["running", "fault"]);
+    assert!(get_flags(0x0000, &flags).is_empty());
+    assert_eq!(set_flags(&[], &flags).unwrap(), 0);
+    assert_eq!(set_flags(&["fault"], &flags).unwrap(), 0x0008);
+    match set_flags(&["invalid"], &flags) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+    match set_flags(&["unknown"], &flags) {
+        Err(Error::BadData) => {},
+        other => panic!("expected bad data, got {:?}", other),
+    }
+}